@@ -0,0 +1,322 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use crate::db::calendar::CalendarEvent;
+
+/// Joins folded lines (a line starting with a space or tab continues the
+/// previous one) and drops the line endings.
+fn unfold(source: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in source.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// The `TZID` parameter of a property, e.g. `DTSTART;TZID=Europe/Berlin`.
+fn tzid(name_and_params: &str) -> Option<&str> {
+    name_and_params.split(';').skip(1)
+        .find_map(|param| param.strip_prefix("TZID=").or_else(|| param.strip_prefix("tzid=")))
+        .map(|tzid| tzid.trim_matches('"'))
+}
+
+/// Parses `20240501`, `20240501T090000` or `20240501T090000Z`. UTC times and
+/// times in a TZID chrono-tz knows are converted to local time; other times
+/// are floating and kept as written.
+fn parse_date_time(value: &str, tzid: Option<&str>) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok()?.and_hms_opt(0, 0, 0);
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&time).with_timezone(&Local).naive_local());
+    }
+    let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    // 落在夏令时跳过的时间里时，按 RFC 5545 顺延一小时
+    match tzid.and_then(|tzid| tzid.trim_start_matches('/').parse::<Tz>().ok()) {
+        Some(tz) => tz.from_local_datetime(&time).earliest()
+            .or_else(|| tz.from_local_datetime(&(time + chrono::Duration::hours(1))).earliest())
+            .map(|time| time.with_timezone(&Local).naive_local()),
+        None => Some(time),
+    }
+}
+
+/// Parses simple `DURATION` values such as `PT1H30M` or `P1D`.
+fn parse_duration(value: &str) -> Option<chrono::Duration> {
+    let value = value.trim().strip_prefix('P')?;
+    let mut total = chrono::Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total += match (unit, in_time) {
+                    ('W', false) => chrono::Duration::weeks(n),
+                    ('D', false) => chrono::Duration::days(n),
+                    ('H', true) => chrono::Duration::hours(n),
+                    ('M', true) => chrono::Duration::minutes(n),
+                    ('S', true) => chrono::Duration::seconds(n),
+                    _ => return None,
+                };
+            },
+        }
+    }
+    Some(total)
+}
+
+#[derive(Default)]
+struct PendingEvent {
+    title: String,
+    description: String,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+    duration: Option<chrono::Duration>,
+    rrule: Option<String>,
+    exceptions: Vec<NaiveDate>,
+    reminder: Option<i64>,
+}
+
+/// Reads the `VEVENT`s of an iCalendar file. Events without a valid
+/// `DTSTART` are skipped.
+pub fn parse(source: &str) -> Vec<CalendarEvent> {
+    let mut events = Vec::new();
+    let mut current: Option<PendingEvent> = None;
+    // VALARM 等嵌套组件的属性不属于事件本身，只读取提醒时间
+    let mut nested = 0;
+    let mut in_alarm = false;
+
+    for line in unfold(source) {
+        let Some((name_and_params, value)) = line.split_once(':') else { continue };
+        let name = name_and_params.split(';').next().unwrap_or("").to_ascii_uppercase();
+
+        match (name.as_str(), value.trim()) {
+            ("BEGIN", "VEVENT") => current = Some(PendingEvent::default()),
+            ("BEGIN", component) if current.is_some() => {
+                nested += 1;
+                in_alarm = nested == 1 && component == "VALARM";
+            },
+            ("END", component) if current.is_some() && component != "VEVENT" => {
+                nested -= 1;
+                in_alarm = false;
+            },
+            ("TRIGGER", trigger) if in_alarm => {
+                let Some(pending) = current.as_mut() else { continue };
+                // 只支持相对开始时间的提醒，比如 -PT15M
+                if let Some(before) = trigger.strip_prefix('-').and_then(parse_duration) {
+                    pending.reminder.get_or_insert(before.num_minutes());
+                }
+            },
+            ("END", "VEVENT") => {
+                let Some(pending) = current.take() else { continue };
+                let Some(start) = pending.start else { continue };
+                let end = pending.end.or_else(|| pending.duration.map(|duration| start + duration));
+                events.push(CalendarEvent {
+                    id: 0,
+                    title: pending.title,
+                    description: pending.description,
+                    start,
+                    end,
+                    rrule: pending.rrule,
+                    exceptions: pending.exceptions,
+                    reminder: pending.reminder,
+                });
+            },
+            _ => {
+                let Some(pending) = current.as_mut().filter(|_| nested == 0) else { continue };
+                match name.as_str() {
+                    "SUMMARY" => pending.title = unescape(value),
+                    "DESCRIPTION" => pending.description = unescape(value),
+                    "DTSTART" => pending.start = parse_date_time(value, tzid(name_and_params)),
+                    "DTEND" => pending.end = parse_date_time(value, tzid(name_and_params)),
+                    "DURATION" => pending.duration = parse_duration(value),
+                    "RRULE" => pending.rrule = Some(value.trim().to_string()),
+                    "EXDATE" => pending.exceptions.extend(value.split(',').filter_map(|value| parse_date_time(value, tzid(name_and_params))).map(|time| time.date())),
+                    _ => {}
+                }
+            },
+        }
+    }
+    events
+}
+
+/// Folds a content line to 75 octets as required by RFC 5545.
+fn push_line(out: &mut String, line: &str) {
+    let mut start = 0;
+    let mut limit = 75;
+    while line.len() - start > limit {
+        let mut end = start + limit;
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        out.push_str(&line[start..end]);
+        out.push_str("\r\n ");
+        start = end;
+        // 续行开头的空格也算一个字节
+        limit = 74;
+    }
+    out.push_str(&line[start..]);
+    out.push_str("\r\n");
+}
+
+/// Writes events as an iCalendar file with floating (local) times.
+pub fn export(events: &[CalendarEvent]) -> String {
+    const FORMAT: &str = "%Y%m%dT%H%M%S";
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//nodian//calendar//EN");
+    for event in events {
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:{}-{}@nodian", event.id, event.start.format(FORMAT)));
+        push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut out, &format!("DTSTART:{}", event.start.format(FORMAT)));
+        if let Some(end) = event.end {
+            push_line(&mut out, &format!("DTEND:{}", end.format(FORMAT)));
+        }
+        push_line(&mut out, &format!("SUMMARY:{}", escape(&event.title)));
+        if !event.description.is_empty() {
+            push_line(&mut out, &format!("DESCRIPTION:{}", escape(&event.description)));
+        }
+        if let Some(rrule) = &event.rrule {
+            push_line(&mut out, &format!("RRULE:{}", rrule));
+        }
+        if !event.exceptions.is_empty() {
+            // EXDATE 的值类型要和 DTSTART 一致，否则日历程序会忽略
+            let times = event.exceptions.iter()
+                .map(|date| date.and_time(event.start.time()).format(FORMAT).to_string())
+                .collect::<Vec<_>>();
+            push_line(&mut out, &format!("EXDATE:{}", times.join(",")));
+        }
+        if let Some(minutes) = event.reminder {
+            push_line(&mut out, "BEGIN:VALARM");
+            push_line(&mut out, "ACTION:DISPLAY");
+            push_line(&mut out, &format!("DESCRIPTION:{}", escape(&event.title)));
+            push_line(&mut out, &format!("TRIGGER:-PT{}M", minutes));
+            push_line(&mut out, "END:VALARM");
+        }
+        push_line(&mut out, "END:VEVENT");
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_sample_file() {
+        let events = parse(include_str!("../../tests/data/sample.ics"));
+        assert_eq!(events.len(), 4);
+
+        // TZID 的时间换算成本地时间
+        let berlin = |time| Tz::Europe__Berlin.from_local_datetime(&time).unwrap().with_timezone(&Local).naive_local();
+        assert_eq!(events[0].title, "Team meeting");
+        assert_eq!(events[0].start, berlin(at(2024, 5, 1, 9, 0)));
+        assert_eq!(events[0].end, Some(berlin(at(2024, 5, 1, 10, 30))));
+        assert_eq!(events[0].description, "Agenda:\n1. Roadmap, budget; misc");
+        assert_eq!(events[0].rrule, None);
+
+        // 全天事件
+        assert_eq!(events[1].title, "Holiday");
+        assert_eq!(events[1].start, at(2024, 5, 6, 0, 0));
+        assert_eq!(events[1].end, Some(at(2024, 5, 7, 0, 0)));
+
+        // 折叠行和 DURATION
+        assert_eq!(events[2].title, "A very long summary that is folded across several content lines in the file");
+        assert_eq!(events[2].end, Some(at(2024, 5, 2, 15, 45)));
+        assert_eq!(events[2].description, "");
+        assert_eq!(events[2].reminder, Some(15));
+
+        assert_eq!(events[3].title, "Weekly sync");
+        assert_eq!(events[3].rrule.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10"));
+        assert_eq!(events[3].exceptions, vec![NaiveDate::from_ymd_opt(2024, 5, 13).unwrap()]);
+    }
+
+    #[test]
+    fn converts_utc_times_to_local() {
+        let events = parse(include_str!("../../tests/data/utc.ics"));
+        assert_eq!(events.len(), 1);
+        let expected = Utc.from_utc_datetime(&at(2024, 1, 15, 12, 0)).with_timezone(&Local).naive_local();
+        assert_eq!(events[0].start, expected);
+        assert_eq!(events[0].end, None);
+    }
+
+    #[test]
+    fn round_trips_sample_files() {
+        for source in [include_str!("../../tests/data/sample.ics"), include_str!("../../tests/data/utc.ics")] {
+            let events = parse(source);
+            let exported = export(&events);
+            assert!(exported.lines().all(|line| line.len() <= 75));
+            assert_eq!(parse(&exported), events);
+        }
+    }
+
+    #[test]
+    fn round_trips_special_characters() {
+        let event = CalendarEvent {
+            id: 7,
+            title: "Lunch; with, friends \\ 朋友们一起吃午饭，然后去公园散步，再去看电影，最后回家休息".to_string(),
+            description: "line 1\nline 2".to_string(),
+            start: at(2024, 12, 31, 23, 0),
+            end: Some(at(2025, 1, 1, 1, 0)),
+            rrule: Some("FREQ=YEARLY".to_string()),
+            exceptions: vec![NaiveDate::from_ymd_opt(2025, 12, 31).unwrap()],
+            reminder: Some(90),
+        };
+        let exported = export(std::slice::from_ref(&event));
+        assert!(exported.contains("\r\nEXDATE:20251231T230000\r\n"));
+        assert_eq!(parse(&exported), vec![CalendarEvent { id: 0, ..event }]);
+    }
+
+    #[test]
+    fn reads_times_in_other_zones() {
+        let source = "BEGIN:VEVENT\nDTSTART;TZID=America/New_York:20240110T090000\nDTEND;TZID=\"America/New_York\":20240110T100000\n\
+            EXDATE;TZID=America/New_York:20240117T090000\nDTSTAMP:20240101T000000Z\nSUMMARY:Standup\nEND:VEVENT\n\
+            BEGIN:VEVENT\nDTSTART;TZID=Custom Zone:20240110T090000\nSUMMARY:Unknown zone\nEND:VEVENT\n";
+        let events = parse(source);
+        let utc = |time| Utc.from_utc_datetime(&time).with_timezone(&Local).naive_local();
+        assert_eq!(events[0].start, utc(at(2024, 1, 10, 14, 0)));
+        assert_eq!(events[0].end, Some(utc(at(2024, 1, 10, 15, 0))));
+        assert_eq!(events[0].exceptions, vec![utc(at(2024, 1, 17, 14, 0)).date()]);
+        assert_eq!(events[1].start, at(2024, 1, 10, 9, 0));
+    }
+}
@@ -0,0 +1,304 @@
+use chrono::{Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use crate::db::calendar::CalendarEvent;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    pub fn name(self) -> &'static str {
+        match self {
+            Frequency::Daily => "daily",
+            Frequency::Weekly => "weekly",
+            Frequency::Monthly => "monthly",
+            Frequency::Yearly => "yearly",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Frequency> {
+        match name.to_ascii_lowercase().as_str() {
+            "daily" => Some(Frequency::Daily),
+            "weekly" => Some(Frequency::Weekly),
+            "monthly" => Some(Frequency::Monthly),
+            "yearly" => Some(Frequency::Yearly),
+            _ => None,
+        }
+    }
+}
+
+/// The subset of an iCalendar `RRULE` the calendar understands:
+/// `FREQ`, `INTERVAL`, `COUNT`, `UNTIL` and, for weekly rules, `BYDAY`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDateTime>,
+    pub by_day: Vec<Weekday>,
+}
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+/// 防止写错的规则（比如 UNTIL 很远的每日事件）让展开跑太久
+const MAX_STEPS: u32 = 100_000;
+
+impl Recurrence {
+    pub fn new(frequency: Frequency) -> Self {
+        Recurrence { frequency, interval: 1, count: None, until: None, by_day: Vec::new() }
+    }
+
+    pub fn parse(rrule: &str) -> Result<Recurrence, String> {
+        let mut frequency = None;
+        let mut rule = Recurrence::new(Frequency::Daily);
+        for part in rrule.trim().trim_start_matches("RRULE:").split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| format!("无效的规则: {}", part))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => frequency = Some(Frequency::from_name(value).ok_or_else(|| format!("不支持的频率: {}", value))?),
+                "INTERVAL" => rule.interval = value.parse().ok().filter(|n| *n > 0).ok_or_else(|| format!("无效的间隔: {}", value))?,
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| format!("无效的次数: {}", value))?),
+                "UNTIL" => rule.until = Some(parse_until(value).ok_or_else(|| format!("无效的结束日期: {}", value))?),
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        let weekday = WEEKDAYS.iter().find(|(name, _)| day.eq_ignore_ascii_case(name))
+                            .ok_or_else(|| format!("不支持的 BYDAY: {}", day))?;
+                        rule.by_day.push(weekday.1);
+                    }
+                },
+                // WKST 等其它部分不影响展开结果
+                _ => {},
+            }
+        }
+        rule.frequency = frequency.ok_or("缺少 FREQ")?;
+        Ok(rule)
+    }
+
+    pub fn to_rrule(&self) -> String {
+        let mut rrule = format!("FREQ={}", self.frequency.name().to_ascii_uppercase());
+        if self.interval > 1 {
+            rrule.push_str(&format!(";INTERVAL={}", self.interval));
+        }
+        if let Some(count) = self.count {
+            rrule.push_str(&format!(";COUNT={}", count));
+        }
+        if let Some(until) = self.until {
+            rrule.push_str(&format!(";UNTIL={}", until.format("%Y%m%dT%H%M%S")));
+        }
+        if !self.by_day.is_empty() {
+            let days = self.by_day.iter()
+                .filter_map(|day| WEEKDAYS.iter().find(|(_, weekday)| weekday == day).map(|(name, _)| *name))
+                .collect::<Vec<_>>();
+            rrule.push_str(&format!(";BYDAY={}", days.join(",")));
+        }
+        rrule
+    }
+
+    /// Start times of the occurrences on days in `from..=to`, skipping
+    /// `exceptions`. Excluded occurrences still count towards `COUNT`.
+    pub fn occurrences(&self, start: NaiveDateTime, from: NaiveDate, to: NaiveDate, exceptions: &[NaiveDate]) -> Vec<NaiveDateTime> {
+        let mut result = Vec::new();
+        let mut seen = 0;
+        for step in 0..MAX_STEPS {
+            // 超出 chrono 能表示的日期时停止展开
+            let Some(candidates) = self.candidates(start, step) else { break };
+            let Some(first) = candidates.first() else {
+                // 该月没有这一天（比如 31 号），RFC 5545 规定直接跳过
+                continue;
+            };
+            if first.date() > to {
+                break;
+            }
+            for candidate in candidates {
+                if candidate < start {
+                    continue;
+                }
+                if self.until.is_some_and(|until| candidate > until) || self.count.is_some_and(|count| seen >= count) {
+                    return result;
+                }
+                seen += 1;
+                let date = candidate.date();
+                if date >= from && date <= to && !exceptions.contains(&date) {
+                    result.push(candidate);
+                }
+            }
+        }
+        result
+    }
+
+    /// Occurrences produced by the `step`-th interval of the rule, in order,
+    /// or `None` once they are past the dates chrono can represent.
+    fn candidates(&self, start: NaiveDateTime, step: u32) -> Option<Vec<NaiveDateTime>> {
+        let n = step.checked_mul(self.interval)?;
+        let time = start.time();
+        match self.frequency {
+            Frequency::Daily => Some(vec![start.date().checked_add_days(Days::new(n as u64))?.and_time(time)]),
+            Frequency::Weekly => {
+                let week = start.date()
+                    .checked_sub_days(Days::new(start.weekday().num_days_from_monday() as u64))?
+                    .checked_add_days(Days::new(7 * n as u64))?;
+                let mut days = if self.by_day.is_empty() { vec![start.weekday()] } else { self.by_day.clone() };
+                days.sort_by_key(|day| day.num_days_from_monday());
+                days.dedup();
+                days.iter()
+                    .map(|day| week.checked_add_days(Days::new(day.num_days_from_monday() as u64)).map(|date| date.and_time(time)))
+                    .collect()
+            },
+            Frequency::Monthly | Frequency::Yearly => {
+                let months = if self.frequency == Frequency::Monthly { n } else { n.checked_mul(12)? };
+                let first_of_month = start.date().with_day(1)?.checked_add_months(Months::new(months))?;
+                Some(first_of_month.with_day(start.day()).map(|date| date.and_time(time)).into_iter().collect())
+            },
+        }
+    }
+}
+
+/// Like DTSTART, a UTC `UNTIL` is converted to local time.
+fn parse_until(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if value.len() == 8 {
+        // 只有日期时包含当天全天
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok()?.and_hms_opt(23, 59, 59);
+    }
+    match value.strip_suffix('Z') {
+        Some(utc) => {
+            let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            Some(Utc.from_utc_datetime(&time).with_timezone(&Local).naive_local())
+        },
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok(),
+    }
+}
+
+/// A single instance of an event inside the shown range. For recurring
+/// events `start`/`end` differ from the stored event's.
+#[derive(Clone, Debug)]
+pub struct Occurrence {
+    pub event: CalendarEvent,
+    pub start: NaiveDateTime,
+    pub end: Option<NaiveDateTime>,
+}
+
+impl Occurrence {
+    /// When the reminder should fire, if the event has one.
+    pub fn reminder_at(&self) -> Option<NaiveDateTime> {
+        let before = chrono::Duration::try_minutes(self.event.reminder?)?;
+        self.start.checked_sub_signed(before)
+    }
+}
+
+/// Expands events into their occurrences on days in `from..=to`, ordered by
+/// start time. Events with a rule the calendar can't read show up once.
+pub fn expand(events: &[CalendarEvent], from: NaiveDate, to: NaiveDate) -> Vec<Occurrence> {
+    let mut occurrences = Vec::new();
+    for event in events {
+        let rule = match event.rrule.as_deref().map(Recurrence::parse) {
+            Some(Ok(rule)) => Some(rule),
+            Some(Err(e)) => {
+                eprintln!("Ignoring recurrence of event {}: {}", event.id, e);
+                None
+            },
+            None => None,
+        };
+        let starts = match rule {
+            Some(rule) => rule.occurrences(event.start, from, to, &event.exceptions),
+            None if event.start.date() >= from && event.start.date() <= to => vec![event.start],
+            None => Vec::new(),
+        };
+        for start in starts {
+            occurrences.push(Occurrence {
+                event: event.clone(),
+                start,
+                end: event.end.map(|end| start + (end - event.start)),
+            });
+        }
+    }
+    occurrences.sort_by_key(|occurrence| occurrence.start);
+    occurrences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, min, 0).unwrap()
+    }
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn occurrences(rrule: &str, start: NaiveDateTime, exceptions: &[NaiveDate]) -> Vec<NaiveDateTime> {
+        Recurrence::parse(rrule).unwrap().occurrences(start, day(2024, 1, 1), day(2024, 12, 31), exceptions)
+    }
+
+    #[test]
+    fn stops_after_count() {
+        let start = at(2024, 5, 1, 9, 0);
+        assert_eq!(occurrences("FREQ=DAILY;INTERVAL=2;COUNT=3", start, &[]), vec![
+            at(2024, 5, 1, 9, 0),
+            at(2024, 5, 3, 9, 0),
+            at(2024, 5, 5, 9, 0),
+        ]);
+    }
+
+    #[test]
+    fn stops_after_until() {
+        // 只有日期的 UNTIL 包含当天
+        let start = at(2024, 1, 31, 18, 0);
+        assert_eq!(occurrences("FREQ=MONTHLY;UNTIL=20240531", start, &[]), vec![
+            at(2024, 1, 31, 18, 0),
+            at(2024, 3, 31, 18, 0),
+            at(2024, 5, 31, 18, 0),
+        ]);
+        assert_eq!(occurrences("FREQ=YEARLY;UNTIL=20240531T170000", at(2023, 5, 31, 18, 0), &[]), vec![]);
+
+        // UTC 的 UNTIL 和 DTSTART 一样换算成本地时间
+        let local = |time| Utc.from_utc_datetime(&time).with_timezone(&Local).naive_local();
+        let start = local(at(2024, 5, 1, 9, 0));
+        let rule = Recurrence::parse("FREQ=DAILY;UNTIL=20240503T090000Z").unwrap();
+        assert_eq!(rule.occurrences(start, day(2024, 1, 1), day(2024, 12, 31), &[]).last(), Some(&local(at(2024, 5, 3, 9, 0))));
+    }
+
+    #[test]
+    fn expands_weekdays() {
+        // 2024-05-01 是星期三，同一周的星期一在开始之前
+        let start = at(2024, 5, 1, 9, 0);
+        assert_eq!(occurrences("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4", start, &[]), vec![
+            at(2024, 5, 1, 9, 0),
+            at(2024, 5, 6, 9, 0),
+            at(2024, 5, 8, 9, 0),
+            at(2024, 5, 13, 9, 0),
+        ]);
+    }
+
+    #[test]
+    fn skips_exceptions() {
+        // 被排除的一次仍然计入 COUNT
+        let start = at(2024, 5, 1, 9, 0);
+        assert_eq!(occurrences("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4", start, &[day(2024, 5, 6)]), vec![
+            at(2024, 5, 1, 9, 0),
+            at(2024, 5, 8, 9, 0),
+            at(2024, 5, 13, 9, 0),
+        ]);
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_calendar() {
+        let rule = Recurrence::parse("FREQ=YEARLY;INTERVAL=4000000000").unwrap();
+        let start = at(2024, 5, 1, 9, 0);
+        assert_eq!(rule.occurrences(start, day(2024, 1, 1), NaiveDate::MAX, &[]), vec![start]);
+        let rule = Recurrence::parse("FREQ=WEEKLY;INTERVAL=1000000").unwrap();
+        assert_eq!(rule.occurrences(start, day(2024, 1, 1), NaiveDate::MAX, &[]).len(), 14);
+    }
+}
@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::Config;
+use crate::export::{self, pdf::PdfOptions, site, Exporter};
+use crate::vault::links::LinkIndex;
+
+const USAGE: &str = "用法: nodian publish [--vault <笔记库>] [--out <输出目录>]
+      nodian export <html|pdf|docx|epub> <笔记或文件夹>... [--vault <笔记库>] [--out <输出目录>]";
+
+/// Runs a headless command given on the command line. Returns `None` when
+/// there is none and the window should open.
+pub fn run(args: &[String]) -> Option<Result<(), String>> {
+    let (command, options) = args.split_first()?;
+    Some(match command.as_str() {
+        "publish" => publish(options),
+        "export" => export_notes(options),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        },
+        other => Err(format!("未知命令 {}\n{}", other, USAGE)),
+    })
+}
+
+/// `--name value` pairs, and the other arguments in order.
+type Arguments = (Vec<(String, String)>, Vec<String>);
+
+fn options(args: &[String], names: &[&str]) -> Result<Arguments, String> {
+    let mut values = Vec::new();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let name = arg.trim_start_matches("--");
+        if !arg.starts_with("--") {
+            positional.push(arg.clone());
+            continue;
+        }
+        if !names.contains(&name) {
+            return Err(format!("未知参数 {}\n{}", arg, USAGE));
+        }
+        let value = args.next().ok_or_else(|| format!("{} 缺少值\n{}", arg, USAGE))?;
+        values.push((name.to_string(), value.clone()));
+    }
+    Ok((values, positional))
+}
+
+fn publish(args: &[String]) -> Result<(), String> {
+    let mut root = PathBuf::from("nodian");
+    let mut out_dir = None;
+    let (values, positional) = options(args, &["vault", "out"])?;
+    if let Some(arg) = positional.first() {
+        return Err(format!("未知参数 {}\n{}", arg, USAGE));
+    }
+    for (name, value) in values {
+        match name.as_str() {
+            "vault" => root = PathBuf::from(value),
+            _ => out_dir = Some(PathBuf::from(value)),
+        }
+    }
+    if !root.is_dir() {
+        return Err(format!("找不到笔记库 {}", root.display()));
+    }
+    let config = Config::load(&root);
+    let out_dir = out_dir.unwrap_or_else(|| config.site_dir(&root));
+    let report = site::publish(&root, &config, &out_dir).map_err(|e| format!("发布失败: {}", e))?;
+    println!(
+        "已生成 {} 个页面，复制 {} 个附件，跳过 {} 篇不发布的笔记: {}",
+        report.pages, report.assets, report.skipped, out_dir.display()
+    );
+    Ok(())
+}
+
+/// Exports notes in the order given; folders expand to the notes in them.
+/// Word and EPUB put everything into one file with a chapter per note.
+fn export_notes(args: &[String]) -> Result<(), String> {
+    let mut root = PathBuf::from("nodian");
+    let mut out_dir = None;
+    let (values, positional) = options(args, &["vault", "out"])?;
+    for (name, value) in values {
+        match name.as_str() {
+            "vault" => root = PathBuf::from(value),
+            _ => out_dir = Some(PathBuf::from(value)),
+        }
+    }
+    let Some((format, targets)) = positional.split_first() else {
+        return Err(format!("缺少导出格式\n{}", USAGE));
+    };
+    if !root.is_dir() {
+        return Err(format!("找不到笔记库 {}", root.display()));
+    }
+    // 路径相对于笔记库，也可以是笔记库里的绝对路径
+    let targets = targets.iter().map(|target| {
+        let path = root.join(target);
+        if path.exists() { Ok(path) } else { Err(format!("找不到 {}", target)) }
+    }).collect::<Result<Vec<_>, _>>()?;
+    let mut notes = Vec::new();
+    for target in &targets {
+        if target.is_dir() {
+            notes.extend(export::notes_in(target).map_err(|e| e.to_string())?);
+        } else {
+            notes.push(target.clone());
+        }
+    }
+    if notes.is_empty() {
+        return Err("没有可导出的笔记".to_string());
+    }
+
+    let config = Config::load(&root);
+    let index = LinkIndex::build(&root).map_err(|e| format!("读取笔记库失败: {}", e))?;
+    let base = match targets.as_slice() {
+        [folder] if folder.is_dir() => folder.clone(),
+        _ => root.clone(),
+    };
+    let published = export::published(&notes);
+    let exporter = Exporter {
+        root: &root,
+        index: &index,
+        base,
+        out_dir: out_dir.unwrap_or_else(|| config.export_dir(&root)),
+        published: Some(&published),
+        copy_assets: false,
+    };
+    let content = |note: &Path| fs::read_to_string(note);
+    let written = match format.as_str() {
+        "html" => export::html::export(&exporter, &notes, content),
+        "pdf" => export::pdf::export(&exporter, &notes, content, &PdfOptions::from_config(&config, &root)),
+        "docx" => export::docx::export(&exporter, &notes, content),
+        "epub" => export::epub::export(&exporter, &notes, content),
+        other => return Err(format!("不支持的格式 {}\n{}", other, USAGE)),
+    }.map_err(|e| format!("导出失败: {}", e))?;
+    for file in &written {
+        println!("{}", file.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-vault settings, stored as JSON in `<vault>/.nodian/config.json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Folder (relative to the vault root) that pasted and dropped files are copied into.
+    pub attachments_folder: String,
+    /// Folder (relative to the vault root) holding note templates.
+    pub templates_folder: String,
+    /// Folder (relative to the vault root) holding daily notes.
+    pub daily_folder: String,
+    /// chrono format string used for daily note file names.
+    pub daily_format: String,
+    /// Note (relative to the vault root) used as the template for new daily
+    /// notes; empty for none.
+    pub daily_template: String,
+    /// Unpinned clipboard history entries to keep.
+    pub clipboard_limit: usize,
+    /// Days after which unpinned clipboard entries are dropped; 0 keeps them.
+    pub clipboard_days: i64,
+    /// Also record text copied in other applications by polling the system
+    /// clipboard.
+    pub clipboard_poll: bool,
+    /// SQLite database file, relative to the vault root or absolute.
+    pub database_path: String,
+    /// Folder exported files are written to, relative to the vault root or
+    /// absolute. Hidden by default so exports don't show up as vault files.
+    pub export_folder: String,
+    /// Folder the static site is generated into, relative to the vault root
+    /// or absolute. Hidden by default, like `export_folder`.
+    pub site_folder: String,
+    /// Site name shown in page headers and the feed; the vault folder name
+    /// when empty.
+    pub site_title: String,
+    /// Public address of the site, e.g. `https://notes.example.com/`. Used
+    /// for absolute links in the feed.
+    pub site_url: String,
+    /// PDF page size: `A4`, `A5`, `Letter`, `Legal` or `<width>x<height>`
+    /// in millimetres.
+    pub pdf_page_size: String,
+    /// PDF page margin in millimetres.
+    pub pdf_margin: f32,
+    /// TrueType font embedded for text the built-in PDF fonts can't show,
+    /// such as Chinese. Common system fonts are tried when empty; without
+    /// any, such text prints as `?`.
+    pub pdf_font: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            attachments_folder: "attachments".to_string(),
+            templates_folder: "Templates".to_string(),
+            daily_folder: "Daily".to_string(),
+            daily_format: "%Y-%m-%d".to_string(),
+            daily_template: String::new(),
+            clipboard_limit: 200,
+            clipboard_days: 30,
+            clipboard_poll: false,
+            database_path: ".nodian/nodian.db".to_string(),
+            export_folder: ".nodian/export".to_string(),
+            site_folder: ".nodian/site".to_string(),
+            site_title: String::new(),
+            site_url: String::new(),
+            pdf_page_size: "A4".to_string(),
+            pdf_margin: 20.0,
+            pdf_font: String::new(),
+        }
+    }
+}
+
+/// A daily note name must format without error (chrono panics otherwise),
+/// stay in the daily folder and read back as the same date.
+fn is_valid_daily_format(format: &str) -> bool {
+    [(2024, 12, 31), (2001, 2, 3)].into_iter().all(|(y, m, d)| {
+        let date = NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let mut name = String::new();
+        write!(name, "{}", date.format(format)).is_ok()
+            && !name.is_empty()
+            && !name.contains(['/', '\\'])
+            && NaiveDate::parse_from_str(&name, format) == Ok(date)
+    })
+}
+
+impl Config {
+    fn path(root: &Path) -> PathBuf {
+        root.join(".nodian").join("config.json")
+    }
+
+    pub fn load(root: &Path) -> Config {
+        let mut config = match fs::read_to_string(Self::path(root)) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                eprintln!("Invalid config file, using defaults: {}", e);
+                Config::default()
+            }),
+            Err(_) => Config::default(),
+        };
+        if !is_valid_daily_format(&config.daily_format) {
+            eprintln!("Invalid daily_format {:?}, using %Y-%m-%d", config.daily_format);
+            config.daily_format = Config::default().daily_format;
+        }
+        config
+    }
+
+    pub fn attachments_dir(&self, root: &Path) -> PathBuf {
+        root.join(&self.attachments_folder)
+    }
+
+    pub fn templates_dir(&self, root: &Path) -> PathBuf {
+        root.join(&self.templates_folder)
+    }
+
+    pub fn export_dir(&self, root: &Path) -> PathBuf {
+        root.join(&self.export_folder)
+    }
+
+    pub fn site_dir(&self, root: &Path) -> PathBuf {
+        root.join(&self.site_folder)
+    }
+
+    pub fn database_path(&self, root: &Path) -> PathBuf {
+        root.join(&self.database_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_daily_format() {
+        for format in ["%Y-%m-%d", "%Y%m%d", "%d.%m.%Y", "%Y年%m月%d日 %A"] {
+            assert!(is_valid_daily_format(format), "{}", format);
+        }
+        // 无效、会建子文件夹、读不回日期
+        for format in ["%Q", "%H:%M", "%Y/%m/%d", "%A", "%m-%d", ""] {
+            assert!(!is_valid_daily_format(format), "{}", format);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalendarEvent {
+    /// 0 for events that have not been stored yet.
+    pub id: i64,
+    pub title: String,
+    pub description: String,
+    pub start: NaiveDateTime,
+    pub end: Option<NaiveDateTime>,
+    /// iCalendar `RRULE` value, e.g. `FREQ=WEEKLY;BYDAY=MO`.
+    pub rrule: Option<String>,
+    /// Days on which a recurring event doesn't take place.
+    pub exceptions: Vec<NaiveDate>,
+    /// Minutes before the start to show a reminder.
+    pub reminder: Option<i64>,
+}
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, TIME_FORMAT).ok()
+}
+
+// 例外日期存成逗号分隔的 YYYY-MM-DD
+fn parse_exceptions(value: Option<String>) -> Vec<NaiveDate> {
+    value.unwrap_or_default()
+        .split(',')
+        .filter_map(|date| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok())
+        .collect()
+}
+
+fn format_exceptions(exceptions: &[NaiveDate]) -> Option<String> {
+    if exceptions.is_empty() {
+        return None;
+    }
+    Some(exceptions.iter().map(|date| date.format("%Y-%m-%d").to_string()).collect::<Vec<_>>().join(","))
+}
+
+/// Events that may occur on a day in `from..=to`: the ones starting in the
+/// range plus every recurring event that started before its end. Use
+/// `calendar::recurrence::expand` to get the actual occurrences.
+pub async fn events_between(pool: &SqlitePool, from: NaiveDate, to: NaiveDate) -> Result<Vec<CalendarEvent>, sqlx::Error> {
+    let end = to.succ_opt().unwrap_or(to).and_hms_opt(0, 0, 0).unwrap().format(TIME_FORMAT).to_string();
+    let rows = sqlx::query(
+        "SELECT id, title, description, start_time, end_time, rrule, exceptions, reminder_minutes FROM calendar_events
+         WHERE (start_time >= ? AND start_time < ?) OR (rrule IS NOT NULL AND start_time < ?) ORDER BY start_time"
+    )
+    .bind(from.and_hms_opt(0, 0, 0).unwrap().format(TIME_FORMAT).to_string())
+    .bind(&end)
+    .bind(&end)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().filter_map(|row| {
+        let start: String = row.get("start_time");
+        let end: Option<String> = row.get("end_time");
+        Some(CalendarEvent {
+            id: row.get("id"),
+            title: row.get("title"),
+            description: row.get::<Option<String>, _>("description").unwrap_or_default(),
+            start: parse_time(&start)?,
+            end: end.as_deref().and_then(parse_time),
+            rrule: row.get("rrule"),
+            exceptions: parse_exceptions(row.get("exceptions")),
+            reminder: row.get("reminder_minutes"),
+        })
+    }).collect())
+}
+
+/// The earliest any reminder fires, in minutes before its event.
+pub async fn max_reminder(pool: &SqlitePool) -> Result<Option<i64>, sqlx::Error> {
+    let row: (Option<i64>,) = sqlx::query_as("SELECT MAX(reminder_minutes) FROM calendar_events")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// Inserts a new event (id 0) or updates an existing one; returns its id.
+/// Updates keep the stored exceptions, which only `add_exception` changes.
+pub async fn save_event(pool: &SqlitePool, event: &CalendarEvent) -> Result<i64, sqlx::Error> {
+    let start = event.start.format(TIME_FORMAT).to_string();
+    let end = event.end.map(|end| end.format(TIME_FORMAT).to_string());
+    if event.id == 0 {
+        let result = sqlx::query(
+            "INSERT INTO calendar_events (title, description, start_time, end_time, rrule, exceptions, reminder_minutes)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&event.title)
+        .bind(&event.description)
+        .bind(start)
+        .bind(end)
+        .bind(&event.rrule)
+        .bind(format_exceptions(&event.exceptions))
+        .bind(event.reminder)
+        .execute(pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    } else {
+        sqlx::query(
+            "UPDATE calendar_events SET title = ?, description = ?, start_time = ?, end_time = ?, rrule = ?, reminder_minutes = ?
+             WHERE id = ?"
+        )
+        .bind(&event.title)
+        .bind(&event.description)
+        .bind(start)
+        .bind(end)
+        .bind(&event.rrule)
+        .bind(event.reminder)
+        .bind(event.id)
+        .execute(pool)
+        .await?;
+        Ok(event.id)
+    }
+}
+
+/// Whether an event with the same title and start time is already stored,
+/// so importing a file twice doesn't duplicate its events.
+async fn event_exists(pool: &SqlitePool, event: &CalendarEvent) -> Result<bool, sqlx::Error> {
+    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM calendar_events WHERE title = ? AND start_time = ?")
+        .bind(&event.title)
+        .bind(event.start.format(TIME_FORMAT).to_string())
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0 > 0)
+}
+
+/// Stores imported events, skipping ones that are already in the database.
+/// Returns how many were added.
+pub async fn import_events(pool: &SqlitePool, events: &[CalendarEvent]) -> Result<usize, sqlx::Error> {
+    let mut added = 0;
+    for event in events {
+        if event_exists(pool, event).await? {
+            continue;
+        }
+        save_event(pool, &CalendarEvent { id: 0, ..event.clone() }).await?;
+        added += 1;
+    }
+    Ok(added)
+}
+
+/// Removes a single occurrence of a recurring event.
+pub async fn add_exception(pool: &SqlitePool, id: i64, date: NaiveDate) -> Result<(), sqlx::Error> {
+    let row: (Option<String>,) = sqlx::query_as("SELECT exceptions FROM calendar_events WHERE id = ?")
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    let mut exceptions = parse_exceptions(row.0);
+    if !exceptions.contains(&date) {
+        exceptions.push(date);
+        exceptions.sort();
+    }
+    sqlx::query("UPDATE calendar_events SET exceptions = ? WHERE id = ?")
+        .bind(format_exceptions(&exceptions))
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_event(pool: &SqlitePool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM calendar_events WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
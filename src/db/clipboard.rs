@@ -0,0 +1,168 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+
+// 带毫秒，同一秒内复制的内容也能排序
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipboardEntry {
+    pub id: i64,
+    pub content: String,
+    /// Vault-relative path of the note it was copied from; `None` for text
+    /// picked up from the system clipboard.
+    pub source: Option<String>,
+    /// Last time the text was copied.
+    pub copied_at: NaiveDateTime,
+    /// Pinned entries are listed first and never pruned.
+    pub pinned: bool,
+}
+
+/// Records a copy. Copying the same text again only moves the existing
+/// entry to the top, keeping its source if the new copy has none.
+pub async fn record(pool: &SqlitePool, content: &str, source: Option<&str>) -> Result<(), sqlx::Error> {
+    let now = Local::now().naive_local().format(TIME_FORMAT).to_string();
+    let updated = sqlx::query(
+        "UPDATE clipboard_history SET created_at = ?, source_note = COALESCE(?, source_note) WHERE content = ?"
+    )
+    .bind(&now)
+    .bind(source)
+    .bind(content)
+    .execute(pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        sqlx::query("INSERT INTO clipboard_history (content, source_note, created_at) VALUES (?, ?, ?)")
+            .bind(content)
+            .bind(source)
+            .bind(&now)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Entries containing `query` (ASCII case-insensitive), pinned first, then newest.
+pub async fn search(pool: &SqlitePool, query: &str, limit: i64) -> Result<Vec<ClipboardEntry>, sqlx::Error> {
+    // % 和 _ 按字面匹配
+    let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    let rows = sqlx::query(
+        "SELECT id, content, source_note, created_at, pinned FROM clipboard_history
+         WHERE content LIKE ? ESCAPE '\\' ORDER BY pinned DESC, created_at DESC, id DESC LIMIT ?"
+    )
+    .bind(pattern)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(|row| {
+        let copied_at: String = row.get("created_at");
+        ClipboardEntry {
+            id: row.get("id"),
+            content: row.get("content"),
+            source: row.get("source_note"),
+            copied_at: NaiveDateTime::parse_from_str(&copied_at, "%Y-%m-%d %H:%M:%S%.f").unwrap_or_default(),
+            pinned: row.get::<i64, _>("pinned") != 0,
+        }
+    }).collect())
+}
+
+pub async fn set_pinned(pool: &SqlitePool, id: i64, pinned: bool) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE clipboard_history SET pinned = ? WHERE id = ?")
+        .bind(pinned)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_entry(pool: &SqlitePool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM clipboard_history WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Removes every entry that isn't pinned.
+pub async fn clear(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM clipboard_history WHERE pinned = 0")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Keeps at most `max_entries` unpinned entries, none older than `max_age`.
+/// Returns how many were removed.
+pub async fn prune(pool: &SqlitePool, max_entries: usize, max_age: Option<Duration>) -> Result<u64, sqlx::Error> {
+    let mut removed = 0;
+    if let Some(max_age) = max_age {
+        let cutoff = (Local::now().naive_local() - max_age).format(TIME_FORMAT).to_string();
+        removed += sqlx::query("DELETE FROM clipboard_history WHERE pinned = 0 AND created_at < ?")
+            .bind(cutoff)
+            .execute(pool)
+            .await?
+            .rows_affected();
+    }
+    removed += sqlx::query(
+        "DELETE FROM clipboard_history WHERE pinned = 0 AND id NOT IN (
+            SELECT id FROM clipboard_history WHERE pinned = 0 ORDER BY created_at DESC, id DESC LIMIT ?
+        )"
+    )
+    .bind(max_entries as i64)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+
+    fn contents(entries: &[ClipboardEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.content.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn records_each_text_once() {
+        let pool = memory_pool().await;
+        record(&pool, "first", Some("a.md")).await.unwrap();
+        record(&pool, "second", None).await.unwrap();
+        // 再次复制只会移到最前，没有来源时保留原来的
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        record(&pool, "first", None).await.unwrap();
+
+        let entries = search(&pool, "", 10).await.unwrap();
+        assert_eq!(contents(&entries), ["first", "second"]);
+        assert_eq!(entries[0].source.as_deref(), Some("a.md"));
+    }
+
+    #[tokio::test]
+    async fn prune_keeps_pinned_entries() {
+        let pool = memory_pool().await;
+        for content in ["one", "two", "three"] {
+            record(&pool, content, None).await.unwrap();
+        }
+        let one = search(&pool, "one", 1).await.unwrap()[0].id;
+        set_pinned(&pool, one, true).await.unwrap();
+
+        assert_eq!(prune(&pool, 1, None).await.unwrap(), 1);
+        assert_eq!(contents(&search(&pool, "", 10).await.unwrap()), ["one", "three"]);
+        assert_eq!(prune(&pool, 0, Some(Duration::zero())).await.unwrap(), 1);
+        assert_eq!(contents(&search(&pool, "", 10).await.unwrap()), ["one"]);
+        clear(&pool).await.unwrap();
+        assert_eq!(contents(&search(&pool, "", 10).await.unwrap()), ["one"]);
+    }
+
+    #[tokio::test]
+    async fn search_matches_wildcards_literally() {
+        let pool = memory_pool().await;
+        for content in ["50% off", "500 off", "snake_case", "snakeXcase", "C:\\temp", "C:temp"] {
+            record(&pool, content, None).await.unwrap();
+        }
+        assert_eq!(contents(&search(&pool, "0%", 10).await.unwrap()), ["50% off"]);
+        assert_eq!(contents(&search(&pool, "SNAKE_", 10).await.unwrap()), ["snake_case"]);
+        assert_eq!(contents(&search(&pool, ":\\", 10).await.unwrap()), ["C:\\temp"]);
+    }
+}
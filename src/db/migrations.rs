@@ -0,0 +1,149 @@
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+
+enum Step {
+    Sql(&'static str),
+    /// Table, column, definition. Skipped when the column exists, since
+    /// databases from before versioning may already have it.
+    AddColumn(&'static str, &'static str, &'static str),
+}
+
+struct Migration {
+    version: i64,
+    steps: &'static [Step],
+}
+
+/// Schema history. Never edit a migration that has shipped; append a new
+/// one instead. The version reached is kept in `PRAGMA user_version`.
+const MIGRATIONS: &[Migration] = &[
+    // 初始的表，旧数据库里可能已经存在
+    Migration {
+        version: 1,
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS notes (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    title TEXT NOT NULL,
+                    content TEXT NOT NULL,
+                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                )"
+            ),
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS calendar_events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    title TEXT NOT NULL,
+                    description TEXT,
+                    start_time TIMESTAMP NOT NULL,
+                    end_time TIMESTAMP,
+                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                )"
+            ),
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS clipboard_history (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    content TEXT NOT NULL,
+                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                )"
+            ),
+            // front matter 属性，列表类型的属性每一项占一行
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS note_properties (
+                    note_path TEXT NOT NULL,
+                    key TEXT NOT NULL,
+                    kind TEXT NOT NULL,
+                    value TEXT NOT NULL,
+                    position INTEGER NOT NULL DEFAULT 0
+                )"
+            ),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_note_properties_note ON note_properties (note_path)"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_note_properties_key ON note_properties (key, value)"),
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS note_tags (
+                    note_path TEXT NOT NULL,
+                    tag TEXT NOT NULL
+                )"
+            ),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_note_tags_tag ON note_tags (tag)"),
+        ],
+    },
+    // 重复事件和提醒
+    Migration {
+        version: 2,
+        steps: &[
+            Step::AddColumn("calendar_events", "rrule", "TEXT"),
+            Step::AddColumn("calendar_events", "exceptions", "TEXT"),
+            Step::AddColumn("calendar_events", "reminder_minutes", "INTEGER"),
+        ],
+    },
+    // 剪贴板历史的来源和置顶
+    Migration {
+        version: 3,
+        steps: &[
+            Step::AddColumn("clipboard_history", "source_note", "TEXT"),
+            Step::AddColumn("clipboard_history", "pinned", "INTEGER NOT NULL DEFAULT 0"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_clipboard_history_created ON clipboard_history (created_at)"),
+        ],
+    },
+    // notes 改为笔记文件的索引。原来的表从未写入过，改名保留
+    Migration {
+        version: 4,
+        steps: &[
+            Step::Sql("ALTER TABLE notes RENAME TO notes_legacy"),
+            Step::Sql(
+                "CREATE TABLE notes (
+                    path TEXT PRIMARY KEY,
+                    title TEXT NOT NULL,
+                    hash TEXT NOT NULL,
+                    mtime INTEGER NOT NULL,
+                    word_count INTEGER NOT NULL DEFAULT 0,
+                    created_at TIMESTAMP NOT NULL,
+                    updated_at TIMESTAMP NOT NULL
+                )"
+            ),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_notes_hash ON notes (hash)"),
+        ],
+    },
+];
+
+async fn add_column(conn: &mut SqliteConnection, table: &str, column: &str, definition: &str) -> Result<(), sqlx::Error> {
+    let columns: Vec<(String,)> = sqlx::query_as(&format!("SELECT name FROM pragma_table_info('{}')", table))
+        .fetch_all(&mut *conn)
+        .await?;
+    if !columns.iter().any(|(name,)| name == column) {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Brings the schema up to date. Each migration runs in its own
+/// transaction together with the version bump, so a failure leaves the
+/// database at the previous version. Returns the version reached.
+pub async fn migrate(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    let (current,): (i64,) = sqlx::query_as("PRAGMA user_version").fetch_one(pool).await?;
+    if current > latest_version() {
+        return Err(sqlx::Error::Protocol(format!(
+            "database schema version {} is newer than this build supports ({})", current, latest_version()
+        )));
+    }
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        let mut tx = pool.begin().await?;
+        for step in migration.steps {
+            match step {
+                Step::Sql(sql) => {
+                    sqlx::query(sql).execute(&mut *tx).await?;
+                },
+                Step::AddColumn(table, column, definition) => add_column(&mut tx, table, column, definition).await?,
+            }
+        }
+        // PRAGMA 不支持参数绑定
+        sqlx::query(&format!("PRAGMA user_version = {}", migration.version)).execute(&mut *tx).await?;
+        tx.commit().await?;
+    }
+    Ok(latest_version())
+}
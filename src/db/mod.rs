@@ -1,43 +1,61 @@
-use sqlx::sqlite::SqlitePool;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+#[cfg(test)]
+use sqlx::sqlite::SqlitePoolOptions;
+use std::fs;
+use std::path::Path;
 
-pub async fn init_database() -> Result<(), sqlx::Error> {
-    let pool = SqlitePool::connect("sqlite:nodian.db").await?;
+pub mod calendar;
+pub mod clipboard;
+mod migrations;
+pub mod notes;
+pub mod properties;
+pub mod tags;
 
-    // Create tables for various features
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS notes (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            title TEXT NOT NULL,
-            content TEXT NOT NULL,
-            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-        )"
-    )
-    .execute(&pool)
-    .await?;
+/// Where databases were created before they moved into the vault.
+const LEGACY_PATH: &str = "nodian.db";
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS calendar_events (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            title TEXT NOT NULL,
-            description TEXT,
-            start_time TIMESTAMP NOT NULL,
-            end_time TIMESTAMP,
-            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-        )"
-    )
-    .execute(&pool)
-    .await?;
+/// Opens (creating if needed) the database at `path` and migrates it to
+/// the current schema. With `adopt_legacy`, a database left in the working
+/// directory by older versions is copied to `path` the first time.
+pub async fn init_database(path: &Path, adopt_legacy: bool) -> Result<SqlitePool, sqlx::Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let legacy = Path::new(LEGACY_PATH);
+    if adopt_legacy && !path.exists() && legacy.is_file() {
+        fs::copy(legacy, path)?;
+        eprintln!("Copied legacy database {} to {}", legacy.display(), path.display());
+    }
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS clipboard_history (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            content TEXT NOT NULL,
-            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-        )"
-    )
-    .execute(&pool)
-    .await?;
+    let options = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+    let pool = SqlitePool::connect_with(options).await?;
+    migrations::migrate(&pool).await?;
+    Ok(pool)
+}
 
+/// A migrated in-memory database. One connection, since each connection
+/// to `:memory:` gets a database of its own.
+#[cfg(test)]
+pub async fn memory_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+    migrations::migrate(&pool).await.unwrap();
+    pool
+}
+
+/// Drops everything indexed for notes that no longer exist.
+pub async fn prune_notes(pool: &SqlitePool, note_paths: &[String]) -> Result<(), sqlx::Error> {
+    for table in ["note_properties", "note_tags"] {
+        let indexed: Vec<(String,)> = sqlx::query_as(&format!("SELECT DISTINCT note_path FROM {}", table))
+            .fetch_all(pool)
+            .await?;
+        for (note_path,) in indexed {
+            if !note_paths.contains(&note_path) {
+                sqlx::query(&format!("DELETE FROM {} WHERE note_path = ?", table))
+                    .bind(&note_path)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
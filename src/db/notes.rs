@@ -0,0 +1,94 @@
+use chrono::NaiveDateTime;
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// What the index knows about a note file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteRecord {
+    /// Vault-relative path with `/` separators.
+    pub path: String,
+    pub title: String,
+    /// BLAKE3 of the content, used to tell edits from mere touches and to
+    /// follow renames.
+    pub hash: String,
+    /// Modification time (Unix seconds) when the file was last indexed.
+    pub mtime: i64,
+    pub word_count: i64,
+    pub created_at: NaiveDateTime,
+    /// Last time the content actually changed.
+    pub updated_at: NaiveDateTime,
+}
+
+fn record(row: &sqlx::sqlite::SqliteRow) -> NoteRecord {
+    let parse = |column: &str| NaiveDateTime::parse_from_str(&row.get::<String, _>(column), TIME_FORMAT).unwrap_or_default();
+    NoteRecord {
+        path: row.get("path"),
+        title: row.get("title"),
+        hash: row.get("hash"),
+        mtime: row.get("mtime"),
+        word_count: row.get("word_count"),
+        created_at: parse("created_at"),
+        updated_at: parse("updated_at"),
+    }
+}
+
+pub async fn get(pool: &SqlitePool, path: &str) -> Result<Option<NoteRecord>, sqlx::Error> {
+    let row = sqlx::query("SELECT * FROM notes WHERE path = ?")
+        .bind(path)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(record))
+}
+
+pub async fn all(pool: &SqlitePool) -> Result<Vec<NoteRecord>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM notes ORDER BY path").fetch_all(pool).await?;
+    Ok(rows.iter().map(record).collect())
+}
+
+pub async fn save(pool: &SqlitePool, note: &NoteRecord) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO notes (path, title, hash, mtime, word_count, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT (path) DO UPDATE SET title = excluded.title, hash = excluded.hash, mtime = excluded.mtime,
+         word_count = excluded.word_count, updated_at = excluded.updated_at"
+    )
+    .bind(&note.path)
+    .bind(&note.title)
+    .bind(&note.hash)
+    .bind(note.mtime)
+    .bind(note.word_count)
+    .bind(note.created_at.format(TIME_FORMAT).to_string())
+    .bind(note.updated_at.format(TIME_FORMAT).to_string())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Moves a note and everything indexed for it to a new path, keeping its
+/// creation time.
+pub async fn rename(pool: &SqlitePool, from: &str, to: &str, title: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE notes SET path = ?, title = ? WHERE path = ?")
+        .bind(to)
+        .bind(title)
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+    for table in ["note_properties", "note_tags"] {
+        sqlx::query(&format!("UPDATE {} SET note_path = ? WHERE note_path = ?", table))
+            .bind(to)
+            .bind(from)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+pub async fn delete(pool: &SqlitePool, path: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM notes WHERE path = ?")
+        .bind(path)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
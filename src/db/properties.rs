@@ -0,0 +1,31 @@
+use sqlx::sqlite::SqlitePool;
+use crate::markdown::front_matter::{Property, PropertyValue};
+
+/// Replaces the indexed properties of a note.
+pub async fn save_properties(pool: &SqlitePool, note_path: &str, properties: &[Property]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM note_properties WHERE note_path = ?")
+        .bind(note_path)
+        .execute(&mut tx)
+        .await?;
+
+    for property in properties {
+        let values = match &property.value {
+            PropertyValue::List(items) => items.clone(),
+            other => vec![other.display()],
+        };
+        for (position, value) in values.iter().enumerate() {
+            sqlx::query("INSERT INTO note_properties (note_path, key, kind, value, position) VALUES (?, ?, ?, ?, ?)")
+                .bind(note_path)
+                .bind(&property.key)
+                .bind(property.value.kind())
+                .bind(value)
+                .bind(position as i64)
+                .execute(&mut tx)
+                .await?;
+        }
+    }
+
+    tx.commit().await
+}
@@ -0,0 +1,41 @@
+use sqlx::sqlite::SqlitePool;
+
+/// Replaces the indexed tags of a note.
+pub async fn save_tags(pool: &SqlitePool, note_path: &str, tags: &[String]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM note_tags WHERE note_path = ?")
+        .bind(note_path)
+        .execute(&mut tx)
+        .await?;
+
+    for tag in tags {
+        sqlx::query("INSERT INTO note_tags (note_path, tag) VALUES (?, ?)")
+            .bind(note_path)
+            .bind(tag)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    tx.commit().await
+}
+
+/// All `(note_path, tag)` pairs in the vault.
+pub async fn all_tags(pool: &SqlitePool) -> Result<Vec<(String, String)>, sqlx::Error> {
+    sqlx::query_as("SELECT note_path, tag FROM note_tags ORDER BY tag")
+        .fetch_all(pool)
+        .await
+}
+
+/// Notes carrying `tag` or one of its nested tags (`tag/...`).
+pub async fn notes_with_tag(pool: &SqlitePool, tag: &str) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT DISTINCT note_path FROM note_tags WHERE tag = ? OR substr(tag, 1, length(?) + 1) = ? || '/'"
+    )
+    .bind(tag)
+    .bind(tag)
+    .bind(tag)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(note_path,)| note_path).collect())
+}
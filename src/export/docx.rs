@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use pulldown_cmark::Alignment;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+use crate::export::{self, xml_escape, Book, Exporter};
+use crate::markdown::document::{self, Block, Inline};
+use crate::markdown::render::parse_size_hint;
+
+/// A4 with 2.5 cm margins, in twentieths of a point.
+const PAGE_WIDTH: u32 = 11906;
+const PAGE_HEIGHT: u32 = 16838;
+const PAGE_MARGIN: u32 = 1418;
+const EMU_PER_TWIP: u64 = 635;
+const EMU_PER_PIXEL: u64 = 9525;
+const INDENT: u32 = 720;
+
+const NAMESPACES: &str = concat!(
+    r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" "#,
+    r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" "#,
+    r#"xmlns:wp="http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing" "#,
+    r#"xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" "#,
+    r#"xmlns:pic="http://schemas.openxmlformats.org/drawingml/2006/picture""#,
+);
+
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:docDefaults>
+<w:rPrDefault><w:rPr><w:rFonts w:ascii="Calibri" w:hAnsi="Calibri" w:cs="Calibri"/><w:sz w:val="22"/><w:szCs w:val="22"/></w:rPr></w:rPrDefault>
+<w:pPrDefault><w:pPr><w:spacing w:after="120" w:line="300" w:lineRule="auto"/></w:pPr></w:pPrDefault>
+</w:docDefaults>
+<w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/><w:qFormat/></w:style>
+<w:style w:type="paragraph" w:styleId="Heading1"><w:name w:val="heading 1"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="360" w:after="160"/><w:outlineLvl w:val="0"/></w:pPr><w:rPr><w:b/><w:sz w:val="40"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading2"><w:name w:val="heading 2"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="320" w:after="120"/><w:outlineLvl w:val="1"/></w:pPr><w:rPr><w:b/><w:sz w:val="32"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading3"><w:name w:val="heading 3"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="280" w:after="120"/><w:outlineLvl w:val="2"/></w:pPr><w:rPr><w:b/><w:sz w:val="28"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading4"><w:name w:val="heading 4"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="240" w:after="80"/><w:outlineLvl w:val="3"/></w:pPr><w:rPr><w:b/><w:sz w:val="24"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading5"><w:name w:val="heading 5"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="240" w:after="80"/><w:outlineLvl w:val="4"/></w:pPr><w:rPr><w:b/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading6"><w:name w:val="heading 6"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="240" w:after="80"/><w:outlineLvl w:val="5"/></w:pPr><w:rPr><w:b/><w:i/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Quote"><w:name w:val="Quote"/><w:basedOn w:val="Normal"/><w:qFormat/><w:pPr><w:pBdr><w:left w:val="single" w:sz="18" w:space="8" w:color="DDDDDD"/></w:pBdr></w:pPr><w:rPr><w:color w:val="666666"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="SourceCode"><w:name w:val="Source Code"/><w:basedOn w:val="Normal"/><w:pPr><w:shd w:val="clear" w:color="auto" w:fill="F6F8FA"/><w:spacing w:after="120" w:line="240" w:lineRule="auto"/></w:pPr><w:rPr><w:rFonts w:ascii="Consolas" w:hAnsi="Consolas" w:cs="Consolas"/><w:sz w:val="18"/></w:rPr></w:style>
+<w:style w:type="character" w:styleId="VerbatimChar"><w:name w:val="Verbatim Char"/><w:rPr><w:rFonts w:ascii="Consolas" w:hAnsi="Consolas" w:cs="Consolas"/><w:sz w:val="20"/><w:shd w:val="clear" w:color="auto" w:fill="F3F3F3"/></w:rPr></w:style>
+<w:style w:type="character" w:styleId="Hyperlink"><w:name w:val="Hyperlink"/><w:rPr><w:color w:val="1F6FEB"/><w:u w:val="single"/></w:rPr></w:style>
+</w:styles>"#;
+
+struct Image {
+    id: String,
+    part: String,
+    width: u64,
+    height: u64,
+}
+
+struct Writer<'a> {
+    exporter: &'a Exporter<'a>,
+    note: &'a Path,
+    body: String,
+    /// Relationships after styles and numbering: (id, type, target, external).
+    relationships: Vec<(String, &'static str, String, bool)>,
+    images: HashMap<PathBuf, Image>,
+    /// Start numbers of the ordered lists; list `i` uses numbering id `i + 2`.
+    ordered_lists: Vec<(usize, u64)>,
+    /// Numbering for the next paragraph: numbering id and level.
+    pending_number: Option<(usize, usize)>,
+    list_depth: usize,
+    quote_depth: usize,
+    drawings: usize,
+}
+
+fn relationship_type(name: &str) -> String {
+    format!("http://schemas.openxmlformats.org/officeDocument/2006/relationships/{}", name)
+}
+
+impl Writer<'_> {
+    fn next_relationship_id(&self) -> String {
+        format!("rId{}", self.relationships.len() + 3)
+    }
+
+    fn runs(&mut self, inlines: &[Inline], bold: bool) -> String {
+        let mut xml = String::new();
+        for run in document::runs(inlines) {
+            let link = run.link.and_then(|url| self.hyperlink(url));
+            let mut properties = String::new();
+            if run.style.code {
+                properties.push_str(r#"<w:rStyle w:val="VerbatimChar"/>"#);
+            } else if link.is_some() {
+                properties.push_str(r#"<w:rStyle w:val="Hyperlink"/>"#);
+            }
+            if bold || run.style.bold {
+                properties.push_str("<w:b/>");
+            }
+            if run.style.italic {
+                properties.push_str("<w:i/>");
+            }
+            if run.style.strike {
+                properties.push_str("<w:strike/>");
+            }
+            let mut content = String::new();
+            for (i, line) in run.text.split('\n').enumerate() {
+                if i > 0 {
+                    content.push_str("<w:br/>");
+                }
+                if !line.is_empty() {
+                    content.push_str(&format!(r#"<w:t xml:space="preserve">{}</w:t>"#, xml_escape(line)));
+                }
+            }
+            if !properties.is_empty() {
+                properties = format!("<w:rPr>{}</w:rPr>", properties);
+            }
+            let run_xml = format!("<w:r>{}{}</w:r>", properties, content);
+            match link {
+                Some(id) => xml.push_str(&format!(r#"<w:hyperlink r:id="{}">{}</w:hyperlink>"#, id, run_xml)),
+                None => xml.push_str(&run_xml),
+            }
+        }
+        xml
+    }
+
+    /// Relationship id for an external link. Links into the vault have no
+    /// target in a Word document and stay plain text.
+    fn hyperlink(&mut self, url: &str) -> Option<String> {
+        if !crate::vault::links::is_external(url) {
+            return None;
+        }
+        if let Some((id, ..)) = self.relationships.iter().find(|(_, kind, target, _)| *kind == "hyperlink" && target == url) {
+            return Some(id.clone());
+        }
+        let id = self.next_relationship_id();
+        self.relationships.push((id.clone(), "hyperlink", url.to_string(), true));
+        Some(id)
+    }
+
+    fn paragraph(&mut self, style: Option<&str>, extra: &str, content: &str) {
+        let style = style.or(if self.quote_depth > 0 { Some("Quote") } else { None });
+        let mut properties = String::new();
+        if let Some(style) = style {
+            properties.push_str(&format!(r#"<w:pStyle w:val="{}"/>"#, style));
+        }
+        // pPr 的子元素必须按规范的顺序
+        let number = self.pending_number.take();
+        if let Some((id, level)) = number {
+            properties.push_str(&format!(r#"<w:numPr><w:ilvl w:val="{}"/><w:numId w:val="{}"/></w:numPr>"#, level, id));
+        }
+        properties.push_str(extra);
+        let level = self.list_depth + self.quote_depth;
+        if number.is_none() && level > 0 {
+            properties.push_str(&format!(r#"<w:ind w:left="{}"/>"#, level as u32 * INDENT));
+        }
+        if !properties.is_empty() {
+            properties = format!("<w:pPr>{}</w:pPr>", properties);
+        }
+        self.body.push_str(&format!("<w:p>{}{}</w:p>", properties, content));
+    }
+
+    fn image(&mut self, url: &str, alt: &str) {
+        let (alt, hint_width, hint_height) = parse_size_hint(alt);
+        let image = self.exporter.link_target(self.note, url)
+            .ok_or_else(|| "不是笔记库中的文件".to_string())
+            .and_then(|(path, _)| self.add_image(&path));
+        let (id, width, height) = match image {
+            Ok(image) => (image.id.clone(), image.width, image.height),
+            Err(e) => {
+                eprintln!("Failed to embed image {}: {}", url, e);
+                let text = format!("[{}]", if alt.is_empty() { url } else { alt });
+                let runs = self.runs(&[Inline::Emphasis(vec![Inline::Text(text)])], false);
+                self.paragraph(None, "", &runs);
+                return;
+            },
+        };
+        // 按 96 DPI 的原始大小，尺寸提示优先，超出版心时缩小
+        let (mut cx, mut cy) = (width * EMU_PER_PIXEL, height * EMU_PER_PIXEL);
+        if hint_width > 0 {
+            let hinted_height = if hint_height > 0 { hint_height as u64 } else { height * hint_width as u64 / width.max(1) };
+            (cx, cy) = (hint_width as u64 * EMU_PER_PIXEL, hinted_height * EMU_PER_PIXEL);
+        }
+        let max_width = (PAGE_WIDTH - 2 * PAGE_MARGIN - (self.list_depth + self.quote_depth) as u32 * INDENT) as u64 * EMU_PER_TWIP;
+        if cx > max_width {
+            cy = cy * max_width / cx;
+            cx = max_width;
+        }
+        self.drawings += 1;
+        let drawing = format!(
+            concat!(
+                r#"<w:r><w:drawing><wp:inline distT="0" distB="0" distL="0" distR="0"><wp:extent cx="{cx}" cy="{cy}"/>"#,
+                r#"<wp:docPr id="{n}" name="Picture {n}" descr="{alt}"/><a:graphic><a:graphicData uri="http://schemas.openxmlformats.org/drawingml/2006/picture">"#,
+                r#"<pic:pic><pic:nvPicPr><pic:cNvPr id="{n}" name="Picture {n}"/><pic:cNvPicPr/></pic:nvPicPr>"#,
+                r#"<pic:blipFill><a:blip r:embed="{id}"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>"#,
+                r#"<pic:spPr><a:xfrm><a:off x="0" y="0"/><a:ext cx="{cx}" cy="{cy}"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom></pic:spPr>"#,
+                r#"</pic:pic></a:graphicData></a:graphic></wp:inline></w:drawing></w:r>"#,
+            ),
+            cx = cx, cy = cy, n = self.drawings, alt = xml_escape(alt), id = id,
+        );
+        self.paragraph(None, "", &drawing);
+    }
+
+    /// Adds an image file once, as a part under `word/media`.
+    fn add_image(&mut self, path: &Path) -> Result<&Image, String> {
+        if !self.images.contains_key(path) {
+            let ext = match export::image_mime(path) {
+                Some("image/png") => "png",
+                Some("image/jpeg") => "jpeg",
+                Some("image/gif") => "gif",
+                Some("image/bmp") => "bmp",
+                _ => return Err("Word 不支持这种图片格式".to_string()),
+            };
+            let (width, height) = image::image_dimensions(path).map_err(|e| e.to_string())?;
+            let id = self.next_relationship_id();
+            let part = format!("media/image{}.{}", self.images.len() + 1, ext);
+            self.relationships.push((id.clone(), "image", part.clone(), false));
+            self.images.insert(path.to_path_buf(), Image { id, part, width: width as u64, height: height as u64 });
+        }
+        Ok(&self.images[path])
+    }
+
+    fn table(&mut self, alignments: &[Alignment], rows: &[Vec<Vec<Inline>>]) {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let width = (PAGE_WIDTH - 2 * PAGE_MARGIN) / columns as u32;
+        let border = r#"w:val="single" w:sz="4" w:space="0" w:color="CCCCCC""#;
+        let mut xml = format!(
+            concat!(
+                r#"<w:tbl><w:tblPr><w:tblW w:w="5000" w:type="pct"/><w:tblBorders>"#,
+                r#"<w:top {b}/><w:left {b}/><w:bottom {b}/><w:right {b}/><w:insideH {b}/><w:insideV {b}/>"#,
+                r#"</w:tblBorders><w:tblCellMar><w:left w:w="100" w:type="dxa"/><w:right w:w="100" w:type="dxa"/></w:tblCellMar></w:tblPr><w:tblGrid>"#,
+            ),
+            b = border,
+        );
+        for _ in 0..columns {
+            xml.push_str(&format!(r#"<w:gridCol w:w="{}"/>"#, width));
+        }
+        xml.push_str("</w:tblGrid>");
+        for (i, row) in rows.iter().enumerate() {
+            xml.push_str(if i == 0 { "<w:tr><w:trPr><w:tblHeader/></w:trPr>" } else { "<w:tr>" });
+            for column in 0..columns {
+                let justify = match alignments.get(column) {
+                    Some(Alignment::Center) => r#"<w:jc w:val="center"/>"#,
+                    Some(Alignment::Right) => r#"<w:jc w:val="right"/>"#,
+                    _ => "",
+                };
+                let shading = if i == 0 { r#"<w:shd w:val="clear" w:color="auto" w:fill="F6F8FA"/>"# } else { "" };
+                let runs = row.get(column).map(|cell| self.runs(cell, i == 0)).unwrap_or_default();
+                xml.push_str(&format!(
+                    r#"<w:tc><w:tcPr><w:tcW w:w="{}" w:type="dxa"/>{}</w:tcPr><w:p><w:pPr><w:spacing w:after="0"/>{}</w:pPr>{}</w:p></w:tc>"#,
+                    width, shading, justify, runs,
+                ));
+            }
+            xml.push_str("</w:tr>");
+        }
+        xml.push_str("</w:tbl>");
+        self.body.push_str(&xml);
+        // 相邻的表格之间需要一个段落，否则会被合并
+        self.paragraph(None, r#"<w:spacing w:after="0"/>"#, "");
+    }
+
+    fn blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            match block {
+                Block::Heading(level, inlines) => {
+                    let runs = self.runs(inlines, false);
+                    self.paragraph(Some(&format!("Heading{}", level.clamp(&1, &6))), "", &runs);
+                },
+                Block::Paragraph(inlines) => {
+                    let runs = self.runs(inlines, false);
+                    self.paragraph(None, "", &runs);
+                },
+                Block::Code { code, .. } => {
+                    let runs = self.runs(&[Inline::Text(code.replace('\t', "    "))], false);
+                    self.paragraph(Some("SourceCode"), "", &runs);
+                },
+                Block::List { start, items } => {
+                    let numbering = match start {
+                        Some(start) => {
+                            self.ordered_lists.push((self.list_depth, *start));
+                            self.ordered_lists.len() + 1
+                        },
+                        None => 1,
+                    };
+                    for item in items {
+                        self.pending_number = Some((numbering, self.list_depth.min(8)));
+                        self.list_depth += 1;
+                        match (item.task, item.blocks.first()) {
+                            (Some(checked), Some(Block::Paragraph(inlines))) => {
+                                let mut inlines = inlines.clone();
+                                inlines.insert(0, Inline::Text(if checked { "☒ " } else { "☐ " }.to_string()));
+                                self.blocks(&[Block::Paragraph(inlines)]);
+                                self.blocks(&item.blocks[1..]);
+                            },
+                            _ => self.blocks(&item.blocks),
+                        }
+                        self.list_depth -= 1;
+                        if self.pending_number.is_some() {
+                            self.paragraph(None, "", "");
+                        }
+                    }
+                },
+                Block::Quote(inner) => {
+                    self.quote_depth += 1;
+                    self.blocks(inner);
+                    self.quote_depth -= 1;
+                },
+                Block::Table { alignments, rows } => self.table(alignments, rows),
+                Block::Image { url, alt } => self.image(url, alt),
+                Block::Rule => self.paragraph(None, r#"<w:pBdr><w:bottom w:val="single" w:sz="6" w:space="1" w:color="DDDDDD"/></w:pBdr>"#, ""),
+                Block::Footnote { label, blocks } => {
+                    let runs = self.runs(&[Inline::Text(format!("[{}]", label))], false);
+                    self.paragraph(None, "", &runs);
+                    self.blocks(blocks);
+                },
+            }
+        }
+    }
+
+    fn numbering(&self) -> String {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#);
+        xml.push_str(r#"<w:numbering xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">"#);
+        for (id, bullet) in [(0, true), (1, false)] {
+            xml.push_str(&format!(r#"<w:abstractNum w:abstractNumId="{}"><w:multiLevelType w:val="hybridMultilevel"/>"#, id));
+            for level in 0..9 {
+                let (format, text) = if bullet {
+                    ("bullet", ["•", "◦", "▪"][level % 3].to_string())
+                } else {
+                    ("decimal", format!("%{}.", level + 1))
+                };
+                xml.push_str(&format!(
+                    r#"<w:lvl w:ilvl="{}"><w:start w:val="1"/><w:numFmt w:val="{}"/><w:lvlText w:val="{}"/><w:lvlJc w:val="left"/><w:pPr><w:ind w:left="{}" w:hanging="360"/></w:pPr></w:lvl>"#,
+                    level, format, text, (level as u32 + 1) * INDENT,
+                ));
+            }
+            xml.push_str("</w:abstractNum>");
+        }
+        xml.push_str(r#"<w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num>"#);
+        // 每个有序列表单独编号，从自己的起始数字开始
+        for (i, (level, start)) in self.ordered_lists.iter().enumerate() {
+            xml.push_str(&format!(
+                r#"<w:num w:numId="{}"><w:abstractNumId w:val="1"/><w:lvlOverride w:ilvl="{}"><w:startOverride w:val="{}"/></w:lvlOverride></w:num>"#,
+                i + 2, level.min(&8), start,
+            ));
+        }
+        xml.push_str("</w:numbering>");
+        xml
+    }
+}
+
+fn core_properties(book: &Book) -> String {
+    let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+    let creator = book.author.as_deref().map(|author| format!("<dc:creator>{}</dc:creator>", xml_escape(author))).unwrap_or_default();
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" "#,
+            r#"xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" "#,
+            r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#,
+            r#"<dc:title>{}</dc:title>{}<dc:language>{}</dc:language>"#,
+            r#"<dcterms:created xsi:type="dcterms:W3CDTF">{now}</dcterms:created><dcterms:modified xsi:type="dcterms:W3CDTF">{now}</dcterms:modified>"#,
+            r#"</cp:coreProperties>"#,
+        ),
+        xml_escape(&book.title), creator, xml_escape(&book.language), now = now,
+    )
+}
+
+/// Writes the book as a Word document, chapters separated by page breaks.
+pub fn render(exporter: &Exporter, book: &Book) -> io::Result<Vec<u8>> {
+    let mut writer = Writer {
+        exporter,
+        note: Path::new(""),
+        body: String::new(),
+        relationships: Vec::new(),
+        images: HashMap::new(),
+        ordered_lists: Vec::new(),
+        pending_number: None,
+        list_depth: 0,
+        quote_depth: 0,
+        drawings: 0,
+    };
+    for (i, chapter) in book.chapters.iter().enumerate() {
+        writer.note = &chapter.note;
+        if i > 0 {
+            writer.body.push_str(r#"<w:p><w:r><w:br w:type="page"/></w:r></w:p>"#);
+        }
+        // 多篇笔记时，没有以标题开头的章节补上笔记标题
+        if book.chapters.len() > 1 && !matches!(chapter.blocks.first(), Some(Block::Heading(..))) {
+            writer.blocks(&[Block::Heading(1, vec![Inline::Text(chapter.title.clone())])]);
+        }
+        writer.blocks(&chapter.blocks);
+    }
+
+    let document = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><w:document {}><w:body>{}"#,
+            r#"<w:sectPr><w:pgSz w:w="{}" w:h="{}"/><w:pgMar w:top="{m}" w:right="{m}" w:bottom="{m}" w:left="{m}" w:header="709" w:footer="709" w:gutter="0"/></w:sectPr>"#,
+            r#"</w:body></w:document>"#,
+        ),
+        NAMESPACES, writer.body, PAGE_WIDTH, PAGE_HEIGHT, m = PAGE_MARGIN,
+    );
+    let mut relationships = String::from(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#);
+    relationships.push_str(&format!(r#"<Relationship Id="rId1" Type="{}" Target="styles.xml"/>"#, relationship_type("styles")));
+    relationships.push_str(&format!(r#"<Relationship Id="rId2" Type="{}" Target="numbering.xml"/>"#, relationship_type("numbering")));
+    for (id, kind, target, external) in &writer.relationships {
+        let mode = if *external { r#" TargetMode="External""# } else { "" };
+        relationships.push_str(&format!(r#"<Relationship Id="{}" Type="{}" Target="{}"{}/>"#, id, relationship_type(kind), xml_escape(target), mode));
+    }
+    relationships.push_str("</Relationships>");
+
+    let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    let add = |zip: &mut ZipWriter<io::Cursor<Vec<u8>>>, name: &str, data: &[u8]| -> io::Result<()> {
+        zip.start_file(name, options).map_err(io::Error::other)?;
+        zip.write_all(data)
+    };
+    add(&mut zip, "[Content_Types].xml", concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+        r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+        r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+        r#"<Default Extension="png" ContentType="image/png"/><Default Extension="jpeg" ContentType="image/jpeg"/>"#,
+        r#"<Default Extension="gif" ContentType="image/gif"/><Default Extension="bmp" ContentType="image/bmp"/>"#,
+        r#"<Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>"#,
+        r#"<Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/>"#,
+        r#"<Override PartName="/word/numbering.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml"/>"#,
+        r#"<Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>"#,
+        r#"</Types>"#,
+    ).as_bytes())?;
+    add(&mut zip, "_rels/.rels", concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+        r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>"#,
+        r#"<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>"#,
+        r#"</Relationships>"#,
+    ).as_bytes())?;
+    add(&mut zip, "docProps/core.xml", core_properties(book).as_bytes())?;
+    add(&mut zip, "word/document.xml", document.as_bytes())?;
+    add(&mut zip, "word/styles.xml", STYLES.as_bytes())?;
+    add(&mut zip, "word/numbering.xml", writer.numbering().as_bytes())?;
+    add(&mut zip, "word/_rels/document.xml.rels", relationships.as_bytes())?;
+    for (path, image) in &writer.images {
+        add(&mut zip, &format!("word/{}", image.part), &fs::read(path)?)?;
+    }
+    Ok(zip.finish().map_err(io::Error::other)?.into_inner())
+}
+
+/// Exports `notes` as one Word document, one chapter per note in the given order.
+pub fn export(
+    exporter: &Exporter,
+    notes: &[PathBuf],
+    content: impl Fn(&Path) -> io::Result<String>,
+) -> io::Result<Vec<PathBuf>> {
+    if notes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let book = Book::load(exporter, notes, content)?;
+    let out_file = book.output_path(exporter, "docx");
+    if let Some(parent) = out_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&out_file, render(exporter, &book)?)?;
+    Ok(vec![out_file])
+}
@@ -0,0 +1,400 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use pulldown_cmark::Alignment;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+use crate::export::{self, xml_escape, Book, Exporter};
+use crate::markdown::document::{self, Block, Inline};
+use crate::markdown::render::parse_size_hint;
+use crate::vault::links;
+
+const STYLE: &str = r#"
+body { line-height: 1.6; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.3; margin: 1.2em 0 0.5em; }
+code { font-family: monospace; font-size: 0.9em; background: #f3f3f3; }
+pre { background: #f6f8fa; padding: 0.6em 0.8em; white-space: pre-wrap; }
+pre code { background: none; }
+blockquote { margin: 0.8em 0; padding: 0 1em; color: #666; border-left: 4px solid #ddd; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.5em; }
+th { background: #f6f8fa; }
+li > p { margin: 0.2em 0; }
+div.image { margin: 0.8em 0; text-align: center; }
+img { max-width: 100%; }
+aside.footnote { font-size: 0.9em; }
+"#;
+
+/// An image file copied into the book: source, name under `images/` and media type.
+struct Image {
+    path: PathBuf,
+    href: String,
+    mime: &'static str,
+}
+
+struct Writer<'a> {
+    exporter: &'a Exporter<'a>,
+    book: &'a Book,
+    note: &'a Path,
+    images: Vec<Image>,
+    ids: HashSet<String>,
+    /// Headings of the current chapter: level, id and text, for the table of contents.
+    headings: Vec<(u32, String, String)>,
+}
+
+fn chapter_href(index: usize) -> String {
+    format!("chapter-{}.xhtml", index + 1)
+}
+
+impl Writer<'_> {
+    fn unique_id(&mut self, text: &str) -> String {
+        let base = match export::slug(text) {
+            slug if slug.is_empty() => "section".to_string(),
+            slug => slug,
+        };
+        let mut id = base.clone();
+        let mut n = 1;
+        while !self.ids.insert(id.clone()) {
+            n += 1;
+            id = format!("{}-{}", base, n);
+        }
+        id
+    }
+
+    /// Where a link points inside the book; `None` for vault files that are not chapters.
+    fn href(&self, url: &str) -> Option<String> {
+        if url.starts_with('#') || links::is_external(url) {
+            return Some(url.to_string());
+        }
+        let (path, fragment) = self.exporter.link_target(self.note, url)?;
+        let chapter = self.book.chapter_of(&path)?;
+        Some(match fragment {
+            Some(fragment) => format!("{}#{}", chapter_href(chapter), export::slug(&fragment)),
+            None => chapter_href(chapter),
+        })
+    }
+
+    /// Copies a vault image into the book once, returning its href.
+    fn image_href(&mut self, url: &str) -> Option<String> {
+        let (path, _) = self.exporter.link_target(self.note, url)?;
+        if let Some(image) = self.images.iter().find(|image| image.path == path) {
+            return Some(image.href.clone());
+        }
+        let mime = export::image_mime(&path)?;
+        if !path.is_file() {
+            return None;
+        }
+        let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+        let href = format!("images/{}.{}", self.images.len() + 1, ext);
+        self.images.push(Image { path, href: href.clone(), mime });
+        Some(href)
+    }
+
+    fn image(&mut self, url: &str, alt: &str) -> String {
+        let (alt, width, height) = parse_size_hint(alt);
+        match self.image_href(url) {
+            Some(href) => {
+                let mut size = String::new();
+                if width > 0 {
+                    size.push_str(&format!(r#" width="{}""#, width));
+                }
+                if height > 0 {
+                    size.push_str(&format!(r#" height="{}""#, height));
+                }
+                format!(r#"<img src="{}" alt="{}"{}/>"#, xml_escape(&href), xml_escape(alt), size)
+            },
+            None => {
+                eprintln!("Failed to embed image {}", url);
+                format!("<em>[{}]</em>", xml_escape(if alt.is_empty() { url } else { alt }))
+            },
+        }
+    }
+
+    fn inlines(&mut self, inlines: &[Inline]) -> String {
+        let mut xml = String::new();
+        for inline in inlines {
+            match inline {
+                Inline::Text(text) => xml.push_str(&xml_escape(text)),
+                Inline::Code(code) => xml.push_str(&format!("<code>{}</code>", xml_escape(code))),
+                Inline::Emphasis(content) => xml.push_str(&format!("<em>{}</em>", self.inlines(content))),
+                Inline::Strong(content) => xml.push_str(&format!("<strong>{}</strong>", self.inlines(content))),
+                Inline::Strikethrough(content) => xml.push_str(&format!("<del>{}</del>", self.inlines(content))),
+                Inline::Link { url, content } => {
+                    let content = self.inlines(content);
+                    match self.href(url) {
+                        Some(href) => xml.push_str(&format!(r#"<a href="{}">{}</a>"#, xml_escape(&href), content)),
+                        None => xml.push_str(&content),
+                    }
+                },
+                Inline::Image { url, alt } if links::is_external(url) => xml.push_str(&xml_escape(alt)),
+                Inline::Image { url, alt } => {
+                    let image = self.image(url, alt);
+                    xml.push_str(&image);
+                },
+                Inline::FootnoteReference(label) => xml.push_str(&format!(
+                    r##"<sup><a epub:type="noteref" href="#fn-{}">{}</a></sup>"##,
+                    export::slug(label), xml_escape(label),
+                )),
+                Inline::SoftBreak => xml.push('\n'),
+                Inline::HardBreak => xml.push_str("<br/>\n"),
+            }
+        }
+        xml
+    }
+
+    fn blocks(&mut self, blocks: &[Block]) -> String {
+        let mut xml = String::new();
+        for block in blocks {
+            match block {
+                Block::Heading(level, inlines) => {
+                    let level = (*level).clamp(1, 6);
+                    let text = document::plain_text(inlines);
+                    let id = self.unique_id(&text);
+                    self.headings.push((level, id.clone(), text));
+                    xml.push_str(&format!("<h{0} id=\"{1}\">{2}</h{0}>\n", level, id, self.inlines(inlines)));
+                },
+                Block::Paragraph(inlines) => xml.push_str(&format!("<p>{}</p>\n", self.inlines(inlines))),
+                Block::Code { lang, code } => {
+                    let class = if lang.is_empty() { String::new() } else { format!(r#" class="language-{}""#, xml_escape(lang)) };
+                    xml.push_str(&format!("<pre><code{}>{}</code></pre>\n", class, xml_escape(code)));
+                },
+                Block::List { start, items } => {
+                    let tag = if start.is_some() { "ol" } else { "ul" };
+                    match start {
+                        Some(start) if *start != 1 => xml.push_str(&format!("<ol start=\"{}\">\n", start)),
+                        _ => xml.push_str(&format!("<{}>\n", tag)),
+                    }
+                    for item in items {
+                        let marker = match item.task {
+                            Some(true) => "☑ ",
+                            Some(false) => "☐ ",
+                            None => "",
+                        };
+                        // 只有一段的列表项不包 <p>
+                        match item.blocks.as_slice() {
+                            [Block::Paragraph(inlines)] => {
+                                xml.push_str(&format!("<li>{}{}</li>\n", marker, self.inlines(inlines)));
+                            },
+                            blocks => {
+                                let content = self.blocks(blocks);
+                                xml.push_str(&format!("<li>{}{}</li>\n", marker, content));
+                            },
+                        }
+                    }
+                    xml.push_str(&format!("</{}>\n", tag));
+                },
+                Block::Quote(inner) => {
+                    let content = self.blocks(inner);
+                    xml.push_str(&format!("<blockquote>\n{}</blockquote>\n", content));
+                },
+                Block::Table { alignments, rows } => {
+                    xml.push_str("<table>\n");
+                    for (i, row) in rows.iter().enumerate() {
+                        let cell_tag = if i == 0 { "th" } else { "td" };
+                        if i == 0 {
+                            xml.push_str("<thead>\n");
+                        } else if i == 1 {
+                            xml.push_str("<tbody>\n");
+                        }
+                        xml.push_str("<tr>");
+                        for (column, cell) in row.iter().enumerate() {
+                            let align = match alignments.get(column) {
+                                Some(Alignment::Left) => r#" style="text-align: left""#,
+                                Some(Alignment::Center) => r#" style="text-align: center""#,
+                                Some(Alignment::Right) => r#" style="text-align: right""#,
+                                _ => "",
+                            };
+                            xml.push_str(&format!("<{0}{1}>{2}</{0}>", cell_tag, align, self.inlines(cell)));
+                        }
+                        xml.push_str("</tr>\n");
+                        if i == 0 {
+                            xml.push_str("</thead>\n");
+                        }
+                    }
+                    if rows.len() > 1 {
+                        xml.push_str("</tbody>\n");
+                    }
+                    xml.push_str("</table>\n");
+                },
+                Block::Image { url, alt } => {
+                    let image = self.image(url, alt);
+                    xml.push_str(&format!("<div class=\"image\">{}</div>\n", image));
+                },
+                Block::Rule => xml.push_str("<hr/>\n"),
+                Block::Footnote { label, blocks } => {
+                    let content = self.blocks(blocks);
+                    xml.push_str(&format!(
+                        "<aside class=\"footnote\" epub:type=\"footnote\" id=\"fn-{}\">\n<p>[{}]</p>\n{}</aside>\n",
+                        export::slug(label), xml_escape(label), content,
+                    ));
+                },
+            }
+        }
+        xml
+    }
+}
+
+fn xhtml(title: &str, language: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n",
+            "<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" xml:lang=\"{1}\" lang=\"{1}\">\n",
+            "<head>\n<meta charset=\"UTF-8\"/>\n<title>{0}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n",
+            "<body>\n{2}</body>\n</html>\n",
+        ),
+        xml_escape(title), xml_escape(language), body,
+    )
+}
+
+/// Nested `<ol>` for table of contents entries of (level, href, text).
+fn toc_list(entries: &[(u32, String, String)]) -> String {
+    let mut xml = String::from("<ol>\n");
+    let mut i = 0;
+    while i < entries.len() {
+        let (level, href, text) = &entries[i];
+        let end = entries[i + 1..].iter().position(|entry| entry.0 <= *level).map_or(entries.len(), |n| i + 1 + n);
+        xml.push_str(&format!("<li><a href=\"{}\">{}</a>", xml_escape(href), xml_escape(text)));
+        if end > i + 1 {
+            xml.push_str(&toc_list(&entries[i + 1..end]));
+        }
+        xml.push_str("</li>\n");
+        i = end;
+    }
+    xml.push_str("</ol>\n");
+    xml
+}
+
+/// A stable identifier, so exporting the same notes again gives the same book.
+fn book_uuid(book: &Book) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(book.title.as_bytes());
+    for chapter in &book.chapters {
+        hasher.update(chapter.note.to_string_lossy().as_bytes());
+    }
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hasher.finalize().as_bytes()[..16]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Writes the book as an EPUB 3 file with one XHTML document per chapter.
+pub fn render(exporter: &Exporter, book: &Book) -> io::Result<Vec<u8>> {
+    let mut writer = Writer {
+        exporter,
+        book,
+        note: Path::new(""),
+        images: Vec::new(),
+        ids: HashSet::new(),
+        headings: Vec::new(),
+    };
+    let mut chapters = Vec::new();
+    let mut toc = Vec::new();
+    for (i, chapter) in book.chapters.iter().enumerate() {
+        writer.note = &chapter.note;
+        writer.ids.clear();
+        writer.headings.clear();
+        let mut body = String::new();
+        // 多篇笔记时，没有以标题开头的章节补上笔记标题
+        if book.chapters.len() > 1 && !matches!(chapter.blocks.first(), Some(Block::Heading(..))) {
+            body.push_str(&writer.blocks(&[Block::Heading(1, vec![Inline::Text(chapter.title.clone())])]));
+            writer.headings.clear();
+        }
+        body.push_str(&writer.blocks(&chapter.blocks));
+        let href = chapter_href(i);
+        if book.chapters.len() > 1 {
+            toc.push((0, href.clone(), chapter.title.clone()));
+            toc.extend(writer.headings.iter()
+                .filter(|(level, ..)| (2..=3).contains(level))
+                .map(|(level, id, text)| (*level, format!("{}#{}", href, id), text.clone())));
+        } else {
+            toc.extend(writer.headings.iter()
+                .filter(|(level, ..)| *level <= 3)
+                .map(|(level, id, text)| (*level, format!("{}#{}", href, id), text.clone())));
+        }
+        chapters.push(xhtml(&chapter.title, &book.language, &body));
+    }
+    if toc.is_empty() {
+        toc.push((0, chapter_href(0), book.title.clone()));
+    }
+    let nav = xhtml(&book.title, &book.language, &format!(
+        "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n{}</nav>\n",
+        xml_escape(&book.title), toc_list(&toc),
+    ));
+
+    let mut manifest = String::from(concat!(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n",
+        "<item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    ));
+    let mut spine = String::new();
+    for i in 0..chapters.len() {
+        manifest.push_str(&format!("<item id=\"chapter-{0}\" href=\"{1}\" media-type=\"application/xhtml+xml\"/>\n", i + 1, chapter_href(i)));
+        spine.push_str(&format!("<itemref idref=\"chapter-{}\"/>\n", i + 1));
+    }
+    for (i, image) in writer.images.iter().enumerate() {
+        manifest.push_str(&format!("<item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>\n", i + 1, xml_escape(&image.href), image.mime));
+    }
+    let creator = book.author.as_deref().map(|author| format!("<dc:creator>{}</dc:creator>\n", xml_escape(author))).unwrap_or_default();
+    let package = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"{lang}\">\n",
+            "<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n",
+            "<dc:identifier id=\"book-id\">urn:uuid:{uuid}</dc:identifier>\n",
+            "<dc:title>{title}</dc:title>\n{creator}<dc:language>{lang}</dc:language>\n",
+            "<meta property=\"dcterms:modified\">{modified}</meta>\n",
+            "</metadata>\n<manifest>\n{manifest}</manifest>\n<spine>\n{spine}</spine>\n</package>\n",
+        ),
+        lang = xml_escape(&book.language),
+        uuid = book_uuid(book),
+        title = xml_escape(&book.title),
+        creator = creator,
+        modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        manifest = manifest,
+        spine = spine,
+    );
+
+    let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+    let add = |zip: &mut ZipWriter<io::Cursor<Vec<u8>>>, name: &str, data: &[u8], method: CompressionMethod| -> io::Result<()> {
+        zip.start_file(name, SimpleFileOptions::default().compression_method(method)).map_err(io::Error::other)?;
+        zip.write_all(data)
+    };
+    // mimetype 必须是第一个文件，而且不能压缩
+    add(&mut zip, "mimetype", b"application/epub+zip", CompressionMethod::Stored)?;
+    add(&mut zip, "META-INF/container.xml", concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n",
+        "<rootfiles><rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/></rootfiles>\n",
+        "</container>\n",
+    ).as_bytes(), CompressionMethod::Deflated)?;
+    add(&mut zip, "OEBPS/content.opf", package.as_bytes(), CompressionMethod::Deflated)?;
+    add(&mut zip, "OEBPS/nav.xhtml", nav.as_bytes(), CompressionMethod::Deflated)?;
+    add(&mut zip, "OEBPS/style.css", STYLE.as_bytes(), CompressionMethod::Deflated)?;
+    for (i, chapter) in chapters.iter().enumerate() {
+        add(&mut zip, &format!("OEBPS/{}", chapter_href(i)), chapter.as_bytes(), CompressionMethod::Deflated)?;
+    }
+    for image in &writer.images {
+        add(&mut zip, &format!("OEBPS/{}", image.href), &fs::read(&image.path)?, CompressionMethod::Deflated)?;
+    }
+    Ok(zip.finish().map_err(io::Error::other)?.into_inner())
+}
+
+/// Exports `notes` as one EPUB book, one chapter per note in the given order.
+pub fn export(
+    exporter: &Exporter,
+    notes: &[PathBuf],
+    content: impl Fn(&Path) -> io::Result<String>,
+) -> io::Result<Vec<PathBuf>> {
+    if notes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let book = Book::load(exporter, notes, content)?;
+    let out_file = book.output_path(exporter, "epub");
+    if let Some(parent) = out_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&out_file, render(exporter, &book)?)?;
+    Ok(vec![out_file])
+}
@@ -0,0 +1,184 @@
+use base64::Engine;
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::export::{self, Exporter};
+use crate::markdown::front_matter;
+use crate::markdown::render::parser_options;
+use crate::vault::{self, links};
+
+pub const STYLE: &str = r#"
+body { margin: 0; background: #fafafa; color: #222; font: 16px/1.7 -apple-system, "Segoe UI", "PingFang SC", "Microsoft YaHei", sans-serif; }
+article { max-width: 760px; margin: 0 auto; padding: 32px 24px 64px; background: #fff; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.3; margin: 1.4em 0 0.6em; }
+h1 { font-size: 2em; border-bottom: 1px solid #eee; padding-bottom: 0.3em; }
+a { color: #1f6feb; text-decoration: none; }
+a:hover { text-decoration: underline; }
+img { max-width: 100%; }
+code { font-family: "SFMono-Regular", Consolas, monospace; font-size: 0.9em; background: #f3f3f3; padding: 0.1em 0.3em; border-radius: 3px; }
+pre { background: #f6f8fa; padding: 12px 16px; overflow-x: auto; border-radius: 4px; }
+pre code { background: none; padding: 0; }
+blockquote { margin: 0; padding: 0 1em; color: #666; border-left: 4px solid #ddd; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ddd; padding: 4px 10px; }
+th { background: #f6f8fa; }
+hr { border: none; border-top: 1px solid #eee; }
+"#;
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    // 写入 String 不会失败
+    pulldown_cmark::escape::escape_html(&mut escaped, text).unwrap_or_default();
+    escaped
+}
+
+/// Inlines a local image as a `data:` URI; `None` when it can't be read.
+fn data_uri(path: &Path) -> Option<String> {
+    let mime = export::image_mime(path)?;
+    let bytes = fs::read(path).ok()?;
+    Some(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(bytes)))
+}
+
+/// Renders the body of `note` to HTML: note links point at the exported
+/// `.html` files, other vault files are linked where they are, images are
+/// embedded and headings get anchors.
+pub fn render_body(exporter: &Exporter, note: &Path, markdown: &str) -> String {
+    let markdown = export::wikilinks_to_markdown(exporter.index, note, front_matter::body(markdown));
+    let out_file = exporter.output_path(note, "html");
+    let out_dir = out_file.parent().unwrap_or(&exporter.out_dir);
+    let relative_url = |target: &Path| {
+        links::encode_path(&links::relative_path(out_dir, target).to_string_lossy().replace('\\', "/"))
+    };
+
+    let file_url = |target: &Path| {
+        if exporter.copy_assets {
+            relative_url(&exporter.asset_path(target))
+        } else {
+            relative_url(target)
+        }
+    };
+
+    let mut events = Vec::new();
+    let mut heading: Option<(usize, Vec<Event>)> = None;
+    // 指向未发布笔记的链接只保留文字
+    let mut dropped_link = false;
+    for event in Parser::new_ext(&markdown, parser_options()) {
+        let event = match event {
+            Event::Start(Tag::Link(kind, url, title)) => {
+                let url = match exporter.link_target(note, &url) {
+                    Some((target, _)) if vault::is_note(&target) && !exporter.is_published(&target) => {
+                        dropped_link = true;
+                        continue;
+                    },
+                    Some((target, fragment)) => {
+                        let fragment = fragment.map(|fragment| format!("#{}", export::slug(&fragment))).unwrap_or_default();
+                        if vault::is_note(&target) {
+                            format!("{}{}", relative_url(&exporter.output_path(&target, "html")), fragment)
+                        } else {
+                            format!("{}{}", file_url(&target), fragment)
+                        }
+                    },
+                    None => url.to_string(),
+                };
+                Event::Start(Tag::Link(kind, CowStr::from(url), title))
+            },
+            Event::End(Tag::Link(..)) if dropped_link => {
+                dropped_link = false;
+                continue;
+            },
+            Event::Start(Tag::Image(kind, url, title)) => {
+                let url = match exporter.link_target(note, &url) {
+                    Some((target, _)) if exporter.copy_assets => file_url(&target),
+                    Some((target, _)) => data_uri(&target).unwrap_or_else(|| relative_url(&target)),
+                    None => url.to_string(),
+                };
+                Event::Start(Tag::Image(kind, CowStr::from(url), title))
+            },
+            other => other,
+        };
+        // 标题先收集起来，算出锚点后再输出
+        match (&mut heading, event) {
+            (None, Event::Start(Tag::Heading(level, _, _))) => heading = Some((level as usize, Vec::new())),
+            (Some((level, inner)), Event::End(Tag::Heading(..))) => {
+                let text = inner.iter().filter_map(|event| match event {
+                    Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                    _ => None,
+                }).collect::<String>();
+                let mut rendered = String::new();
+                html::push_html(&mut rendered, inner.drain(..));
+                events.push(Event::Html(CowStr::from(format!("<h{0} id=\"{1}\">{2}</h{0}>\n", level, escape(&export::slug(&text)), rendered))));
+                heading = None;
+            },
+            (Some((_, inner)), event) => inner.push(event),
+            (None, event) => events.push(event),
+        }
+    }
+    let mut body = String::new();
+    html::push_html(&mut body, events.into_iter());
+    body
+}
+
+/// A complete page with the stylesheet inlined.
+pub fn page(title: &str, body: &str, style: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<article>\n{}</article>\n</body>\n</html>\n",
+        escape(title), style, body
+    )
+}
+
+/// Writes each note as a standalone page. `content` supplies the text of
+/// a note when it differs from the file (e.g. unsaved edits).
+pub fn export(exporter: &Exporter, notes: &[PathBuf], content: impl Fn(&Path) -> io::Result<String>) -> io::Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for note in notes {
+        let markdown = content(note)?;
+        let body = render_body(exporter, note, &markdown);
+        let out_file = exporter.output_path(note, "html");
+        if let Some(parent) = out_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&out_file, page(&export::note_title(note, &markdown), &body, STYLE))?;
+        written.push(out_file);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::links::LinkIndex;
+
+    #[test]
+    fn links_only_the_exported_notes() {
+        let root = std::env::temp_dir().join(format!("nodian-html-{}", std::process::id()));
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.md"), "See [[b#Part two]], [[sub/c|the other one]] and [b](b.md).").unwrap();
+        fs::write(root.join("b.md"), "## Part two\n\nBack to [[a]].").unwrap();
+        fs::write(root.join("sub/c.md"), "Not exported.").unwrap();
+        let index = LinkIndex::build(&root).unwrap();
+        let notes = vec![root.join("a.md"), root.join("b.md")];
+        let published = export::published(&notes);
+        let exporter = Exporter {
+            root: &root,
+            index: &index,
+            base: root.clone(),
+            out_dir: root.join(".nodian/export"),
+            published: Some(&published),
+            copy_assets: false,
+        };
+
+        let written = export(&exporter, &notes, |note| fs::read_to_string(note)).unwrap();
+        assert_eq!(written, [root.join(".nodian/export/a.html"), root.join(".nodian/export/b.html")]);
+        let a = fs::read_to_string(&written[0]).unwrap();
+        assert!(a.contains(r##"<a href="b.html#part-two">"##), "{}", a);
+        assert!(a.contains(r#"<a href="b.html">b</a>"#), "{}", a);
+        assert!(a.contains(", the other one and"), "{}", a);
+        assert!(!a.contains("c.html") && !a.contains("c.md"), "{}", a);
+        assert!(fs::read_to_string(&written[1]).unwrap().contains(r#"<a href="a.html">"#));
+        // 导出目录是隐藏的，不会被当成笔记库里的文件
+        assert_eq!(vault::vault_files(&root).unwrap().len(), 3);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
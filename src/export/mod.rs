@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use pulldown_cmark::{Event, Parser, Tag};
+use crate::markdown::document::{self, Block};
+use crate::markdown::front_matter::{self, PropertyValue};
+use crate::markdown::render::parser_options;
+use crate::vault::{self, links::{self, LinkIndex, NoteLink}};
+
+pub mod docx;
+pub mod epub;
+pub mod html;
+pub mod pdf;
+pub mod site;
+
+/// Where and how a set of notes is exported. Output files mirror the
+/// layout of the notes below `base`.
+pub struct Exporter<'a> {
+    pub root: &'a Path,
+    pub index: &'a LinkIndex,
+    pub base: PathBuf,
+    pub out_dir: PathBuf,
+    /// Notes that get a page; links to other notes keep only their text.
+    /// `None` links every note.
+    pub published: Option<&'a HashSet<PathBuf>>,
+    /// Link other vault files at their copy in `out_dir` instead of the
+    /// original, and don't inline images.
+    pub copy_assets: bool,
+}
+
+impl Exporter<'_> {
+    /// File a note is exported to, with extension `ext`.
+    pub fn output_path(&self, note: &Path, ext: &str) -> PathBuf {
+        let relative = links::relative_path(&self.base, note);
+        links::normalize(&self.out_dir.join(relative)).with_extension(ext)
+    }
+
+    /// Where a non-note file is copied to when `copy_assets` is set.
+    pub fn asset_path(&self, file: &Path) -> PathBuf {
+        links::normalize(&self.out_dir.join(links::relative_path(&self.base, file)))
+    }
+
+    pub fn is_published(&self, note: &Path) -> bool {
+        self.published.is_none_or(|published| published.contains(note))
+    }
+
+    /// Vault file a link in `note` points to, and its `#fragment` if any.
+    /// `None` for external links and links within the page.
+    pub fn link_target(&self, note: &Path, url: &str) -> Option<(PathBuf, Option<String>)> {
+        if url.is_empty() || url.starts_with('#') || links::is_external(url) {
+            return None;
+        }
+        let fragment = url.split_once('#').map(|(_, fragment)| links::decode_path(fragment));
+        let note_dir = note.parent().unwrap_or(self.root);
+        Some((links::normalize(&links::resolve(url, note_dir, self.root)), fragment))
+    }
+}
+
+/// Every note below `folder`.
+pub fn notes_in(folder: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(vault::vault_files(folder)?.into_iter().filter(|path| vault::is_note(path)).collect())
+}
+
+/// The notes of one export, as `Exporter::published`: links to notes
+/// exported along keep working, the others keep only their text.
+pub fn published(notes: &[PathBuf]) -> HashSet<PathBuf> {
+    notes.iter().map(|note| links::normalize(note)).collect()
+}
+
+/// A text or list property from the front matter; list items are comma separated.
+pub fn note_property(markdown: &str, key: &str) -> Option<String> {
+    let properties = front_matter::parse(markdown).ok()?;
+    let value = match properties.into_iter().find(|property| property.key == key)?.value {
+        value @ (PropertyValue::Text(_) | PropertyValue::List(_)) => value.display(),
+        _ => return None,
+    };
+    Some(value).filter(|value| !value.trim().is_empty())
+}
+
+/// The `title` property, falling back to the file name.
+pub fn note_title(note: &Path, markdown: &str) -> String {
+    let from_front_matter = front_matter::parse(markdown).ok().and_then(|properties| {
+        properties.into_iter().find(|property| property.key == "title").and_then(|property| match property.value {
+            PropertyValue::Text(title) if !title.trim().is_empty() => Some(title),
+            _ => None,
+        })
+    });
+    from_front_matter.unwrap_or_else(|| note.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default())
+}
+
+/// Anchor id for a heading, also applied to link fragments so
+/// `[[note#Some Heading]]` lands on it.
+pub fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() || c == '_' || c == '-' {
+            slug.extend(c.to_lowercase());
+        } else if c.is_whitespace() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug
+}
+
+pub fn image_mime(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        _ => return None,
+    })
+}
+
+pub struct Chapter {
+    pub note: PathBuf,
+    pub title: String,
+    pub blocks: Vec<Block>,
+}
+
+/// Notes gathered into a single document with one chapter per note, for
+/// the formats that produce one file.
+pub struct Book {
+    pub title: String,
+    pub author: Option<String>,
+    pub language: String,
+    pub chapters: Vec<Chapter>,
+}
+
+impl Book {
+    /// Reads `notes` in the given order. Title and author come from the front
+    /// matter of a single note; a folder is named after itself and credited
+    /// to the first author found.
+    pub fn load(exporter: &Exporter, notes: &[PathBuf], content: impl Fn(&Path) -> io::Result<String>) -> io::Result<Book> {
+        let mut book = Book { title: String::new(), author: None, language: String::new(), chapters: Vec::new() };
+        let mut language = None;
+        for note in notes {
+            let markdown = content(note)?;
+            book.author = book.author.or_else(|| note_property(&markdown, "author"));
+            language = language.or_else(|| note_property(&markdown, "lang")).or_else(|| note_property(&markdown, "language"));
+            let body = wikilinks_to_markdown(exporter.index, note, front_matter::body(&markdown));
+            if language.is_none() && body.chars().any(is_cjk) {
+                language = Some("zh".to_string());
+            }
+            book.chapters.push(Chapter {
+                note: note.clone(),
+                title: note_title(note, &markdown),
+                blocks: document::parse(&body),
+            });
+        }
+        book.title = match book.chapters.as_slice() {
+            [chapter] => chapter.title.clone(),
+            _ => exporter.base.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+        };
+        book.language = language.unwrap_or_else(|| "en".to_string());
+        Ok(book)
+    }
+
+    /// A single note is exported next to where its page would be; a folder
+    /// becomes one file named after it.
+    pub fn output_path(&self, exporter: &Exporter, ext: &str) -> PathBuf {
+        match self.chapters.as_slice() {
+            [chapter] => exporter.output_path(&chapter.note, ext),
+            _ => exporter.out_dir.join(format!("{}.{}", file_name(&self.title), ext)),
+        }
+    }
+
+    /// Index of the chapter made from `note`.
+    pub fn chapter_of(&self, note: &Path) -> Option<usize> {
+        self.chapters.iter().position(|chapter| chapter.note == note)
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{ac00}'..='\u{d7af}')
+}
+
+/// Replaces characters that are not allowed in file names.
+fn file_name(title: &str) -> String {
+    let name = title.trim().replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
+    if name.is_empty() { "notes".to_string() } else { name }
+}
+
+/// Escapes text for XML, dropping control characters XML 1.0 does not allow.
+pub fn xml_escape(text: &str) -> String {
+    let text: String = text.chars().filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r')).collect();
+    html::escape(&text)
+}
+
+/// Rewrites `[[target#heading|alias]]` and `![[target]]` as ordinary
+/// markdown links relative to `note`, so exporters only have to deal with
+/// one kind of link. Wikilinks inside code are left alone.
+pub fn wikilinks_to_markdown(index: &LinkIndex, note: &Path, markdown: &str) -> String {
+    let code_ranges = Parser::new_ext(markdown, parser_options()).into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::Start(Tag::CodeBlock(_)) | Event::Code(_)))
+        .map(|(_, range)| range)
+        .collect::<Vec<_>>();
+    let note_dir = note.parent().unwrap_or(Path::new(""));
+
+    let mut out = String::with_capacity(markdown.len());
+    let mut copied = 0;
+    let mut search_from = 0;
+    while let Some(start) = markdown[search_from..].find("[[").map(|i| i + search_from) {
+        let Some(end) = markdown[start + 2..].find("]]").map(|i| i + start + 2) else { break };
+        let inner = &markdown[start + 2..end];
+        if inner.contains('\n') || code_ranges.iter().any(|range| range.contains(&start)) {
+            search_from = start + 2;
+            continue;
+        }
+        search_from = end + 2;
+        let (target, alias) = match inner.split_once('|') {
+            Some((target, alias)) => (target.trim(), Some(alias.trim())),
+            None => (inner.trim(), None),
+        };
+        let (path, fragment) = match target.split_once('#') {
+            Some((path, fragment)) => (path.trim(), Some(fragment.trim())),
+            None => (target, None),
+        };
+        let link = if path.is_empty() {
+            // [[#标题]] 指向本页
+            String::new()
+        } else {
+            let resolved = index.resolve(note, &NoteLink { target: path.to_string(), embed: false, wiki: true, line: 0 });
+            links::encode_path(&links::relative_path(note_dir, &resolved).to_string_lossy().replace('\\', "/"))
+        };
+        let url = match fragment {
+            Some(fragment) => format!("{}#{}", link, slug(fragment)),
+            None => link,
+        };
+        let text = alias.unwrap_or(if path.is_empty() { fragment.unwrap_or_default() } else { target });
+        let embed = start > 0 && markdown.as_bytes()[start - 1] == b'!';
+        let start = if embed { start - 1 } else { start };
+        out.push_str(&markdown[copied..start]);
+        if embed {
+            out.push('!');
+        }
+        out.push_str(&format!("[{}](<{}>)", text.replace('[', "\\[").replace(']', "\\]"), url));
+        copied = end + 2;
+    }
+    out.push_str(&markdown[copied..]);
+    out
+}
@@ -0,0 +1,670 @@
+use printpdf::path::{PaintMode, WindingOrder};
+use printpdf::{
+    BuiltinFont, Color, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference,
+    PdfLayerReference, PdfPageIndex, Point, Rect, Rgb, TextRenderingMode,
+};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::config::Config;
+use crate::export::{self, Exporter};
+use crate::markdown::document::{self, Block, Inline};
+use crate::markdown::front_matter;
+
+/// Millimetres per point.
+const PT: f32 = 0.352_778;
+const BODY_SIZE: f32 = 11.0;
+const CODE_SIZE: f32 = 9.0;
+const TABLE_SIZE: f32 = 10.0;
+const HEADING_SIZES: [f32; 6] = [22.0, 18.0, 15.0, 13.0, 11.5, 11.0];
+const LIST_INDENT: f32 = 6.0;
+const CELL_PADDING: f32 = 1.5;
+
+/// Fonts tried, in order, when a note has text outside Windows-1252 and
+/// `pdf_font` is empty. Only plain TrueType files can be embedded.
+const FONT_CANDIDATES: &[&str] = &[
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\simkai.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/google-droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/truetype/arphic-gbsn00lp/gbsn00lp.ttf",
+];
+
+// Helvetica 和 Helvetica-Bold 中 ASCII 字符的宽度（AFM，千分之一字号）
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+const BUILTIN_FONTS: [BuiltinFont; 6] = [
+    BuiltinFont::Helvetica,
+    BuiltinFont::HelveticaBold,
+    BuiltinFont::HelveticaOblique,
+    BuiltinFont::HelveticaBoldOblique,
+    BuiltinFont::Courier,
+    BuiltinFont::CourierBold,
+];
+
+pub struct PdfOptions {
+    /// Page width and height in millimetres.
+    pub page_size: (f32, f32),
+    pub margin: f32,
+    pub font: Option<PathBuf>,
+}
+
+impl PdfOptions {
+    pub fn from_config(config: &Config, root: &Path) -> PdfOptions {
+        let page_size = page_size(&config.pdf_page_size).unwrap_or_else(|| {
+            eprintln!("Unknown PDF page size {:?}, using A4", config.pdf_page_size);
+            (210.0, 297.0)
+        });
+        PdfOptions {
+            page_size,
+            margin: config.pdf_margin.clamp(0.0, page_size.0.min(page_size.1) / 3.0),
+            font: (!config.pdf_font.is_empty()).then(|| root.join(&config.pdf_font)),
+        }
+    }
+}
+
+fn page_size(name: &str) -> Option<(f32, f32)> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "a3" => Some((297.0, 420.0)),
+        "a4" => Some((210.0, 297.0)),
+        "a5" => Some((148.0, 210.0)),
+        "letter" => Some((215.9, 279.4)),
+        "legal" => Some((215.9, 355.6)),
+        other => {
+            let (width, height) = other.split_once('x')?;
+            let size: (f32, f32) = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+            (size.0 >= 50.0 && size.1 >= 50.0).then_some(size)
+        },
+    }
+}
+
+/// Characters the built-in PDF fonts can show.
+fn is_win_ansi(c: char) -> bool {
+    matches!(c, ' '..='~' | '\u{a0}'..='\u{ff}') || "€‚ƒ„…†‡ˆ‰Š‹ŒŽ‘’“”•–—˜™š›œžŸ".contains(c)
+}
+
+/// Characters a line may break after, as in CJK text.
+fn breaks_anywhere(c: char) -> bool {
+    matches!(c as u32, 0x2E80..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF | 0x20000..=0x2FFFF)
+}
+
+fn is_true_type(data: &[u8]) -> bool {
+    (data.starts_with(&[0, 1, 0, 0]) || data.starts_with(b"true")) && ttf_parser::Face::parse(data, 0).is_ok()
+}
+
+/// The configured font, or the first system font found; `None` when there
+/// is neither.
+fn load_font(options: &PdfOptions) -> io::Result<Option<Vec<u8>>> {
+    if let Some(path) = &options.font {
+        let data = fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("无法读取字体 {}: {}", path.display(), e)))?;
+        if !is_true_type(&data) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} 不是 TrueType 字体", path.display())));
+        }
+        return Ok(Some(data));
+    }
+    Ok(FONT_CANDIDATES.iter()
+        .filter_map(|path| fs::read(path).ok())
+        .find(|data| is_true_type(data)))
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    code: bool,
+    link: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Span {
+    text: String,
+    style: Style,
+}
+
+fn spans(inlines: &[Inline]) -> Vec<Span> {
+    document::runs(inlines).into_iter().map(|run| Span {
+        text: run.text,
+        style: Style {
+            bold: run.style.bold,
+            italic: run.style.italic,
+            code: run.style.code,
+            link: run.link.is_some(),
+        },
+    }).collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Face {
+    /// Index into `BUILTIN_FONTS`.
+    Builtin(usize),
+    External,
+}
+
+struct Fonts<'a> {
+    builtin: Vec<IndirectFontRef>,
+    external: Option<(IndirectFontRef, ttf_parser::Face<'a>)>,
+}
+
+impl Fonts<'_> {
+    /// Font used for `c`, with `?` standing in for characters no font has.
+    fn face(&self, style: Style, c: char) -> (Face, char) {
+        if is_win_ansi(c) {
+            let index = if style.code { 4 + style.bold as usize } else { style.bold as usize + 2 * style.italic as usize };
+            (Face::Builtin(index), c)
+        } else if self.external.as_ref().is_some_and(|(_, face)| face.glyph_index(c).is_some()) {
+            (Face::External, c)
+        } else {
+            self.face(style, '?')
+        }
+    }
+
+    /// Advance width of `c` in millimetres.
+    fn advance(&self, face: Face, c: char, size: f32) -> f32 {
+        let em = match face {
+            Face::Builtin(4 | 5) => 0.6,
+            Face::Builtin(index) => {
+                let widths = if index % 2 == 1 { &HELVETICA_BOLD } else { &HELVETICA };
+                let code = c as usize;
+                f32::from(if (32..127).contains(&code) { widths[code - 32] } else { 556 }) / 1000.0
+            },
+            Face::External => self.external.as_ref().map_or(0.0, |(_, face)| {
+                let advance = face.glyph_index(c).and_then(|glyph| face.glyph_hor_advance(glyph)).unwrap_or(0);
+                f32::from(advance) / f32::from(face.units_per_em())
+            }),
+        };
+        em * size * PT
+    }
+
+    fn font(&self, face: Face) -> &IndirectFontRef {
+        match (face, &self.external) {
+            (Face::External, Some((font, _))) => font,
+            (Face::Builtin(index), _) => &self.builtin[index],
+            _ => &self.builtin[0],
+        }
+    }
+}
+
+/// A run of text in one font on a line; `x` and `width` in millimetres.
+struct Piece {
+    text: String,
+    face: Face,
+    style: Style,
+    size: f32,
+    x: f32,
+    width: f32,
+}
+
+fn color(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb(Rgb::new(f32::from(r) / 255.0, f32::from(g) / 255.0, f32::from(b) / 255.0, None))
+}
+
+struct Writer<'a> {
+    doc: PdfDocumentReference,
+    fonts: Fonts<'a>,
+    pages: Vec<(PdfPageIndex, PdfLayerReference)>,
+    options: &'a PdfOptions,
+    exporter: &'a Exporter<'a>,
+    note: &'a Path,
+    /// Distance of the next line from the top of the page, in millimetres.
+    y: f32,
+    /// Positions of the bars drawn left of quoted lines.
+    quote_bars: Vec<f32>,
+    list_depth: usize,
+}
+
+impl Writer<'_> {
+    fn layer(&self) -> &PdfLayerReference {
+        &self.pages[self.pages.len() - 1].1
+    }
+
+    fn content_width(&self) -> f32 {
+        self.options.page_size.0 - 2.0 * self.options.margin
+    }
+
+    fn new_page(&mut self) {
+        let (width, height) = self.options.page_size;
+        let (page, layer) = self.doc.add_page(Mm(width), Mm(height), "内容");
+        let layer = self.doc.get_page(page).get_layer(layer);
+        self.pages.push((page, layer));
+        self.y = self.options.margin;
+    }
+
+    /// Starts a new page unless `height` still fits on this one.
+    fn ensure(&mut self, height: f32) {
+        if self.y + height > self.options.page_size.1 - self.options.margin && self.y > self.options.margin + 0.1 {
+            self.new_page();
+        }
+    }
+
+    fn place(&self, line: &mut Vec<Piece>, x: &mut f32, c: char, style: Style, size: f32) {
+        let (face, c) = self.fonts.face(style, c);
+        let size = if style.code { size * 0.9 } else { size };
+        let advance = self.fonts.advance(face, c, size);
+        match line.last_mut() {
+            Some(piece) if piece.face == face && piece.style == style => {
+                piece.text.push(c);
+                piece.width += advance;
+            },
+            _ => line.push(Piece { text: c.to_string(), face, style, size, x: *x, width: advance }),
+        }
+        *x += advance;
+    }
+
+    fn measure(&self, word: &[(char, Style)], size: f32) -> f32 {
+        word.iter().map(|&(c, style)| {
+            let (face, c) = self.fonts.face(style, c);
+            self.fonts.advance(face, c, if style.code { size * 0.9 } else { size })
+        }).sum()
+    }
+
+    /// Breaks text into lines at most `width` wide, between words or after
+    /// any CJK character. Words longer than a line are split anywhere.
+    fn wrap(&self, spans: &[Span], size: f32, width: f32) -> Vec<Vec<Piece>> {
+        let mut lines = vec![Vec::new()];
+        let mut x = 0.0;
+        let mut word: Vec<(char, Style)> = Vec::new();
+        let mut space: Option<Style> = None;
+        let flush = |writer: &Self, lines: &mut Vec<Vec<Piece>>, x: &mut f32, word: &mut Vec<(char, Style)>, space: &mut Option<Style>| {
+            if word.is_empty() {
+                return;
+            }
+            let space_width = space.map_or(0.0, |style| writer.measure(&[(' ', style)], size));
+            if *x > 0.0 && *x + space_width + writer.measure(word, size) > width {
+                lines.push(Vec::new());
+                *x = 0.0;
+            } else if let (Some(style), Some(line)) = (space.filter(|_| *x > 0.0), lines.last_mut()) {
+                writer.place(line, x, ' ', style, size);
+            }
+            *space = None;
+            for (c, style) in word.drain(..) {
+                if *x > 0.0 && *x + writer.measure(&[(c, style)], size) > width {
+                    lines.push(Vec::new());
+                    *x = 0.0;
+                }
+                if let Some(line) = lines.last_mut() {
+                    writer.place(line, x, c, style, size);
+                }
+            }
+        };
+        for span in spans {
+            for c in span.text.chars() {
+                if c == '\n' {
+                    flush(self, &mut lines, &mut x, &mut word, &mut space);
+                    lines.push(Vec::new());
+                    x = 0.0;
+                    space = None;
+                } else if c.is_whitespace() {
+                    flush(self, &mut lines, &mut x, &mut word, &mut space);
+                    space = Some(span.style);
+                } else {
+                    word.push((c, span.style));
+                    if breaks_anywhere(c) {
+                        flush(self, &mut lines, &mut x, &mut word, &mut space);
+                    }
+                }
+            }
+        }
+        flush(self, &mut lines, &mut x, &mut word, &mut space);
+        lines
+    }
+
+    /// Draws one line of pieces whose top is at `self.y`.
+    fn draw_line(&self, line: &[Piece], left: f32, line_height: f32, size: f32) {
+        let page_height = self.options.page_size.1;
+        let baseline = self.y + (line_height + size * PT * 0.7) / 2.0;
+        let layer = self.layer();
+        for &bar in &self.quote_bars {
+            layer.set_outline_color(color(0xdd, 0xdd, 0xdd));
+            layer.set_outline_thickness(2.0);
+            layer.add_line(Line {
+                points: vec![
+                    (Point::new(Mm(bar), Mm(page_height - self.y)), false),
+                    (Point::new(Mm(bar), Mm(page_height - self.y - line_height)), false),
+                ],
+                is_closed: false,
+            });
+        }
+        for piece in line {
+            if piece.style.code {
+                layer.set_fill_color(color(0xf0, 0xf0, 0xf0));
+                let top = page_height - baseline + piece.size * PT * 0.85;
+                layer.add_rect(Rect::new(Mm(left + piece.x), Mm(top - piece.size * PT * 1.15), Mm(left + piece.x + piece.width), Mm(top)));
+            }
+            layer.set_fill_color(if piece.style.link {
+                color(0x1f, 0x6f, 0xeb)
+            } else if self.quote_bars.is_empty() {
+                color(0x22, 0x22, 0x22)
+            } else {
+                color(0x66, 0x66, 0x66)
+            });
+            // 外部字体没有粗体，用描边加粗
+            let fake_bold = piece.face == Face::External && piece.style.bold;
+            if fake_bold {
+                layer.set_text_rendering_mode(TextRenderingMode::FillStroke);
+                layer.set_outline_thickness(piece.size / 30.0);
+                layer.set_outline_color(color(0x22, 0x22, 0x22));
+            }
+            layer.use_text(piece.text.as_str(), piece.size, Mm(left + piece.x), Mm(page_height - baseline), self.fonts.font(piece.face));
+            if fake_bold {
+                layer.set_text_rendering_mode(TextRenderingMode::Fill);
+            }
+        }
+    }
+
+    fn paragraph(&mut self, spans: &[Span], size: f32, indent: f32) {
+        let line_height = size * PT * 1.5;
+        let left = self.options.margin + indent;
+        for line in self.wrap(spans, size, self.content_width() - indent) {
+            self.ensure(line_height);
+            self.draw_line(&line, left, line_height, size);
+            self.y += line_height;
+        }
+    }
+
+    fn code(&mut self, code: &str, indent: f32) {
+        let line_height = CODE_SIZE * PT * 1.45;
+        let left = self.options.margin + indent;
+        let width = self.content_width() - indent;
+        let style = Style { code: true, ..Style::default() };
+        let page_height = self.options.page_size.1;
+        self.y += 1.0;
+        for source_line in code.split('\n') {
+            // 代码保留空白，超出宽度时按字符折行
+            let mut lines = vec![Vec::new()];
+            let mut x = 0.0;
+            for c in source_line.replace('\t', "    ").chars() {
+                if x + self.measure(&[(c, style)], CODE_SIZE / 0.9) > width - 4.0 {
+                    lines.push(Vec::new());
+                    x = 0.0;
+                }
+                if let Some(line) = lines.last_mut() {
+                    self.place(line, &mut x, c, style, CODE_SIZE / 0.9);
+                }
+            }
+            for mut line in lines {
+                self.ensure(line_height);
+                let layer = self.layer();
+                layer.set_fill_color(color(0xf6, 0xf8, 0xfa));
+                layer.add_rect(Rect::new(Mm(left), Mm(page_height - self.y - line_height), Mm(left + width), Mm(page_height - self.y)));
+                for piece in &mut line {
+                    // 背景已经画过了
+                    piece.style.code = false;
+                    piece.x += 2.0;
+                }
+                self.draw_line(&line, left, line_height, CODE_SIZE);
+                self.y += line_height;
+            }
+        }
+        self.y += 3.0;
+    }
+
+    fn table(&mut self, rows: &[Vec<Vec<Span>>], indent: f32) {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let available = self.content_width() - indent;
+        let mut widths = vec![12.0_f32; columns];
+        for row in rows {
+            for (column, cell) in row.iter().enumerate() {
+                let natural = cell.iter().map(|span| {
+                    let chars = span.text.chars().map(|c| (c, Style { bold: true, ..span.style })).collect::<Vec<_>>();
+                    self.measure(&chars, TABLE_SIZE)
+                }).sum::<f32>() + 2.0 * CELL_PADDING + 1.0;
+                widths[column] = widths[column].max(natural);
+            }
+        }
+        let total: f32 = widths.iter().sum();
+        if total > available {
+            // 窄的列保持原宽，剩下的空间按比例分给宽的列
+            let fair = available / columns as f32;
+            let narrow: f32 = widths.iter().filter(|width| **width <= fair).sum();
+            let wide: f32 = total - narrow;
+            widths.iter_mut().filter(|width| **width > fair).for_each(|width| *width *= (available - narrow) / wide);
+        }
+
+        let line_height = TABLE_SIZE * PT * 1.45;
+        let left = self.options.margin + indent;
+        let page_height = self.options.page_size.1;
+        for (i, row) in rows.iter().enumerate() {
+            let cells = (0..columns).map(|column| {
+                let mut spans = row.get(column).cloned().unwrap_or_default();
+                if i == 0 {
+                    spans.iter_mut().for_each(|span| span.style.bold = true);
+                }
+                self.wrap(&spans, TABLE_SIZE, widths[column] - 2.0 * CELL_PADDING)
+            }).collect::<Vec<_>>();
+            let row_height = cells.iter().map(Vec::len).max().unwrap_or(1) as f32 * line_height + 2.0 * CELL_PADDING;
+            self.ensure(row_height);
+            let top = self.y;
+            let mut x = left;
+            for (column, lines) in cells.iter().enumerate() {
+                let rect = Rect::new(Mm(x), Mm(page_height - top - row_height), Mm(x + widths[column]), Mm(page_height - top));
+                if i == 0 {
+                    self.layer().set_fill_color(color(0xf6, 0xf8, 0xfa));
+                    self.layer().add_rect(rect);
+                }
+                self.layer().set_outline_color(color(0xcc, 0xcc, 0xcc));
+                self.layer().set_outline_thickness(0.5);
+                self.layer().add_rect(rect.with_mode(PaintMode::Stroke).with_winding(WindingOrder::NonZero));
+                self.y = top + CELL_PADDING;
+                for line in lines {
+                    self.draw_line(line, x + CELL_PADDING, line_height, TABLE_SIZE);
+                    self.y += line_height;
+                }
+                x += widths[column];
+            }
+            self.y = top + row_height;
+        }
+        self.y += 3.0;
+    }
+
+    fn image(&mut self, url: &str, alt: &str, indent: f32) {
+        let image = self.exporter.link_target(self.note, url)
+            .ok_or_else(|| "不是笔记库中的文件".to_string())
+            .and_then(|(path, _)| printpdf::image_crate::open(&path).map_err(|e| e.to_string()));
+        let image = match image {
+            Ok(image) => image,
+            Err(e) => {
+                eprintln!("Failed to embed image {}: {}", url, e);
+                let text = if alt.is_empty() { url } else { alt };
+                self.paragraph(&[Span { text: format!("[{}]", text), style: Style { italic: true, ..Style::default() } }], BODY_SIZE, indent);
+                return;
+            },
+        };
+        // 按 96 DPI 的原始大小，放不下时缩小
+        let natural = (image.width() as f32 * 25.4 / 96.0, image.height() as f32 * 25.4 / 96.0);
+        let max_height = self.options.page_size.1 - 2.0 * self.options.margin;
+        let scale = ((self.content_width() - indent) / natural.0).min(max_height / natural.1).min(1.0);
+        let height = natural.1 * scale;
+        self.ensure(height);
+        Image::from_dynamic_image(&image).add_to_layer(self.layer().clone(), ImageTransform {
+            translate_x: Some(Mm(self.options.margin + indent)),
+            translate_y: Some(Mm(self.options.page_size.1 - self.y - height)),
+            scale_x: Some(scale),
+            scale_y: Some(scale),
+            dpi: Some(96.0),
+            ..Default::default()
+        });
+        self.y += height + 3.0;
+    }
+
+    fn blocks(&mut self, blocks: &[Block], indent: f32, tight: bool) {
+        let gap = if tight { 1.0 } else { 3.0 };
+        for block in blocks {
+            match block {
+                Block::Heading(level, inlines) => {
+                    let size = HEADING_SIZES[*level.clamp(&1, &6) as usize - 1];
+                    if self.y > self.options.margin + 0.1 {
+                        self.y += 3.0;
+                    }
+                    // 标题不单独留在页尾
+                    self.ensure(size * PT * 1.5 + BODY_SIZE * PT * 3.0);
+                    if *level <= 3 {
+                        self.doc.add_bookmark(document::plain_text(inlines), self.pages[self.pages.len() - 1].0);
+                    }
+                    let mut spans = spans(inlines);
+                    spans.iter_mut().for_each(|span| span.style.bold = true);
+                    self.paragraph(&spans, size, indent);
+                    self.y += 2.0;
+                },
+                Block::Paragraph(inlines) => {
+                    self.paragraph(&spans(inlines), BODY_SIZE, indent);
+                    self.y += gap;
+                },
+                Block::Code { code, .. } => self.code(code, indent),
+                Block::List { start, items } => {
+                    let line_height = BODY_SIZE * PT * 1.5;
+                    for (i, item) in items.iter().enumerate() {
+                        let marker = match (item.task, start) {
+                            (Some(true), _) => "[x]".to_string(),
+                            (Some(false), _) => "[ ]".to_string(),
+                            (None, Some(start)) => format!("{}.", start + i as u64),
+                            (None, None) if self.list_depth == 0 => "•".to_string(),
+                            (None, None) => "–".to_string(),
+                        };
+                        self.ensure(line_height);
+                        let mut line = Vec::new();
+                        let mut x = 0.0;
+                        for c in marker.chars() {
+                            self.place(&mut line, &mut x, c, Style::default(), BODY_SIZE);
+                        }
+                        self.draw_line(&line, self.options.margin + indent + 1.0, line_height, BODY_SIZE);
+                        self.list_depth += 1;
+                        self.blocks(&item.blocks, indent + LIST_INDENT, true);
+                        self.list_depth -= 1;
+                    }
+                    if self.list_depth == 0 {
+                        self.y += 2.0;
+                    }
+                },
+                Block::Quote(inner) => {
+                    self.quote_bars.push(self.options.margin + indent + 1.0);
+                    self.blocks(inner, indent + 5.0, tight);
+                    self.quote_bars.pop();
+                    self.y += 1.0;
+                },
+                Block::Table { rows, .. } => {
+                    let rows = rows.iter().map(|row| row.iter().map(|cell| spans(cell)).collect()).collect::<Vec<_>>();
+                    self.table(&rows, indent);
+                },
+                Block::Image { url, alt } => self.image(url, alt, indent),
+                Block::Footnote { label, blocks } => {
+                    self.paragraph(&[Span { text: format!("[{}]", label), style: Style::default() }], BODY_SIZE, indent);
+                    self.blocks(blocks, indent, tight);
+                },
+                Block::Rule => {
+                    self.ensure(6.0);
+                    let y = self.options.page_size.1 - self.y - 3.0;
+                    let layer = self.layer();
+                    layer.set_outline_color(color(0xdd, 0xdd, 0xdd));
+                    layer.set_outline_thickness(0.5);
+                    layer.add_line(Line {
+                        points: vec![
+                            (Point::new(Mm(self.options.margin + indent), Mm(y)), false),
+                            (Point::new(Mm(self.options.page_size.0 - self.options.margin), Mm(y)), false),
+                        ],
+                        is_closed: false,
+                    });
+                    self.y += 6.0;
+                },
+            }
+        }
+    }
+
+    fn page_numbers(&self) {
+        let total = self.pages.len();
+        let size = 9.0;
+        for (i, (_, layer)) in self.pages.iter().enumerate() {
+            let text = format!("{} / {}", i + 1, total);
+            let width: f32 = text.chars().map(|c| self.fonts.advance(Face::Builtin(0), c, size)).sum();
+            layer.set_fill_color(color(0x88, 0x88, 0x88));
+            layer.use_text(text, size, Mm((self.options.page_size.0 - width) / 2.0), Mm(self.options.margin / 2.0), &self.fonts.builtin[0]);
+        }
+    }
+}
+
+fn pdf_error(e: printpdf::Error) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+/// Lays out one note as a PDF document.
+pub fn render(exporter: &Exporter, note: &Path, markdown: &str, options: &PdfOptions) -> io::Result<Vec<u8>> {
+    let body = export::wikilinks_to_markdown(exporter.index, note, front_matter::body(markdown));
+    let font_data = if body.chars().any(|c| !c.is_whitespace() && !is_win_ansi(c)) {
+        let data = load_font(options)?;
+        if data.is_none() {
+            // 没有字体时这些字符显示为 ?
+            eprintln!("No font for some characters in {}, set pdf_font in the config", note.display());
+        }
+        data
+    } else {
+        None
+    };
+
+    let (width, height) = options.page_size;
+    let (doc, page, layer) = PdfDocument::new(export::note_title(note, markdown), Mm(width), Mm(height), "内容");
+    let builtin = BUILTIN_FONTS.iter()
+        .map(|font| doc.add_builtin_font(*font))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(pdf_error)?;
+    let external = match &font_data {
+        Some(data) => {
+            let font = doc.add_external_font(data.as_slice()).map_err(pdf_error)?;
+            let face = ttf_parser::Face::parse(data, 0).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            Some((font, face))
+        },
+        None => None,
+    };
+
+    let layer = doc.get_page(page).get_layer(layer);
+    let mut writer = Writer {
+        doc,
+        fonts: Fonts { builtin, external },
+        pages: vec![(page, layer)],
+        options,
+        exporter,
+        note,
+        y: options.margin,
+        quote_bars: Vec::new(),
+        list_depth: 0,
+    };
+    writer.blocks(&document::parse(&body), 0.0, false);
+    writer.page_numbers();
+    writer.doc.save_to_bytes().map_err(pdf_error)
+}
+
+pub fn export(
+    exporter: &Exporter,
+    notes: &[PathBuf],
+    content: impl Fn(&Path) -> io::Result<String>,
+    options: &PdfOptions,
+) -> io::Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for note in notes {
+        let pdf = render(exporter, note, &content(note)?, options)?;
+        let out_file = exporter.output_path(note, "pdf");
+        if let Some(parent) = out_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&out_file, pdf)?;
+        written.push(out_file);
+    }
+    Ok(written)
+}
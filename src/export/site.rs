@@ -0,0 +1,381 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use pulldown_cmark::{Event, Parser};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::config::Config;
+use crate::export::{self, html, Exporter};
+use crate::markdown::front_matter::{self, Property, PropertyValue};
+use crate::markdown::render::parser_options;
+use crate::markdown::tags;
+use crate::vault::{self, links::{self, LinkIndex}};
+
+/// Written into the output folder so a later run knows it may clear it.
+const MARKER: &str = ".nodian-site";
+const FEED_ENTRIES: usize = 50;
+/// 以 _ 开头，不会和笔记 tags.md 生成的页面重名
+const TAGS_PAGE: &str = "_tags.html";
+
+const SITE_STYLE: &str = r#"
+header { max-width: 760px; margin: 0 auto; padding: 12px 24px; display: flex; gap: 16px; align-items: center; }
+header .site { font-weight: 600; color: #222; }
+header input { margin-left: auto; padding: 4px 8px; border: 1px solid #ddd; border-radius: 4px; }
+#results { max-width: 760px; margin: 0 auto; padding: 0 24px 0 48px; }
+.meta { color: #888; font-size: 0.9em; }
+.tags a { margin-right: 8px; }
+.backlinks { margin-top: 48px; padding-top: 12px; border-top: 1px solid #eee; font-size: 0.95em; }
+ul.listing { padding-left: 1.2em; }
+"#;
+
+const SEARCH_SCRIPT: &str = r#"
+const box = document.getElementById('search'), results = document.getElementById('results');
+let index;
+box.addEventListener('input', async () => {
+  const query = box.value.trim().toLowerCase();
+  if (!query) { results.innerHTML = ''; return; }
+  index = index || await (await fetch(ROOT + 'search.json')).json();
+  const escape = text => text.replace(/&/g, '&amp;').replace(/</g, '&lt;');
+  results.innerHTML = index
+    .filter(page => [page.title, page.tags.join(' '), page.text].join(' ').toLowerCase().includes(query))
+    .slice(0, 20)
+    .map(page => `<li><a href="${ROOT + page.url}">${escape(page.title)}</a></li>`)
+    .join('');
+});
+"#;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SiteReport {
+    pub pages: usize,
+    /// Notes left out because of `publish: false`.
+    pub skipped: usize,
+    pub assets: usize,
+}
+
+struct Page {
+    note: PathBuf,
+    title: String,
+    date: Option<NaiveDateTime>,
+    tags: Vec<String>,
+    markdown: String,
+}
+
+fn is_unpublished(properties: &[Property]) -> bool {
+    properties.iter().any(|property| property.key == "publish" && match &property.value {
+        PropertyValue::Checkbox(publish) => !publish,
+        PropertyValue::Text(text) => matches!(text.trim().to_ascii_lowercase().as_str(), "false" | "no"),
+        _ => false,
+    })
+}
+
+/// Date of a note for the feed, from `date`, `published` or `created`.
+fn note_date(properties: &[Property]) -> Option<NaiveDateTime> {
+    ["date", "published", "created"].iter().find_map(|key| {
+        let property = properties.iter().find(|property| property.key == *key)?;
+        let (PropertyValue::Date(text) | PropertyValue::Text(text)) = &property.value else { return None };
+        let text = text.trim();
+        let date = NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()?;
+        let time = text.get(10..).map(|time| time.trim_start_matches(['T', ' '])).and_then(|time| {
+            ["%H:%M:%S", "%H:%M"].iter().find_map(|format| chrono::NaiveTime::parse_from_str(time, format).ok())
+        });
+        Some(date.and_time(time.unwrap_or_default()))
+    })
+}
+
+fn plain_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new_ext(markdown, parser_options()) {
+        match event {
+            Event::Text(part) | Event::Code(part) => text.push_str(&part),
+            Event::SoftBreak | Event::HardBreak | Event::End(_) if !text.ends_with(' ') => text.push(' '),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `../` repeated once per folder between the site root and `file`.
+fn root_prefix(out_dir: &Path, file: &Path) -> String {
+    let depth = file.strip_prefix(out_dir).map_or(0, |relative| relative.components().count().saturating_sub(1));
+    "../".repeat(depth)
+}
+
+/// URL of `file` relative to the site root.
+fn site_url(out_dir: &Path, file: &Path) -> String {
+    links::encode_path(&vault::relative_name(out_dir, file))
+}
+
+fn tag_url(tag: &str) -> String {
+    format!("{}#{}", TAGS_PAGE, export::slug(tag))
+}
+
+fn write(file: &Path, content: &str) -> io::Result<()> {
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(file, content)
+}
+
+/// Empties the output folder, refusing to touch one this generator didn't
+/// create.
+fn prepare_output(root: &Path, out_dir: &Path) -> io::Result<()> {
+    if root.starts_with(out_dir) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} 包含笔记库，不能作为输出目录", out_dir.display())));
+    }
+    if out_dir.exists() {
+        let is_empty = fs::read_dir(out_dir)?.next().is_none();
+        if !is_empty && !out_dir.join(MARKER).exists() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} 不是生成的站点，不会覆盖", out_dir.display())));
+        }
+        fs::remove_dir_all(out_dir)?;
+    }
+    fs::create_dir_all(out_dir)?;
+    fs::write(out_dir.join(MARKER), "")
+}
+
+struct Site<'a> {
+    title: String,
+    url: String,
+    out_dir: &'a Path,
+}
+
+impl Site<'_> {
+    fn layout(&self, file: &Path, title: &str, body: &str) -> String {
+        let root = root_prefix(self.out_dir, file);
+        let page_title = if title == self.title { title.to_string() } else { format!("{} - {}", title, self.title) };
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{title}</title>\n<link rel=\"alternate\" type=\"application/atom+xml\" href=\"{root}feed.xml\">\n<style>{style}{site_style}</style>\n</head>\n<body>\n<header><a class=\"site\" href=\"{root}index.html\">{site}</a><a href=\"{root}{tags}\">标签</a><input id=\"search\" type=\"search\" placeholder=\"搜索\"></header>\n<ul id=\"results\"></ul>\n<article>\n{body}</article>\n<script>const ROOT = \"{root}\";{script}</script>\n</body>\n</html>\n",
+            title = html::escape(&page_title),
+            root = root,
+            style = html::STYLE,
+            site_style = SITE_STYLE,
+            site = html::escape(&self.title),
+            tags = TAGS_PAGE,
+            body = body,
+            script = SEARCH_SCRIPT,
+        )
+    }
+
+    fn absolute_url(&self, relative: &str) -> String {
+        if self.url.is_empty() {
+            relative.to_string()
+        } else {
+            format!("{}/{}", self.url.trim_end_matches('/'), relative)
+        }
+    }
+}
+
+/// Generates a static site from every note in the vault except templates
+/// and notes with `publish: false`: one page per note with its tags and
+/// backlinks, an index page per folder, `_tags.html`, `search.json` for the
+/// search box and an Atom `feed.xml` of the dated notes. Files linked from
+/// published notes are copied along.
+pub fn publish(root: &Path, config: &Config, out_dir: &Path) -> io::Result<SiteReport> {
+    let index = LinkIndex::build(root)?;
+    let templates_dir = config.templates_dir(root);
+    let mut report = SiteReport::default();
+
+    let mut pages = Vec::new();
+    for note in vault::vault_files(root)? {
+        if !vault::is_note(&note) || note.starts_with(&templates_dir) || note.starts_with(out_dir) {
+            continue;
+        }
+        let markdown = fs::read_to_string(&note)?;
+        let properties = front_matter::parse(&markdown).unwrap_or_default();
+        if is_unpublished(&properties) {
+            report.skipped += 1;
+            continue;
+        }
+        pages.push(Page {
+            title: export::note_title(&note, &markdown),
+            date: note_date(&properties),
+            tags: tags::extract_tags(&markdown, &properties),
+            note,
+            markdown,
+        });
+    }
+    let published: HashSet<PathBuf> = pages.iter().map(|page| page.note.clone()).collect();
+
+    prepare_output(root, out_dir)?;
+    let exporter = Exporter {
+        root,
+        index: &index,
+        base: root.to_path_buf(),
+        out_dir: out_dir.to_path_buf(),
+        published: Some(&published),
+        copy_assets: true,
+    };
+    let site = Site {
+        title: if config.site_title.is_empty() {
+            root.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+        } else {
+            config.site_title.clone()
+        },
+        url: config.site_url.clone(),
+        out_dir,
+    };
+
+    // 反向链接和需要复制的附件
+    let mut backlinks: HashMap<PathBuf, BTreeSet<PathBuf>> = HashMap::new();
+    let mut assets = BTreeSet::new();
+    for (note, note_links) in index.notes().filter(|(note, _)| published.contains(*note)) {
+        for link in note_links {
+            let target = index.resolve(note, link);
+            if published.contains(&target) && &target != note {
+                backlinks.entry(target).or_default().insert(note.clone());
+            } else if !vault::is_note(&target) && index.exists(&target) {
+                assets.insert(target);
+            }
+        }
+    }
+    for asset in &assets {
+        let copy = exporter.asset_path(asset);
+        if let Some(parent) = copy.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(asset, copy)?;
+    }
+    report.assets = assets.len();
+
+    let titles: HashMap<&Path, &str> = pages.iter().map(|page| (page.note.as_path(), page.title.as_str())).collect();
+    let mut folders: BTreeMap<PathBuf, Vec<&Page>> = BTreeMap::new();
+    for page in &pages {
+        let mut folder = page.note.parent().unwrap_or(root).to_path_buf();
+        folders.entry(folder.clone()).or_default().push(page);
+        // 上层文件夹也要有索引页
+        while folder != root && folder.pop() {
+            folders.entry(folder.clone()).or_default();
+        }
+    }
+    folders.entry(root.to_path_buf()).or_default();
+    let listing = |folder: &Path, page_file: &Path| {
+        let page_dir = page_file.parent().unwrap_or(out_dir);
+        let mut items = String::new();
+        for subfolder in folders.keys().filter(|other| other.parent() == Some(folder)) {
+            let name = subfolder.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            let url = links::relative_path(page_dir, &exporter.asset_path(subfolder).join("index.html"));
+            items.push_str(&format!("<li><a href=\"{}\">{}/</a></li>\n", links::encode_path(&url.to_string_lossy().replace('\\', "/")), html::escape(&name)));
+        }
+        for page in folders.get(folder).into_iter().flatten() {
+            let out_file = exporter.output_path(&page.note, "html");
+            if out_file == page_file {
+                continue;
+            }
+            let url = links::relative_path(page_dir, &out_file);
+            let date = page.date.map(|date| format!(" <span class=\"meta\">{}</span>", date.format("%Y-%m-%d"))).unwrap_or_default();
+            items.push_str(&format!("<li><a href=\"{}\">{}</a>{}</li>\n", links::encode_path(&url.to_string_lossy().replace('\\', "/")), html::escape(&page.title), date));
+        }
+        format!("<ul class=\"listing\">\n{}</ul>\n", items)
+    };
+
+    let mut search = Vec::new();
+    let mut tag_pages: BTreeMap<&str, Vec<&Page>> = BTreeMap::new();
+    for page in &pages {
+        let out_file = exporter.output_path(&page.note, "html");
+        let root_url = root_prefix(out_dir, &out_file);
+        let mut body = html::render_body(&exporter, &page.note, &page.markdown);
+        if page.date.is_some() || !page.tags.is_empty() {
+            let date = page.date.map(|date| format!("<span class=\"meta\">{}</span> ", date.format("%Y-%m-%d"))).unwrap_or_default();
+            let tag_links = page.tags.iter()
+                .map(|tag| format!("<a href=\"{}{}\">#{}</a>", root_url, tag_url(tag), html::escape(tag)))
+                .collect::<String>();
+            body = format!("<p class=\"tags\">{}{}</p>\n{}", date, tag_links, body);
+        }
+        if page.note.file_name().is_some_and(|name| name == "index.md") {
+            body.push_str(&listing(page.note.parent().unwrap_or(root), &out_file));
+        }
+        if let Some(sources) = backlinks.get(&page.note) {
+            body.push_str("<section class=\"backlinks\">\n<h4>反向链接</h4>\n<ul>\n");
+            for source in sources {
+                let url = links::relative_path(out_file.parent().unwrap_or(out_dir), &exporter.output_path(source, "html"));
+                let title = titles.get(source.as_path()).copied().unwrap_or_default();
+                body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", links::encode_path(&url.to_string_lossy().replace('\\', "/")), html::escape(title)));
+            }
+            body.push_str("</ul>\n</section>\n");
+        }
+        write(&out_file, &site.layout(&out_file, &page.title, &body))?;
+        report.pages += 1;
+
+        for tag in &page.tags {
+            tag_pages.entry(tag).or_default().push(page);
+        }
+        search.push(serde_json::json!({
+            "title": page.title,
+            "url": site_url(out_dir, &out_file),
+            "tags": page.tags,
+            "text": plain_text(&export::wikilinks_to_markdown(&index, &page.note, front_matter::body(&page.markdown))),
+        }));
+    }
+
+    // 没有 index.md 的文件夹生成目录页
+    for folder in folders.keys() {
+        if published.contains(&folder.join("index.md")) {
+            continue;
+        }
+        let out_file = exporter.asset_path(folder).join("index.html");
+        let title = if folder == root {
+            site.title.clone()
+        } else {
+            vault::relative_name(root, folder)
+        };
+        let body = format!("<h1>{}</h1>\n{}", html::escape(&title), listing(folder, &out_file));
+        write(&out_file, &site.layout(&out_file, &title, &body))?;
+    }
+
+    let tags_file = out_dir.join(TAGS_PAGE);
+    let mut body = String::from("<h1>标签</h1>\n");
+    for (tag, tagged) in &tag_pages {
+        body.push_str(&format!("<h2 id=\"{}\">#{}</h2>\n<ul class=\"listing\">\n", html::escape(&export::slug(tag)), html::escape(tag)));
+        for page in tagged {
+            let url = site_url(out_dir, &exporter.output_path(&page.note, "html"));
+            body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", url, html::escape(&page.title)));
+        }
+        body.push_str("</ul>\n");
+    }
+    write(&tags_file, &site.layout(&tags_file, "标签", &body))?;
+
+    let search = serde_json::to_string(&search).map_err(io::Error::other)?;
+    write(&out_dir.join("search.json"), &search)?;
+    write(&out_dir.join("feed.xml"), &feed(&site, &exporter, &pages))?;
+    Ok(report)
+}
+
+fn rfc3339(date: NaiveDateTime) -> String {
+    Local.from_local_datetime(&date).earliest()
+        .map(|date| date.to_rfc3339())
+        .unwrap_or_else(|| date.and_utc().to_rfc3339())
+}
+
+/// Atom feed of the most recent dated notes.
+fn feed(site: &Site, exporter: &Exporter, pages: &[Page]) -> String {
+    let mut dated: Vec<&Page> = pages.iter().filter(|page| page.date.is_some()).collect();
+    dated.sort_by_key(|page| std::cmp::Reverse(page.date));
+    dated.truncate(FEED_ENTRIES);
+    let updated = dated.first().and_then(|page| page.date).map(rfc3339)
+        .unwrap_or_else(|| Local::now().to_rfc3339());
+    let id = |url: &str| if site.url.is_empty() { format!("urn:nodian:{}", url) } else { site.absolute_url(url) };
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("<title>{}</title>\n", html::escape(&site.title)));
+    xml.push_str(&format!("<link href=\"{}\"/>\n", html::escape(&site.absolute_url("index.html"))));
+    xml.push_str(&format!("<link rel=\"self\" href=\"{}\"/>\n", html::escape(&site.absolute_url("feed.xml"))));
+    xml.push_str(&format!("<id>{}</id>\n<updated>{}</updated>\n", html::escape(&id("")), updated));
+    for page in dated {
+        let out_file = exporter.output_path(&page.note, "html");
+        let url = site_url(site.out_dir, &out_file);
+        let body = html::render_body(exporter, &page.note, &page.markdown);
+        xml.push_str("<entry>\n");
+        xml.push_str(&format!("<title>{}</title>\n", html::escape(&page.title)));
+        xml.push_str(&format!("<link href=\"{}\"/>\n", html::escape(&site.absolute_url(&url))));
+        xml.push_str(&format!("<id>{}</id>\n", html::escape(&id(&url))));
+        xml.push_str(&format!("<updated>{}</updated>\n", page.date.map(rfc3339).unwrap_or_default()));
+        for tag in &page.tags {
+            xml.push_str(&format!("<category term=\"{}\"/>\n", html::escape(tag)));
+        }
+        // 正文里的相对链接以页面地址为基准
+        xml.push_str(&format!("<content type=\"html\" xml:base=\"{}\">{}</content>\n", html::escape(&site.absolute_url(&url)), html::escape(&body)));
+        xml.push_str("</entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{Local, NaiveDateTime};
+use md5::{Digest, Md5};
+use roxmltree::{Document, Node, ParsingOptions};
+use crate::import::{dedupe, sanitize_file_name, Contents, Import, ImportedFile};
+use crate::markdown::from_html::Converter;
+use crate::markdown::front_matter::{self, Property, PropertyValue};
+use crate::vault::{self, links};
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|child| child.text()).map(str::trim).filter(|text| !text.is_empty())
+}
+
+/// Evernote timestamps look like `20240102T030405Z`.
+fn local_time(timestamp: &str) -> Option<String> {
+    let utc = NaiveDateTime::parse_from_str(timestamp, "%Y%m%dT%H%M%SZ").ok()?.and_utc();
+    Some(utc.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+}
+
+fn extension(mime: &str) -> &str {
+    match mime.rsplit('/').next().unwrap_or_default() {
+        "jpeg" => "jpg",
+        "svg+xml" => "svg",
+        "plain" => "txt",
+        "" => "bin",
+        ext => ext,
+    }
+}
+
+/// Reads an `.enex` export, or a folder of them where each notebook gets a
+/// folder of its own.
+pub fn read(path: &Path) -> io::Result<Import> {
+    let sources: Vec<PathBuf> = if path.is_dir() {
+        vault::vault_files(path)?.into_iter()
+            .filter(|file| file.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("enex")))
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+    let mut import = Import::default();
+    let mut taken = HashSet::new();
+    for source in &sources {
+        let dir = if sources.len() > 1 {
+            let stem = source.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+            format!("{}/", sanitize_file_name(&stem))
+        } else {
+            String::new()
+        };
+        let text = fs::read_to_string(source)?;
+        if let Err(e) = read_notebook(&text, &dir, &mut taken, &mut import) {
+            import.skipped.push(format!("{}: {}", source.display(), e));
+        }
+    }
+    Ok(import)
+}
+
+fn read_notebook(text: &str, dir: &str, taken: &mut HashSet<String>, import: &mut Import) -> Result<(), String> {
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let document = Document::parse_with_options(text, options).map_err(|e| e.to_string())?;
+    for note in document.root_element().children().filter(|node| node.has_tag_name("note")) {
+        let title = child_text(note, "title").unwrap_or("Untitled");
+        let path = dedupe(taken, &format!("{}{}.md", dir, sanitize_file_name(title)));
+
+        // 附件按内容的 MD5 被 <en-media hash="…"> 引用
+        let mut converter = Converter::default();
+        for resource in note.children().filter(|node| node.has_tag_name("resource")) {
+            let encoded = child_text(resource, "data").unwrap_or_default().split_whitespace().collect::<String>();
+            let data = match STANDARD.decode(encoded) {
+                Ok(data) => data,
+                Err(e) => {
+                    import.skipped.push(format!("{} 的附件: {}", title, e));
+                    continue;
+                },
+            };
+            let hash = hex::encode(Md5::digest(&data));
+            let mime = child_text(resource, "mime").unwrap_or_default();
+            let name = child(resource, "resource-attributes")
+                .and_then(|attributes| child_text(attributes, "file-name"))
+                .map(sanitize_file_name)
+                .unwrap_or_else(|| format!("{}.{}", hash, extension(mime)));
+            let attachment = dedupe(taken, &format!("{}attachments/{}", dir, name));
+            let relative = links::relative_path(Path::new(dir), Path::new(&attachment));
+            converter.media.insert(hash, links::encode_path(&vault::relative_name(Path::new(""), &relative)));
+            import.files.push(ImportedFile { path: attachment, contents: Contents::Data(data) });
+        }
+
+        let body = converter.convert(child_text(note, "content").unwrap_or_default());
+        let mut properties = Vec::new();
+        for (key, element) in [("created", "created"), ("updated", "updated")] {
+            if let Some(time) = child_text(note, element).and_then(local_time) {
+                properties.push(Property { key: key.to_string(), value: PropertyValue::Date(time) });
+            }
+        }
+        let tags = note.children()
+            .filter(|node| node.has_tag_name("tag"))
+            .filter_map(|tag| tag.text())
+            .map(|tag| tag.trim().replace(' ', "-"))
+            .filter(|tag| !tag.is_empty())
+            .collect::<Vec<_>>();
+        if !tags.is_empty() {
+            properties.push(Property { key: "tags".to_string(), value: PropertyValue::List(tags) });
+        }
+        let attributes = child(note, "note-attributes");
+        for (key, element) in [("author", "author"), ("source", "source-url")] {
+            if let Some(text) = attributes.and_then(|attributes| child_text(attributes, element)) {
+                properties.push(Property { key: key.to_string(), value: PropertyValue::Text(text.to_string()) });
+            }
+        }
+        let note = front_matter::with_properties(&body, &properties);
+        import.files.push(ImportedFile { path, contents: Contents::Data(note.into_bytes()) });
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::ui::markdown_editor::MarkdownEditor;
+use crate::vault::{self, attachments};
+
+pub mod evernote;
+pub mod notion;
+pub mod obsidian;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    Obsidian,
+    Notion,
+    Evernote,
+}
+
+impl Source {
+    pub fn from_name(name: &str) -> Option<Source> {
+        match name {
+            "obsidian" => Some(Source::Obsidian),
+            "notion" => Some(Source::Notion),
+            "evernote" => Some(Source::Evernote),
+            _ => None,
+        }
+    }
+}
+
+pub enum Contents {
+    Data(Vec<u8>),
+    /// A file on disk copied as it is.
+    Copy(PathBuf),
+}
+
+pub struct ImportedFile {
+    /// Path inside the import folder, with `/` separators.
+    pub path: String,
+    pub contents: Contents,
+}
+
+/// What an importer read from an export, before anything is written.
+#[derive(Default)]
+pub struct Import {
+    pub files: Vec<ImportedFile>,
+    /// Entries that couldn't be converted, with the reason.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub folder: PathBuf,
+    pub notes: usize,
+    pub attachments: usize,
+    pub skipped: Vec<String>,
+}
+
+/// Converts the export at `path` and writes it into a new folder of the
+/// vault named after it. The editor is only locked while a file is written.
+pub fn import(editor: &Mutex<MarkdownEditor>, source: Source, path: &Path) -> io::Result<ImportReport> {
+    let root = editor.lock().unwrap().get_root_dir().to_path_buf();
+    let import = match source {
+        Source::Obsidian => obsidian::read(path)?,
+        Source::Notion => notion::read(path)?,
+        Source::Evernote => evernote::read(path)?,
+    };
+    if import.files.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} 中没有可导入的笔记", path.display())));
+    }
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let folder = attachments::unique_path(&root, &sanitize_file_name(notion::strip_hash(&stem)));
+    let name = folder.file_name().map(PathBuf::from).unwrap_or_default();
+    let mut report = ImportReport { folder, skipped: import.skipped, ..ImportReport::default() };
+    for file in import.files {
+        let relative = name.join(&file.path);
+        match file.contents {
+            Contents::Data(data) => editor.lock().unwrap().write_file(&relative, &data)?,
+            Contents::Copy(source) => editor.lock().unwrap().copy_file(&relative, &source)?,
+        };
+        if vault::is_note(&relative) {
+            report.notes += 1;
+        } else {
+            report.attachments += 1;
+        }
+    }
+    Ok(report)
+}
+
+/// Replaces characters that are not allowed in file names.
+pub fn sanitize_file_name(name: &str) -> String {
+    let name = name.trim().replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
+    let name = name.trim_start_matches('.').trim();
+    if name.is_empty() { "Untitled".to_string() } else { name.to_string() }
+}
+
+/// Appends ` 1`, ` 2`... to the file stem until `path` isn't in `taken`,
+/// ignoring case as some file systems do.
+pub fn dedupe(taken: &mut HashSet<String>, path: &str) -> String {
+    let (dir, file) = path.rsplit_once('/').map_or(("", path), |(dir, file)| (dir, file));
+    let (stem, ext) = match file.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (file, String::new()),
+    };
+    std::iter::once(path.to_string())
+        .chain((1..).map(|n| {
+            let file = format!("{} {}{}", stem, n, ext);
+            if dir.is_empty() { file } else { format!("{}/{}", dir, file) }
+        }))
+        .find(|candidate| taken.insert(candidate.to_lowercase()))
+        .unwrap()
+}
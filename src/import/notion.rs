@@ -0,0 +1,234 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek};
+use std::path::Path;
+use pulldown_cmark::{Event, Parser, Tag};
+use zip::ZipArchive;
+use crate::import::{dedupe, sanitize_file_name, Contents, Import, ImportedFile};
+use crate::markdown::render::parser_options;
+use crate::vault::{self, links};
+
+/// Removes the ` 0123…` page id Notion appends to file and folder names.
+pub fn strip_hash(name: &str) -> &str {
+    match name.rsplit_once(' ') {
+        Some((title, id)) if id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit()) => title,
+        _ => name,
+    }
+}
+
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => (&name[..dot], &name[dot..]),
+        _ => (name, ""),
+    }
+}
+
+/// `Tasks 0123….csv` and `Tasks 0123…_all.csv` are both the `Tasks` database.
+fn database_name(path: &str) -> &str {
+    let stem = path.strip_suffix(".csv").unwrap_or(path);
+    stem.strip_suffix("_all").unwrap_or(stem)
+}
+
+fn is_csv(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".csv")
+}
+
+/// The path of an entry without page ids; databases become notes.
+fn clean_path(path: &str) -> String {
+    let mut components = path.split('/').collect::<Vec<_>>();
+    let file = components.pop().unwrap_or_default();
+    let mut cleaned = components.into_iter()
+        .map(|dir| sanitize_file_name(strip_hash(dir)))
+        .collect::<Vec<_>>();
+    let file = if is_csv(file) {
+        format!("{}.md", sanitize_file_name(strip_hash(database_name(file))))
+    } else {
+        let (stem, ext) = split_extension(file);
+        format!("{}{}", sanitize_file_name(strip_hash(stem)), ext)
+    };
+    cleaned.push(file);
+    cleaned.join("/")
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// Reads the entries of an archive, unpacking the zips Notion nests inside
+/// large exports into the same tree.
+fn read_archive<R: Read + Seek>(archive: &mut ZipArchive<R>, entries: &mut Vec<(String, Vec<u8>)>) -> io::Result<()> {
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(io::Error::other)?;
+        let Some(name) = file.enclosed_name() else { continue };
+        let name = vault::relative_name(Path::new(""), &name);
+        let hidden = name.split('/').any(|component| component.starts_with('.') || component == "__MACOSX");
+        if file.is_dir() || hidden {
+            continue;
+        }
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        if name.to_ascii_lowercase().ends_with(".zip") {
+            drop(file);
+            let mut nested = ZipArchive::new(Cursor::new(data)).map_err(io::Error::other)?;
+            read_archive(&mut nested, entries)?;
+        } else {
+            entries.push((name, data));
+        }
+    }
+    Ok(())
+}
+
+/// Reads a Notion "Markdown & CSV" export, either the zip or the folder it
+/// unpacks to.
+pub fn read(path: &Path) -> io::Result<Import> {
+    let mut entries = Vec::new();
+    if path.is_dir() {
+        for file in vault::vault_files(path)? {
+            entries.push((vault::relative_name(path, &file), fs::read(&file)?));
+        }
+    } else {
+        let mut archive = ZipArchive::new(File::open(path)?).map_err(io::Error::other)?;
+        read_archive(&mut archive, &mut entries)?;
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // 有 _all.csv 时，只含当前视图的 .csv 是多余的
+    let complete = entries.iter()
+        .filter(|(name, _)| name.ends_with("_all.csv"))
+        .map(|(name, _)| database_name(name).to_string())
+        .collect::<HashSet<_>>();
+    let mut dropped = Vec::new();
+    entries.retain(|(name, _)| {
+        let keep = !is_csv(name) || name.ends_with("_all.csv") || !complete.contains(database_name(name));
+        if !keep {
+            dropped.push(name.clone());
+        }
+        keep
+    });
+
+    let mut taken = HashSet::new();
+    let mut renamed = entries.iter()
+        .map(|(name, _)| (name.clone(), dedupe(&mut taken, &clean_path(name))))
+        .collect::<HashMap<_, _>>();
+    // 页面里链接的是视图的 .csv
+    for name in dropped {
+        if let Some(note) = renamed.get(&format!("{}_all.csv", database_name(&name))).cloned() {
+            renamed.insert(name, note);
+        }
+    }
+
+    let mut import = Import::default();
+    for (name, data) in entries {
+        let path = renamed[&name].clone();
+        let data = if is_csv(&name) {
+            match table_note(&name, &data, &path, &renamed) {
+                Ok(note) => note.into_bytes(),
+                Err(e) => {
+                    import.skipped.push(format!("{}: {}", name, e));
+                    continue;
+                },
+            }
+        } else if vault::is_note(Path::new(&name)) {
+            rewrite_links(&String::from_utf8_lossy(&data), &name, &path, &renamed).into_bytes()
+        } else {
+            data
+        };
+        import.files.push(ImportedFile { path, contents: Contents::Data(data) });
+    }
+    Ok(import)
+}
+
+/// A link from the note at `from` to `to`, both paths inside the import.
+fn link_between(from: &str, to: &str) -> String {
+    let relative = links::relative_path(Path::new(parent(from)), Path::new(to));
+    links::encode_path(&vault::relative_name(Path::new(""), &relative))
+}
+
+/// Points links at the renamed files. Notion writes them percent-encoded
+/// and relative to the page.
+fn rewrite_links(markdown: &str, old: &str, new: &str, renamed: &HashMap<String, String>) -> String {
+    let mut replacements = Vec::new();
+    for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+        let (Event::Start(Tag::Link(_, url, _)) | Event::Start(Tag::Image(_, url, _))) = event else { continue };
+        if url.is_empty() || url.starts_with('#') || links::is_external(&url) {
+            continue;
+        }
+        let (target, fragment) = url.split_once('#').map_or((&*url, None), |(target, fragment)| (target, Some(fragment)));
+        let resolved = links::normalize(&Path::new(parent(old)).join(links::decode_path(target)));
+        let Some(renamed_target) = renamed.get(&vault::relative_name(Path::new(""), &resolved)) else { continue };
+        let mut link = link_between(new, renamed_target);
+        if let Some(fragment) = fragment {
+            link.push('#');
+            link.push_str(fragment);
+        }
+        // 目标写在链接源码的最后
+        if let Some(offset) = markdown[range.clone()].rfind(&*url) {
+            replacements.push((range.start + offset, url.len(), link));
+        }
+    }
+    replacements.sort_by_key(|(start, _, _)| *start);
+
+    let mut out = String::with_capacity(markdown.len());
+    let mut copied = 0;
+    for (start, len, link) in replacements {
+        if start < copied {
+            continue;
+        }
+        out.push_str(&markdown[copied..start]);
+        out.push_str(&link);
+        copied = start + len;
+    }
+    out.push_str(&markdown[copied..]);
+    out
+}
+
+fn table_cell(text: &str) -> String {
+    text.trim().replace('|', "\\|").replace("\r\n", "<br>").replace('\n', "<br>")
+}
+
+/// Turns a database CSV into a note with a table. Entries in the first
+/// column link to their pages, which Notion puts in a folder named after
+/// the database.
+fn table_note(old: &str, data: &[u8], new: &str, renamed: &HashMap<String, String>) -> Result<String, String> {
+    let text = String::from_utf8_lossy(data);
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+    let rows = reader.records()
+        .map(|record| record.map(|record| record.iter().map(String::from).collect::<Vec<_>>()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let title = split_extension(new.rsplit('/').next().unwrap_or(new)).0;
+    let Some((header, rows)) = rows.split_first() else {
+        return Ok(format!("# {}\n", title));
+    };
+    let folder = format!("{}/", database_name(old));
+    let pages = renamed.iter()
+        .filter_map(|(old_page, new_page)| {
+            let file = old_page.strip_prefix(&folder).filter(|file| !file.contains('/'))?;
+            let stem = file.strip_suffix(".md")?;
+            Some((strip_hash(stem).to_string(), new_page))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let columns = rows.iter().map(Vec::len).chain([header.len()]).max().unwrap_or(0);
+    let row_line = |cells: Vec<String>| {
+        let mut cells = cells;
+        cells.resize(columns, String::new());
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = vec![
+        row_line(header.iter().map(|cell| table_cell(cell)).collect()),
+        format!("|{}", " --- |".repeat(columns)),
+    ];
+    for row in rows {
+        let cells = row.iter().enumerate().map(|(i, cell)| match pages.get(cell.trim()) {
+            Some(page) if i == 0 => format!("[{}]({})", table_cell(cell), link_between(new, page)),
+            _ => table_cell(cell),
+        }).collect();
+        lines.push(row_line(cells));
+    }
+    Ok(format!("# {}\n\n{}\n", title, lines.join("\n")))
+}
@@ -0,0 +1,16 @@
+use std::io;
+use std::path::Path;
+use crate::import::{Contents, Import, ImportedFile};
+use crate::vault;
+
+/// Reads an Obsidian vault folder. Notes and attachments are copied as they
+/// are; `.obsidian` settings, `.trash` and other hidden entries stay out.
+pub fn read(path: &Path) -> io::Result<Import> {
+    if !path.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} 不是文件夹", path.display())));
+    }
+    let files = vault::vault_files(path)?.into_iter()
+        .map(|file| ImportedFile { path: vault::relative_name(path, &file), contents: Contents::Copy(file) })
+        .collect();
+    Ok(Import { files, skipped: Vec::new() })
+}
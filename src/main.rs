@@ -1,13 +1,29 @@
 mod ui;
 mod db;
+mod calendar;
+mod cli;
+mod config;
+mod export;
+mod import;
+mod markdown;
+mod vault;
 
 use ui::MainWindow;
 use tokio::runtime::Runtime;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(result) = cli::run(&args) {
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let rt = Runtime::new()?;
     rt.block_on(async {
-        let main_window = MainWindow::new()?;
+        let main_window = MainWindow::new().await?;
         println!("About to run the main window");
         main_window.run().await
     })?;
@@ -0,0 +1,256 @@
+use std::iter::Peekable;
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, Parser, Tag};
+use crate::markdown::render::parser_options;
+use crate::vault::links;
+
+/// Inline content of a block, nested the way it was written.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Emphasis(Vec<Inline>),
+    Strong(Vec<Inline>),
+    Strikethrough(Vec<Inline>),
+    Link { url: String, content: Vec<Inline> },
+    /// An image that stays in the text flow: external, or inside a heading or emphasis.
+    Image { url: String, alt: String },
+    FootnoteReference(String),
+    SoftBreak,
+    HardBreak,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListItem {
+    /// `Some(checked)` for task list items.
+    pub task: Option<bool>,
+    pub blocks: Vec<Block>,
+}
+
+/// The block structure of a note, shared by the preview and the exporters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Heading(u32, Vec<Inline>),
+    Paragraph(Vec<Inline>),
+    Code { lang: String, code: String },
+    /// `start` is the first number of an ordered list.
+    List { start: Option<u64>, items: Vec<ListItem> },
+    Quote(Vec<Block>),
+    /// Rows of cells; the first row is the header.
+    Table { alignments: Vec<Alignment>, rows: Vec<Vec<Vec<Inline>>> },
+    /// An image from the vault, split out of its paragraph.
+    Image { url: String, alt: String },
+    Rule,
+    Footnote { label: String, blocks: Vec<Block> },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub strike: bool,
+    pub code: bool,
+}
+
+/// A piece of text with a single style, for writers that lay out flat runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run<'a> {
+    pub text: String,
+    pub style: Style,
+    pub link: Option<&'a str>,
+}
+
+struct Reader<'a> {
+    events: Peekable<Parser<'a, 'a>>,
+    task: Option<bool>,
+}
+
+/// Parses a markdown body (without front matter) into blocks.
+pub fn parse(markdown: &str) -> Vec<Block> {
+    let mut reader = Reader {
+        events: Parser::new_ext(markdown, parser_options()).peekable(),
+        task: None,
+    };
+    reader.blocks()
+}
+
+fn is_inline(event: &Event) -> bool {
+    match event {
+        Event::Start(tag) | Event::End(tag) => {
+            matches!(tag, Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..))
+        },
+        Event::Text(_) | Event::Code(_) | Event::Html(_) | Event::SoftBreak | Event::HardBreak
+        | Event::FootnoteReference(_) | Event::TaskListMarker(_) => true,
+        Event::Rule => false,
+    }
+}
+
+/// Vault images at the top level of a paragraph become blocks of their own,
+/// splitting the text around them.
+fn push_paragraph(blocks: &mut Vec<Block>, inlines: Vec<Inline>) {
+    let mut text = Vec::new();
+    for inline in inlines {
+        match inline {
+            Inline::Image { url, alt } if !links::is_external(&url) => {
+                if !is_blank(&text) {
+                    blocks.push(Block::Paragraph(std::mem::take(&mut text)));
+                }
+                text.clear();
+                blocks.push(Block::Image { url, alt });
+            },
+            inline => text.push(inline),
+        }
+    }
+    if !is_blank(&text) {
+        blocks.push(Block::Paragraph(text));
+    }
+}
+
+fn is_blank(inlines: &[Inline]) -> bool {
+    inlines.iter().all(|inline| match inline {
+        Inline::Text(text) => text.trim().is_empty(),
+        Inline::SoftBreak | Inline::HardBreak => true,
+        _ => false,
+    })
+}
+
+impl Reader<'_> {
+    /// Collects inline content up to the next block-level event, or up to the
+    /// end of the enclosing inline container, which is consumed.
+    fn inlines(&mut self) -> Vec<Inline> {
+        let mut inlines = Vec::new();
+        while let Some(event) = self.events.next_if(is_inline) {
+            match event {
+                Event::End(_) => break,
+                Event::Text(text) => match inlines.last_mut() {
+                    Some(Inline::Text(last)) => last.push_str(&text),
+                    _ => inlines.push(Inline::Text(text.to_string())),
+                },
+                Event::Code(code) => inlines.push(Inline::Code(code.to_string())),
+                Event::Start(Tag::Emphasis) => inlines.push(Inline::Emphasis(self.inlines())),
+                Event::Start(Tag::Strong) => inlines.push(Inline::Strong(self.inlines())),
+                Event::Start(Tag::Strikethrough) => inlines.push(Inline::Strikethrough(self.inlines())),
+                Event::Start(Tag::Link(_, url, _)) => {
+                    inlines.push(Inline::Link { url: url.to_string(), content: self.inlines() });
+                },
+                Event::Start(Tag::Image(_, url, _)) => {
+                    let alt = plain_text(&self.inlines());
+                    inlines.push(Inline::Image { url: url.to_string(), alt });
+                },
+                Event::FootnoteReference(label) => inlines.push(Inline::FootnoteReference(label.to_string())),
+                Event::TaskListMarker(checked) => self.task = Some(checked),
+                Event::SoftBreak => inlines.push(Inline::SoftBreak),
+                Event::HardBreak => inlines.push(Inline::HardBreak),
+                _ => {},
+            }
+        }
+        inlines
+    }
+
+    /// Reads blocks until the end of the enclosing container, which is consumed.
+    fn blocks(&mut self) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        while let Some(event) = self.events.peek() {
+            if is_inline(event) {
+                // 紧凑列表项里的文字没有段落标签
+                let inlines = self.inlines();
+                push_paragraph(&mut blocks, inlines);
+                continue;
+            }
+            let Some(event) = self.events.next() else { break };
+            match event {
+                Event::End(_) => break,
+                Event::Rule => blocks.push(Block::Rule),
+                Event::Start(Tag::Paragraph) => {
+                    let inlines = self.inlines();
+                    self.events.next();
+                    push_paragraph(&mut blocks, inlines);
+                },
+                Event::Start(Tag::Heading(level, _, _)) => {
+                    let inlines = self.inlines();
+                    self.events.next();
+                    blocks.push(Block::Heading(level as u32, inlines));
+                },
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    let lang = match kind {
+                        CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+                        CodeBlockKind::Indented => String::new(),
+                    };
+                    let mut code = String::new();
+                    while let Some(Event::Text(text)) = self.events.next_if(|event| matches!(event, Event::Text(_))) {
+                        code.push_str(&text);
+                    }
+                    self.events.next();
+                    blocks.push(Block::Code { lang, code: code.trim_end_matches('\n').to_string() });
+                },
+                Event::Start(Tag::List(start)) => {
+                    let mut items = Vec::new();
+                    while self.events.next_if(|event| matches!(event, Event::Start(Tag::Item))).is_some() {
+                        let task = self.task.take();
+                        let item_blocks = self.blocks();
+                        items.push(ListItem { task: std::mem::replace(&mut self.task, task), blocks: item_blocks });
+                    }
+                    self.events.next();
+                    blocks.push(Block::List { start, items });
+                },
+                Event::Start(Tag::BlockQuote) => blocks.push(Block::Quote(self.blocks())),
+                Event::Start(Tag::Table(alignments)) => {
+                    let mut rows: Vec<Vec<Vec<Inline>>> = Vec::new();
+                    while let Some(event) = self.events.next() {
+                        match event {
+                            Event::Start(Tag::TableHead | Tag::TableRow) => rows.push(Vec::new()),
+                            Event::Start(Tag::TableCell) => {
+                                let cell = self.inlines();
+                                self.events.next();
+                                if let Some(row) = rows.last_mut() {
+                                    row.push(cell);
+                                }
+                            },
+                            Event::End(Tag::Table(_)) => break,
+                            _ => {},
+                        }
+                    }
+                    blocks.push(Block::Table { alignments, rows });
+                },
+                Event::Start(Tag::FootnoteDefinition(label)) => {
+                    blocks.push(Block::Footnote { label: label.to_string(), blocks: self.blocks() });
+                },
+                Event::Start(_) => blocks.extend(self.blocks()),
+                _ => {},
+            }
+        }
+        blocks
+    }
+}
+
+/// The text of inline content without any markup.
+pub fn plain_text(inlines: &[Inline]) -> String {
+    runs(inlines).into_iter().map(|run| run.text).collect()
+}
+
+/// Flattens inline content into styled runs. Line breaks become `"\n"`.
+pub fn runs(inlines: &[Inline]) -> Vec<Run<'_>> {
+    let mut runs = Vec::new();
+    push_runs(&mut runs, inlines, Style::default(), None);
+    runs
+}
+
+fn push_runs<'a>(runs: &mut Vec<Run<'a>>, inlines: &'a [Inline], style: Style, link: Option<&'a str>) {
+    let push = |runs: &mut Vec<Run<'a>>, text: &str, style: Style| match runs.last_mut() {
+        Some(run) if run.style == style && run.link == link => run.text.push_str(text),
+        _ => runs.push(Run { text: text.to_string(), style, link }),
+    };
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => push(runs, text, style),
+            Inline::Code(code) => push(runs, code, Style { code: true, ..style }),
+            Inline::Emphasis(content) => push_runs(runs, content, Style { italic: true, ..style }, link),
+            Inline::Strong(content) => push_runs(runs, content, Style { bold: true, ..style }, link),
+            Inline::Strikethrough(content) => push_runs(runs, content, Style { strike: true, ..style }, link),
+            Inline::Link { url, content } => push_runs(runs, content, style, Some(url)),
+            Inline::Image { alt, .. } => push(runs, alt, style),
+            Inline::FootnoteReference(label) => push(runs, &format!("[{}]", label), style),
+            Inline::SoftBreak => push(runs, " ", style),
+            Inline::HardBreak => push(runs, "\n", style),
+        }
+    }
+}
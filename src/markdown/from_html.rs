@@ -0,0 +1,689 @@
+use std::collections::HashMap;
+use scraper::{ElementRef, Html, Node};
+
+const HARD_BREAK: &str = "\\\n";
+
+/// Converts clipboard HTML to markdown.
+pub fn to_markdown(html: &str) -> String {
+    // Windows 剪贴板的 HTML 前面有 Version:/StartHTML: 等头部
+    let html = html.find('<').map_or(html, |start| &html[start..]);
+    Converter::default().convert(html)
+}
+
+/// Converts HTML to markdown.
+#[derive(Default)]
+pub struct Converter {
+    /// Link targets for Evernote's `<en-media hash="…">`, by hash.
+    pub media: HashMap<String, String>,
+}
+
+impl Converter {
+    pub fn convert(&self, html: &str) -> String {
+        let document = Html::parse_fragment(html);
+        let blocks = Blocks::new(self, false).collect(document.root_element());
+        if blocks.is_empty() {
+            String::new()
+        } else {
+            blocks.join("\n\n") + "\n"
+        }
+    }
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div" | "section" | "article" | "header" | "footer" | "main" | "nav" | "aside" | "address"
+            | "figure" | "figcaption" | "center" | "form" | "fieldset" | "details" | "summary" | "caption"
+            | "body" | "html" | "en-note" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "li"
+            | "dl" | "dt" | "dd" | "blockquote" | "pre" | "table" | "thead" | "tbody" | "tfoot" | "tr"
+            | "td" | "th" | "hr"
+    )
+}
+
+fn is_skipped(name: &str) -> bool {
+    matches!(name, "script" | "style" | "head" | "title" | "meta" | "link" | "noscript" | "template" | "button" | "select" | "textarea")
+}
+
+fn has_block(element: ElementRef) -> bool {
+    element.descendants().skip(1).any(|node| matches!(node.value(), Node::Element(child) if is_block(child.name())))
+}
+
+/// The value of `property` in an inline `style` attribute, lowercased.
+fn style_value(element: ElementRef, property: &str) -> Option<String> {
+    element.value().attr("style")?
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case(property))
+        .map(|(_, value)| value.trim().to_ascii_lowercase())
+}
+
+/// Whether an inline `style` attribute sets `property` to one of `values`.
+fn style_is(element: ElementRef, property: &str, values: &[&str]) -> bool {
+    style_value(element, property).is_some_and(|value| values.iter().any(|v| value.starts_with(v)))
+}
+
+/// Markers for the bold, italic and strikethrough an inline `style` sets.
+fn style_marker(element: ElementRef) -> String {
+    let mut marker = String::new();
+    if style_is(element, "text-decoration", &["line-through"]) || style_is(element, "text-decoration-line", &["line-through"]) {
+        marker.push_str("~~");
+    }
+    if style_is(element, "font-weight", &["bold", "600", "700", "800", "900"]) {
+        marker.push_str("**");
+    }
+    if style_is(element, "font-style", &["italic"]) {
+        marker.push('*');
+    }
+    marker
+}
+
+/// The list id and marker of a Word paragraph styled with `mso-list`. The
+/// marker is kept in a span marked `mso-list:Ignore`.
+fn office_list_marker(element: ElementRef) -> Option<(String, String)> {
+    let list = style_value(element, "mso-list").filter(|value| value != "ignore")?;
+    let id = list.split_whitespace().next().unwrap_or_default().to_string();
+    let level = list.split_whitespace()
+        .find_map(|part| part.strip_prefix("level")?.parse::<usize>().ok())
+        .unwrap_or(1);
+    let bullet = element.descendants().filter_map(ElementRef::wrap)
+        .find(|span| style_is(*span, "mso-list", &["ignore"]))
+        .map(|span| span.text().collect::<String>().replace('\u{a0}', " ").trim().to_string())
+        .unwrap_or_default();
+    let digits = bullet.chars().take_while(char::is_ascii_digit).collect::<String>();
+    let marker = if !digits.is_empty() { format!("{}. ", digits) } else { "- ".to_string() };
+    Some((id, format!("{}{}", "    ".repeat(level.saturating_sub(1)), marker)))
+}
+
+/// Escapes characters markdown would read as markup.
+fn escape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut escaped = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let intraword = i > 0 && i + 1 < chars.len() && chars[i - 1].is_alphanumeric() && chars[i + 1].is_alphanumeric();
+        match c {
+            '\\' | '`' | '*' | '[' | ']' | '<' | '~' => escaped.push('\\'),
+            '_' if !intraword => escaped.push('\\'),
+            _ => {},
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escapes what would start a heading, quote or list at the beginning of a
+/// line, or make it a thematic break or setext underline.
+fn escape_line_start(line: &str) -> String {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let rest = &line[digits..];
+    if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ") || rest == "." || rest == ")") {
+        return format!("{}\\{}", &line[..digits], rest);
+    }
+    // ---、* * *、___ 这样只有一种符号的行
+    let mut marks = line.chars().filter(|c| !c.is_whitespace());
+    let first = marks.next();
+    if matches!(first, Some('-' | '*' | '_')) && marks.all(|c| Some(c) == first) {
+        return format!("\\{}", line);
+    }
+    match line.chars().next() {
+        Some('#' | '>' | '=') => format!("\\{}", line),
+        Some('-' | '+') if line.len() == 1 || line[1..].starts_with(' ') => format!("\\{}", line),
+        _ => line.to_string(),
+    }
+}
+
+/// Puts inline markdown between `open` and `close`, keeping surrounding
+/// whitespace outside.
+fn surround(inner: &str, open: &str, close: &str) -> String {
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        return inner.to_string();
+    }
+    let leading = &inner[..inner.len() - inner.trim_start().len()];
+    let trailing = &inner[inner.trim_end().len()..];
+    format!("{}{}{}{}{}", leading, open, trimmed, close, trailing)
+}
+
+fn code_span(code: &str) -> String {
+    let code = code.split_whitespace().collect::<Vec<_>>().join(" ");
+    if code.is_empty() {
+        return String::new();
+    }
+    let mut fence = "`".to_string();
+    while code.contains(&fence) {
+        fence.push('`');
+    }
+    if fence.len() > 1 || code.starts_with('`') || code.ends_with('`') {
+        format!("{} {} {}", fence, code, fence)
+    } else {
+        format!("{}{}{}", fence, code, fence)
+    }
+}
+
+/// `<url>` form for destinations markdown would otherwise cut short.
+fn destination(url: &str) -> String {
+    if url.contains([' ', '(', ')']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Text of a `<pre>`, keeping line breaks.
+fn pre_text(element: ElementRef, text: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(t) => text.push_str(t),
+            Node::Element(e) if e.name() == "br" => text.push('\n'),
+            Node::Element(e) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    pre_text(child, text);
+                }
+                if is_block(e.name()) && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+/// The language of a code block from `language-rust` style classes on the
+/// `<pre>`, its `<code>` or, on GitHub, the `highlight-source-rust` wrapper.
+fn code_language(element: ElementRef) -> String {
+    let code = element.children().filter_map(ElementRef::wrap).find(|child| child.value().name() == "code");
+    let parent = element.parent().and_then(ElementRef::wrap);
+    [Some(element), code, parent].into_iter().flatten()
+        .flat_map(|element| element.value().classes())
+        .find_map(|class| {
+            class.strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+                .or_else(|| class.strip_prefix("highlight-source-"))
+        })
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Markdown blocks collected from the children of one container.
+struct Blocks<'a> {
+    converter: &'a Converter,
+    blocks: Vec<String>,
+    text: String,
+    /// Inside a list item, where a checkbox doesn't start a list of its own.
+    list_item: bool,
+    /// List id and marker of the Word list paragraph being read.
+    marker: Option<(String, String)>,
+    /// The block that holds the last Word list and the list's id.
+    office_list: Option<(usize, String)>,
+}
+
+impl<'a> Blocks<'a> {
+    fn new(converter: &'a Converter, list_item: bool) -> Blocks<'a> {
+        Blocks { converter, blocks: Vec::new(), text: String::new(), list_item, marker: None, office_list: None }
+    }
+
+    fn collect(mut self, element: ElementRef) -> Vec<String> {
+        self.children(element);
+        self.flush();
+        self.blocks
+    }
+
+    /// Inline markdown of an element's content on a single line.
+    fn inline(&self, element: ElementRef) -> String {
+        Blocks::new(self.converter, true).collect(element).join(" ").replace(HARD_BREAK, " ").replace('\n', " ")
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let at_line_start = self.text.is_empty() || self.text.ends_with('\n');
+        let text = if at_line_start || self.text.ends_with(' ') { text.trim_start() } else { text };
+        self.text.push_str(text);
+    }
+
+    fn flush(&mut self) {
+        let text = std::mem::take(&mut self.text);
+        let marker = self.marker.take();
+        let mut lines = text.split('\n').map(str::trim).collect::<Vec<_>>().join("\n");
+        // 去掉结尾的换行
+        while let Some(stripped) = lines.trim_end().strip_suffix('\\').filter(|rest| !rest.ends_with('\\')) {
+            lines = stripped.to_string();
+        }
+        let lines = lines.trim_end();
+        if lines.is_empty() {
+            return;
+        }
+        let mut paragraph = lines.split('\n').map(escape_line_start).collect::<Vec<_>>().join("\n");
+        if let Some((id, marker)) = marker {
+            let item = format!("{}{}", marker, paragraph.replace('\n', &format!("\n{}", " ".repeat(marker.len()))));
+            let continues = self.office_list.as_ref().is_some_and(|(index, list)| index + 1 == self.blocks.len() && *list == id);
+            match self.blocks.last_mut() {
+                Some(list) if continues => {
+                    list.push('\n');
+                    list.push_str(&item);
+                },
+                _ => {
+                    self.office_list = Some((self.blocks.len(), id));
+                    self.blocks.push(item);
+                },
+            }
+            return;
+        }
+        if !self.list_item && (paragraph.starts_with("[ ] ") || paragraph.starts_with("[x] ")) {
+            paragraph.insert_str(0, "- ");
+            // 连续的待办合成一个列表
+            if let Some(last) = self.blocks.last_mut().filter(|last| is_todo(last)) {
+                last.push('\n');
+                last.push_str(&paragraph.replace('\n', "\n  "));
+                return;
+            }
+            paragraph = paragraph.replace('\n', "\n  ");
+        }
+        self.blocks.push(paragraph);
+    }
+
+    fn children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => {
+                    let text = text.replace('\u{a0}', " ");
+                    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    let leading = if text.starts_with(char::is_whitespace) && !collapsed.is_empty() { " " } else { "" };
+                    let trailing = if text.ends_with(char::is_whitespace) { " " } else { "" };
+                    self.push_text(&format!("{}{}{}", leading, escape(&collapsed), trailing));
+                },
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    /// Converts the children inline and wraps the result in `marker`,
+    /// unless they contain blocks that can't be wrapped.
+    fn styled(&mut self, element: ElementRef, marker: &str) {
+        if marker.is_empty() || has_block(element) {
+            self.children(element);
+            return;
+        }
+        let start = self.text.len();
+        self.children(element);
+        let inner = self.text.split_off(start);
+        let close = marker.chars().rev().collect::<String>();
+        // 已经有同样样式时不再重复
+        let nested = inner.trim().starts_with(marker) && inner.trim().ends_with(&close);
+        self.text.push_str(&if nested { inner } else { surround(&inner, marker, &close) });
+    }
+
+    fn code_block(&mut self, element: ElementRef, language: String) {
+        self.flush();
+        let mut code = String::new();
+        pre_text(element, &mut code);
+        let code = code.trim_matches('\n');
+        let mut fence = "```".to_string();
+        while code.contains(&fence) {
+            fence.push('`');
+        }
+        self.blocks.push(format!("{}{}\n{}\n{}", fence, language, code, fence));
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        if is_skipped(name) || style_is(element, "mso-list", &["ignore"]) {
+            return;
+        }
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.flush();
+                let text = self.inline(element);
+                if !text.trim().is_empty() {
+                    let level = name[1..].parse().unwrap_or(1);
+                    self.blocks.push(format!("{} {}", "#".repeat(level), text.trim()));
+                }
+            },
+            "ul" | "ol" => {
+                self.flush();
+                let list = self.list(element);
+                if !list.is_empty() {
+                    self.blocks.push(list);
+                }
+            },
+            "li" => {
+                self.flush();
+                let item = self.item(element, "- ");
+                self.blocks.push(item);
+            },
+            "blockquote" => {
+                self.flush();
+                let inner = Blocks::new(self.converter, false).collect(element).join("\n\n");
+                if !inner.is_empty() {
+                    let quoted = inner.lines().map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) });
+                    self.blocks.push(quoted.collect::<Vec<_>>().join("\n"));
+                }
+            },
+            "pre" => self.code_block(element, code_language(element)),
+            // VS Code 复制的代码是 white-space: pre 的 div
+            "div" if style_value(element, "white-space").as_deref() == Some("pre") => self.code_block(element, String::new()),
+            "table" => {
+                self.flush();
+                self.table(element);
+            },
+            "hr" => {
+                self.flush();
+                self.blocks.push("---".to_string());
+            },
+            "dt" => {
+                self.flush();
+                let text = self.inline(element);
+                if !text.trim().is_empty() {
+                    self.blocks.push(format!("**{}**", text.trim()));
+                }
+            },
+            "br" => {
+                let trimmed = self.text.trim_end().len();
+                self.text.truncate(trimmed);
+                self.text.push_str(HARD_BREAK);
+            },
+            "img" => {
+                // data: 图片太长，不放进笔记
+                let Some(src) = element.value().attr("src").filter(|src| !src.trim().is_empty() && !src.starts_with("data:")) else { return };
+                let alt = escape(element.value().attr("alt").unwrap_or_default().trim());
+                let width = element.value().attr("width").and_then(|width| width.trim().trim_end_matches("px").parse::<u32>().ok());
+                let alt = match width {
+                    Some(width) => format!("{}|{}", alt, width),
+                    None => alt,
+                };
+                self.push_text(&format!("![{}]({})", alt, destination(src.trim())));
+            },
+            "a" => {
+                let href = element.value().attr("href").map(str::trim).unwrap_or_default();
+                if href.is_empty() || href.starts_with("javascript:") || has_block(element) {
+                    self.children(element);
+                    return;
+                }
+                let start = self.text.len();
+                self.children(element);
+                let inner = self.text.split_off(start);
+                let text = inner.trim();
+                if text.is_empty() {
+                    self.text.push_str(&inner);
+                } else if text == escape(href) {
+                    self.text.push_str(&format!("<{}>", href));
+                } else {
+                    self.text.push_str(&surround(&inner, "[", &format!("]({})", destination(href))));
+                }
+            },
+            "strong" | "b" if !style_is(element, "font-weight", &["normal", "400"]) => self.styled(element, "**"),
+            "em" | "i" | "cite" | "dfn" | "var" => self.styled(element, "*"),
+            "del" | "s" | "strike" => self.styled(element, "~~"),
+            "code" | "kbd" | "samp" | "tt" => {
+                let code = code_span(&element.text().collect::<String>());
+                self.push_text(&code);
+            },
+            "input" => {
+                if element.value().attr("type").is_some_and(|kind| kind.eq_ignore_ascii_case("checkbox")) {
+                    let checked = element.value().attr("checked").is_some();
+                    self.push_text(if checked { "[x] " } else { "[ ] " });
+                }
+            },
+            "en-todo" => {
+                let checked = element.value().attr("checked").is_some_and(|checked| checked == "true");
+                self.push_text(if checked { "[x] " } else { "[ ] " });
+                self.children(element);
+            },
+            "en-media" => {
+                let target = element.value().attr("hash").and_then(|hash| self.converter.media.get(hash));
+                if let Some(target) = target {
+                    let is_image = element.value().attr("type").is_some_and(|mime| mime.starts_with("image/"));
+                    let name = target.rsplit('/').next().unwrap_or(target);
+                    let name = urlencoding::decode(name).map(|name| name.into_owned()).unwrap_or_else(|_| name.to_string());
+                    if is_image {
+                        self.push_text(&format!("![{}]({})", escape(&name), destination(target)));
+                    } else {
+                        self.push_text(&format!("[{}]({})", escape(&name), destination(target)));
+                    }
+                }
+                self.children(element);
+            },
+            "en-crypt" => self.push_text("\\[加密内容\\]"),
+            _ if is_block(name) => {
+                self.flush();
+                self.marker = office_list_marker(element);
+                self.children(element);
+                self.flush();
+            },
+            // span、font 等只在 style 里写样式
+            _ => self.styled(element, &style_marker(element)),
+        }
+    }
+
+    /// One list item; continuation lines are indented under the marker.
+    fn item(&self, element: ElementRef, marker: &str) -> String {
+        let content = Blocks::new(self.converter, true).collect(element);
+        let mut text = String::new();
+        for (i, block) in content.iter().enumerate() {
+            if i > 0 {
+                // 嵌套列表紧跟在上一段后面
+                text.push_str(if block.starts_with(['-', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9']) && is_list(block) { "\n" } else { "\n\n" });
+            }
+            text.push_str(block);
+        }
+        let indent = " ".repeat(marker.len());
+        let mut lines = text.lines();
+        let mut item = format!("{}{}", marker, lines.next().unwrap_or_default()).trim_end().to_string();
+        for line in lines {
+            item.push('\n');
+            if !line.is_empty() {
+                item.push_str(&indent);
+                item.push_str(line);
+            }
+        }
+        item
+    }
+
+    fn list(&self, element: ElementRef) -> String {
+        let ordered = element.value().name() == "ol";
+        let mut number = element.value().attr("start").and_then(|start| start.trim().parse::<u64>().ok()).unwrap_or(1);
+        // Evernote 的清单：<ul style="--en-todo:true"><li style="--en-checked:true">
+        let todo = style_is(element, "--en-todo", &["true"]);
+        let mut items: Vec<String> = Vec::new();
+        for child in element.children() {
+            let Some(child) = ElementRef::wrap(child) else { continue };
+            match child.value().name() {
+                "li" => {
+                    let marker = match (ordered, todo) {
+                        (_, true) if style_is(child, "--en-checked", &["true"]) => "- [x] ".to_string(),
+                        (_, true) => "- [ ] ".to_string(),
+                        (true, _) => format!("{}. ", number),
+                        (false, _) => "- ".to_string(),
+                    };
+                    number += 1;
+                    items.push(self.item(child, &marker));
+                },
+                // 直接嵌在列表里的子列表归到上一项
+                "ul" | "ol" => {
+                    let nested = self.list(child);
+                    match items.last_mut() {
+                        Some(last) if !nested.is_empty() => {
+                            for line in nested.lines() {
+                                last.push('\n');
+                                if !line.is_empty() {
+                                    last.push_str("  ");
+                                    last.push_str(line);
+                                }
+                            }
+                        },
+                        _ if !nested.is_empty() => items.push(nested),
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+        let separator = if items.iter().any(|item| item.contains("\n\n")) { "\n\n" } else { "\n" };
+        items.join(separator)
+    }
+
+    fn table(&mut self, element: ElementRef) {
+        let mut rows = Vec::new();
+        table_rows(element, &mut rows);
+        let columns = rows.iter().map(|row| {
+            row.iter().map(|cell| cell.value().attr("colspan").and_then(|span| span.parse::<usize>().ok()).unwrap_or(1)).sum::<usize>()
+        }).max().unwrap_or(0);
+        let nested = rows.iter().flatten().any(|cell| cell.descendants().skip(1).any(|node| matches!(node.value(), Node::Element(e) if e.name() == "table")));
+        // 单列或嵌套的表格只是排版用的，按普通内容处理
+        if columns < 2 || nested {
+            for cell in rows.iter().flatten() {
+                self.children(*cell);
+                self.flush();
+            }
+            return;
+        }
+        let mut lines = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            let mut cells = Vec::new();
+            for cell in row {
+                let content = Blocks::new(self.converter, true).collect(*cell)
+                    .join("<br>")
+                    .replace(HARD_BREAK, "<br>")
+                    .replace('\n', " ")
+                    .replace('|', "\\|");
+                cells.push(content);
+                let span = cell.value().attr("colspan").and_then(|span| span.parse::<usize>().ok()).unwrap_or(1);
+                cells.extend(std::iter::repeat_n(String::new(), span.saturating_sub(1)));
+            }
+            cells.resize(columns, String::new());
+            lines.push(format!("| {} |", cells.join(" | ")));
+            if i == 0 {
+                lines.push(format!("|{}", " --- |".repeat(columns)));
+            }
+        }
+        self.blocks.push(lines.join("\n"));
+    }
+}
+
+/// Rows of a table in order, without those of nested tables.
+fn table_rows<'a>(element: ElementRef<'a>, rows: &mut Vec<Vec<ElementRef<'a>>>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        match child.value().name() {
+            "thead" | "tbody" | "tfoot" => table_rows(child, rows),
+            "tr" => rows.push(child.children().filter_map(ElementRef::wrap)
+                .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                .collect()),
+            _ => {},
+        }
+    }
+}
+
+fn is_todo(block: &str) -> bool {
+    let last = block.lines().last().unwrap_or_default();
+    block.starts_with("- [") && (last.starts_with("- [ ] ") || last.starts_with("- [x] ") || last.starts_with("  "))
+}
+
+fn is_list(block: &str) -> bool {
+    let first = block.lines().next().unwrap_or_default();
+    let digits = first.chars().take_while(char::is_ascii_digit).count();
+    first.starts_with("- ") || first == "-" || (digits > 0 && first[digits..].starts_with(". "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_browser_selection() {
+        assert_eq!(to_markdown(include_str!("../../tests/data/clipboard/chrome.html")), "\
+## Getting started
+
+Install the [command line tool](https://example.com/cli) with `cargo install nodian`, then run it:
+
+```bash
+nodian --vault ~/notes
+nodian export pdf note.md
+```
+
+- Fast **search**
+- Works *offline*
+  - Sync is optional
+
+| Format | Extension |
+| --- | --- |
+| Markdown | .md |
+| Word | .docx |
+
+![Logo](https://example.com/logo.png)
+");
+    }
+
+    #[test]
+    fn converts_google_docs() {
+        assert_eq!(to_markdown(include_str!("../../tests/data/clipboard/google_docs.html")), "\
+# Meeting notes
+
+**Decisions**: ship the *import wizard* ~~next week~~.
+
+1. Review the [spec](https://docs.example.com/spec)
+2. Write tests
+");
+    }
+
+    #[test]
+    fn converts_word_document() {
+        assert_eq!(to_markdown(include_str!("../../tests/data/clipboard/word.html")), "\
+## Quarterly report
+
+Revenue grew by **12%** compared to *last quarter*.
+
+- New customers
+    - Mostly in Europe
+- Lower costs
+
+1. Hire two engineers
+2. Open the Berlin office
+
+| **Region** | **Sales** |
+| --- | --- |
+| EMEA | 1,200 |
+");
+    }
+
+    #[test]
+    fn converts_code_from_github_and_editors() {
+        assert_eq!(to_markdown(include_str!("../../tests/data/clipboard/github.html")), "\
+### Usage
+
+Run the binary:
+
+```rust
+fn main() {
+    println!(\"a * b\");
+}
+```
+
+- [x] Parse front matter
+- [ ] Export to [EPUB](https://en.wikipedia.org/wiki/EPUB)
+
+> Note: paths are relative to the vault.
+");
+        assert_eq!(to_markdown(include_str!("../../tests/data/clipboard/vscode.html")), "```\nlet x = 1;\n\nprint(x)\n```\n");
+    }
+
+    #[test]
+    fn escapes_text_that_looks_like_markdown() {
+        assert_eq!(to_markdown("<p>1. not a list, *not* em, snake_case and _this_</p><p># not a heading</p>"),
+            "1\\. not a list, \\*not\\* em, snake_case and \\_this\\_\n\n\\# not a heading\n");
+        assert_eq!(to_markdown("<p>a<br>b<br></p>"), "a\\\nb\n");
+        assert_eq!(to_markdown("<p>---</p><p>a<br>---</p><p>- - -</p>"), "\\---\n\na\\\n\\---\n\n\\- - -\n");
+        assert_eq!(to_markdown("<p>~~x~~ and</p><p>~~~</p>"), "\\~\\~x\\~\\~ and\n\n\\~\\~\\~\n");
+        assert_eq!(to_markdown("<p><a href=\"https://a.com\">https://a.com</a> <img src=\"data:image/png;base64,AAAA\"></p>"), "<https://a.com>\n");
+    }
+
+    #[test]
+    fn converts_evernote_markup() {
+        let mut converter = Converter::default();
+        converter.media.insert("abc".to_string(), "attachments/photo%201.png".to_string());
+        let enml = "<en-note><div><en-todo checked=\"true\"/>passport</div><div><en-todo/>tickets</div>\
+            <div><en-media hash=\"abc\" type=\"image/png\"/></div></en-note>";
+        assert_eq!(converter.convert(enml), "- [x] passport\n- [ ] tickets\n\n![photo 1.png](attachments/photo%201.png)\n");
+    }
+}
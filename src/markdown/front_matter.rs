@@ -0,0 +1,280 @@
+use serde_yaml::{Mapping, Number, Value};
+
+/// Returns the byte length of a leading `---` YAML front matter block,
+/// including its closing delimiter line, or `None` when there is none.
+pub fn front_matter_len(source: &str) -> Option<usize> {
+    let first_line_end = source.find('\n')?;
+    if source[..first_line_end].trim_end() != "---" {
+        return None;
+    }
+    let mut offset = first_line_end + 1;
+    for line in source[offset..].split_inclusive('\n') {
+        offset += line.len();
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return Some(offset);
+        }
+    }
+    None
+}
+
+/// Note body without its front matter.
+pub fn body(source: &str) -> &str {
+    &source[front_matter_len(source).unwrap_or(0)..]
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Text(String),
+    List(Vec<String>),
+    /// As written in the note, so `1.10` or a large integer stays unchanged.
+    Number(String),
+    Checkbox(bool),
+    /// `YYYY-MM-DD`, optionally followed by a time.
+    Date(String),
+    /// Nested mappings and other YAML the panel can't edit, kept verbatim.
+    Yaml(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    pub key: String,
+    pub value: PropertyValue,
+}
+
+/// Keys that always hold a list, even when written as a single string.
+const LIST_KEYS: &[&str] = &["tags", "aliases"];
+
+impl PropertyValue {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PropertyValue::Text(_) | PropertyValue::Yaml(_) => "text",
+            PropertyValue::List(_) => "list",
+            PropertyValue::Number(_) => "number",
+            PropertyValue::Checkbox(_) => "checkbox",
+            PropertyValue::Date(_) => "date",
+        }
+    }
+
+    /// The value as shown in the properties panel; list items are comma separated.
+    pub fn display(&self) -> String {
+        match self {
+            PropertyValue::Text(text) | PropertyValue::Date(text) | PropertyValue::Yaml(text) => text.clone(),
+            PropertyValue::List(items) => items.join(", "),
+            PropertyValue::Number(number) => number.clone(),
+            PropertyValue::Checkbox(checked) => checked.to_string(),
+        }
+    }
+
+    /// Parses panel input for the given kind, falling back to text when the
+    /// input does not fit.
+    pub fn from_input(kind: &str, input: &str) -> PropertyValue {
+        let input = input.trim();
+        match kind {
+            "list" => PropertyValue::List(
+                input.split(',').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect()
+            ),
+            "number" if input.parse::<f64>().is_ok_and(f64::is_finite) => PropertyValue::Number(input.to_string()),
+            "checkbox" => PropertyValue::Checkbox(matches!(input, "true" | "yes" | "1")),
+            "date" => PropertyValue::Date(input.to_string()),
+            _ => PropertyValue::Text(input.to_string()),
+        }
+    }
+
+    /// `source` is the YAML the value was read from, used to keep numbers
+    /// as they are written.
+    fn from_yaml(key: &str, value: &Value, source: &str) -> PropertyValue {
+        let as_list = LIST_KEYS.contains(&key);
+        match value {
+            Value::Bool(checked) => PropertyValue::Checkbox(*checked),
+            Value::Number(number) if !as_list => PropertyValue::Number(number_text(number, source)),
+            Value::Sequence(items) => PropertyValue::List(items.iter().map(scalar_text).collect()),
+            // 只有 tags 允许用空格分隔，别名本身可以有空格
+            Value::String(text) if as_list => {
+                let separators: &[char] = if key == "tags" { &[',', ' '] } else { &[','] };
+                PropertyValue::List(
+                    text.split(separators).map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect()
+                )
+            },
+            Value::String(text) if is_date(text) => PropertyValue::Date(text.clone()),
+            Value::Null if as_list => PropertyValue::List(Vec::new()),
+            Value::Mapping(_) | Value::Tagged(_) => PropertyValue::Yaml(scalar_text(value)),
+            other => PropertyValue::Text(scalar_text(other)),
+        }
+    }
+
+    fn to_yaml(&self) -> Value {
+        match self {
+            PropertyValue::Text(text) | PropertyValue::Date(text) => Value::String(text.clone()),
+            PropertyValue::List(items) => Value::Sequence(items.iter().cloned().map(Value::String).collect()),
+            PropertyValue::Number(number) => serde_yaml::from_str(number).unwrap_or_else(|_| Value::String(number.clone())),
+            PropertyValue::Checkbox(checked) => Value::Bool(*checked),
+            PropertyValue::Yaml(yaml) => serde_yaml::from_str(yaml).unwrap_or_else(|_| Value::String(yaml.clone())),
+        }
+    }
+}
+
+/// The number as written after the key on the first line of `source`,
+/// when that reads back as the same number.
+fn number_text(number: &Number, source: &str) -> String {
+    let line = source.lines().next().unwrap_or_default();
+    let written = line.split_once(':').map_or("", |(_, value)| value);
+    let written = written.split_once(" #").map_or(written, |(value, _)| value).trim();
+    match serde_yaml::from_str::<Value>(written) {
+        Ok(Value::Number(parsed)) if parsed == *number => written.to_string(),
+        _ => number.to_string(),
+    }
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other).unwrap_or_default().trim_end().to_string(),
+    }
+}
+
+fn is_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 10
+        && bytes[..10].iter().enumerate().all(|(i, b)| if i == 4 || i == 7 { *b == b'-' } else { b.is_ascii_digit() })
+        && (bytes.len() == 10 || matches!(bytes[10], b'T' | b' '))
+}
+
+/// The YAML between the `---` lines, if the note has front matter.
+fn yaml_block(source: &str) -> Option<&str> {
+    let len = front_matter_len(source)?;
+    let first_line_end = source.find('\n').unwrap_or(0) + 1;
+    let closing_start = source[..len].trim_end_matches('\n').rfind('\n').map_or(first_line_end, |i| i + 1);
+    Some(&source[first_line_end..closing_start.max(first_line_end)])
+}
+
+/// Splits the YAML into the comments before the first key and the text of
+/// each top-level key, including its nested lines and the comments after
+/// it. `None` when the pieces don't parse one key each, e.g. for complex
+/// keys.
+fn entries(yaml: &str) -> Option<(String, Vec<(Property, String)>)> {
+    let mut preamble = String::new();
+    let mut texts: Vec<String> = Vec::new();
+    for line in yaml.split_inclusive('\n') {
+        let starts_key = !line.starts_with([' ', '\t', '#', '-', '\n', '\r']);
+        match texts.last_mut() {
+            Some(text) if !starts_key => text.push_str(line),
+            None if !starts_key => preamble.push_str(line),
+            _ => texts.push(line.to_string()),
+        }
+    }
+    let mut entries = Vec::new();
+    for mut text in texts {
+        let mapping: Mapping = serde_yaml::from_str(&text).ok()?;
+        let (key, value) = mapping.iter().next().filter(|_| mapping.len() == 1)?;
+        let key = scalar_text(key);
+        let value = PropertyValue::from_yaml(&key, value, &text);
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        entries.push((Property { key, value }, text));
+    }
+    Some((preamble, entries))
+}
+
+/// Parses the front matter of a note. Returns an empty list when there is no
+/// front matter and an error when the YAML is malformed.
+pub fn parse(source: &str) -> Result<Vec<Property>, String> {
+    let Some(yaml) = yaml_block(source) else {
+        return Ok(Vec::new());
+    };
+    if yaml.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mapping: Mapping = serde_yaml::from_str(yaml).map_err(|e| e.to_string())?;
+    if let Some((_, entries)) = entries(yaml).filter(|(_, entries)| entries.len() == mapping.len()) {
+        return Ok(entries.into_iter().map(|(property, _)| property).collect());
+    }
+    Ok(mapping.iter().map(|(key, value)| {
+        let key = scalar_text(key);
+        let value = PropertyValue::from_yaml(&key, value, "");
+        Property { key, value }
+    }).collect())
+}
+
+/// Replaces the front matter of `source` with `properties`, removing the
+/// block entirely when there are none left. Keys whose value didn't change
+/// keep their text, comments included.
+pub fn with_properties(source: &str, properties: &[Property]) -> String {
+    let body = body(source);
+    if properties.is_empty() {
+        return body.to_string();
+    }
+    let (mut yaml, old) = yaml_block(source).and_then(entries).unwrap_or_default();
+    for property in properties {
+        match old.iter().find(|(old, _)| old == property) {
+            Some((_, text)) => yaml.push_str(text),
+            None => yaml.push_str(&property_yaml(property)),
+        }
+    }
+    format!("---\n{}---\n{}", yaml, body)
+}
+
+fn property_yaml(property: &Property) -> String {
+    let key = Value::String(property.key.clone());
+    if let PropertyValue::Number(number) = &property.value {
+        // 原样写出，不经过 f64
+        if matches!(serde_yaml::from_str(number), Ok(Value::Number(_))) {
+            return format!("{}: {}\n", serde_yaml::to_string(&key).unwrap_or_default().trim_end(), number);
+        }
+    }
+    let mut mapping = Mapping::new();
+    mapping.insert(key, property.value.to_yaml());
+    serde_yaml::to_string(&mapping).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "---\n# 笔记信息\nid: 9007199254740993\nversion: 1.10  # 不是 1.1\ntags:\n- a\n- b\naliases: My Alias, Other\n'quoted key': \"x\"\n---\nBody\n";
+
+    fn value(properties: &[Property], key: &str) -> PropertyValue {
+        properties.iter().find(|property| property.key == key).unwrap().value.clone()
+    }
+
+    #[test]
+    fn parses_values_as_written() {
+        let properties = parse(NOTE).unwrap();
+        assert_eq!(properties.iter().map(|property| property.key.as_str()).collect::<Vec<_>>(), ["id", "version", "tags", "aliases", "quoted key"]);
+        assert_eq!(value(&properties, "id"), PropertyValue::Number("9007199254740993".to_string()));
+        assert_eq!(value(&properties, "version"), PropertyValue::Number("1.10".to_string()));
+        assert_eq!(value(&properties, "aliases"), PropertyValue::List(vec!["My Alias".to_string(), "Other".to_string()]));
+        assert_eq!(parse("---\ntags: a b,c\n---\n").unwrap()[0].value, PropertyValue::List(vec!["a".into(), "b".into(), "c".into()]));
+    }
+
+    #[test]
+    fn editing_one_property_keeps_the_others() {
+        let mut properties = parse(NOTE).unwrap();
+        properties[4].value = PropertyValue::from_input("text", "y");
+        assert_eq!(with_properties(NOTE, &properties), NOTE.replace("'quoted key': \"x\"", "quoted key: y"));
+
+        let mut properties = parse(NOTE).unwrap();
+        properties[1].value = PropertyValue::from_input("number", "1.20");
+        properties.remove(2);
+        properties.push(Property { key: "done".to_string(), value: PropertyValue::from_input("checkbox", "yes") });
+        assert_eq!(
+            with_properties(NOTE, &properties),
+            "---\n# 笔记信息\nid: 9007199254740993\nversion: 1.20\naliases: My Alias, Other\n'quoted key': \"x\"\ndone: true\n---\nBody\n",
+        );
+        assert_eq!(with_properties(NOTE, &[]), "Body\n");
+    }
+
+    #[test]
+    fn adds_front_matter_to_a_plain_note() {
+        let properties = [
+            Property { key: "tags".to_string(), value: PropertyValue::List(vec!["a".to_string()]) },
+            Property { key: "count".to_string(), value: PropertyValue::from_input("number", "12345678901234567890") },
+        ];
+        assert_eq!(with_properties("Body\n", &properties), "---\ntags:\n- a\ncount: 12345678901234567890\n---\nBody\n");
+    }
+}
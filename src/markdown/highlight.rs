@@ -0,0 +1,85 @@
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+#[derive(Clone, Debug)]
+pub struct HighlightedSpan {
+    pub text: String,
+    pub color: (u8, u8, u8),
+    pub bold: bool,
+    pub italic: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct HighlightedCode {
+    pub background: (u8, u8, u8),
+    pub lines: Vec<Vec<HighlightedSpan>>,
+}
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme(dark: bool) -> &'static Theme {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    let themes = THEMES.get_or_init(ThemeSet::load_defaults);
+    // 跟随应用的明暗主题
+    let name = if dark { "base16-ocean.dark" } else { "InspiredGitHub" };
+    &themes.themes[name]
+}
+
+fn theme_background(theme: &Theme) -> (u8, u8, u8) {
+    theme.settings.background
+        .map(|c| (c.r, c.g, c.b))
+        .unwrap_or((0xf6, 0xf8, 0xfa))
+}
+
+fn theme_foreground(theme: &Theme) -> (u8, u8, u8) {
+    theme.settings.foreground
+        .map(|c| (c.r, c.g, c.b))
+        .unwrap_or((0x33, 0x33, 0x33))
+}
+
+/// Highlights `code` using the syntax matching the fence info `lang`.
+/// Unknown languages fall back to plain text in the theme's foreground color.
+pub fn highlight_code(code: &str, lang: &str, dark: bool) -> HighlightedCode {
+    let syntaxes = syntax_set();
+    let theme = theme(dark);
+    let syntax = syntaxes
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let spans = match highlighter.highlight_line(line, syntaxes) {
+            Ok(ranges) => ranges.into_iter()
+                .map(|(style, text)| HighlightedSpan {
+                    text: text.trim_end_matches(['\n', '\r']).replace('\t', "    "),
+                    color: (style.foreground.r, style.foreground.g, style.foreground.b),
+                    bold: style.font_style.contains(FontStyle::BOLD),
+                    italic: style.font_style.contains(FontStyle::ITALIC),
+                })
+                .filter(|span| !span.text.is_empty())
+                .collect(),
+            Err(e) => {
+                eprintln!("Failed to highlight code block: {}", e);
+                vec![HighlightedSpan {
+                    text: line.trim_end_matches(['\n', '\r']).to_string(),
+                    color: theme_foreground(theme),
+                    bold: false,
+                    italic: false,
+                }]
+            }
+        };
+        lines.push(spans);
+    }
+
+    HighlightedCode {
+        background: theme_background(theme),
+        lines,
+    }
+}
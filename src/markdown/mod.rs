@@ -0,0 +1,8 @@
+pub mod document;
+pub mod from_html;
+pub mod front_matter;
+pub mod highlight;
+pub mod outline;
+pub mod render;
+pub mod tags;
+pub mod tokenizer;
@@ -0,0 +1,42 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use crate::markdown::front_matter;
+use crate::markdown::render::parser_options;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heading {
+    pub level: u32,
+    pub text: String,
+    /// Byte offset of the heading line in the note source.
+    pub offset: usize,
+}
+
+/// Collects the non-empty headings of a note in document order.
+pub fn headings(markdown: &str) -> Vec<Heading> {
+    let body_start = markdown.len() - front_matter::body(markdown).len();
+    let mut headings = Vec::new();
+    let mut current: Option<Heading> = None;
+
+    for (event, range) in Parser::new_ext(&markdown[body_start..], parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading(level, _, _)) => {
+                current = Some(Heading { level: level as u32, text: String::new(), offset: range.start + body_start });
+            },
+            Event::End(Tag::Heading(..)) => {
+                if let Some(mut heading) = current.take() {
+                    heading.text = heading.text.trim().to_string();
+                    // 空标题不会出现在预览中
+                    if !heading.text.is_empty() {
+                        headings.push(heading);
+                    }
+                }
+            },
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = &mut current {
+                    heading.text.push_str(&text);
+                }
+            },
+            _ => {}
+        }
+    }
+    headings
+}
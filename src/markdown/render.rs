@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+use pulldown_cmark::Options;
+use crate::markdown::document::{self, Inline};
+use crate::markdown::front_matter;
+use crate::markdown::highlight::{highlight_code, HighlightedCode};
+use crate::vault::links;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockKind {
+    Heading(u32),
+    Paragraph,
+    ListItem(String),
+    CodeBlock,
+    Rule,
+    /// An embedded image; `text` holds the alt text. Zero sizes mean "natural size".
+    Image { path: PathBuf, width: u32, height: u32 },
+}
+
+/// Where the rendered note lives, used to resolve relative image links.
+pub struct RenderContext<'a> {
+    pub dark: bool,
+    pub note_dir: &'a Path,
+    pub root: &'a Path,
+}
+
+/// A rendered preview block. `text` holds the inline content as the markdown
+/// subset understood by Slint's `StyledText`.
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub indent: usize,
+    pub text: String,
+    pub code: Option<HighlightedCode>,
+}
+
+struct Renderer<'a> {
+    blocks: Vec<Block>,
+    context: &'a RenderContext<'a>,
+}
+
+pub fn parser_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES
+}
+
+pub fn render_blocks(markdown: &str, context: &RenderContext) -> Vec<Block> {
+    let mut renderer = Renderer { blocks: Vec::new(), context };
+    // front matter 在属性面板中显示，不进入预览
+    renderer.render(&document::parse(front_matter::body(markdown)), 0, 0);
+    renderer.blocks
+}
+
+impl Renderer<'_> {
+    fn render(&mut self, blocks: &[document::Block], quote_depth: usize, list_depth: usize) {
+        let indent = quote_depth + list_depth.saturating_sub(1);
+        for block in blocks {
+            match block {
+                document::Block::Heading(level, inlines) => {
+                    let text = styled_text(inlines);
+                    if !text.trim().is_empty() {
+                        self.push(BlockKind::Heading(*level), indent, format!("**{}**", text.trim()));
+                    }
+                },
+                document::Block::Paragraph(inlines) => {
+                    let text = styled_text(inlines);
+                    if !text.trim().is_empty() {
+                        self.push(BlockKind::Paragraph, indent, text.trim().to_string());
+                    }
+                },
+                document::Block::Code { lang, code } => self.blocks.push(Block {
+                    kind: BlockKind::CodeBlock,
+                    indent,
+                    text: String::new(),
+                    code: Some(highlight_code(code, lang, self.context.dark)),
+                }),
+                document::Block::List { start, items } => {
+                    let item_indent = quote_depth + list_depth;
+                    for (i, item) in items.iter().enumerate() {
+                        let marker = match start {
+                            Some(start) => format!("{}.", start + i as u64),
+                            None => "•".to_string(),
+                        };
+                        let mut text = match item.task {
+                            Some(checked) => if checked { "☑ " } else { "☐ " }.to_string(),
+                            None => String::new(),
+                        };
+                        // 列表项的第一段和标记放在同一行
+                        let rest = match item.blocks.first() {
+                            Some(document::Block::Paragraph(inlines)) => {
+                                text.push_str(&styled_text(inlines));
+                                &item.blocks[1..]
+                            },
+                            _ => &item.blocks[..],
+                        };
+                        self.push(BlockKind::ListItem(marker), item_indent, text.trim().to_string());
+                        self.render(rest, quote_depth, list_depth + 1);
+                    }
+                },
+                document::Block::Quote(inner) => self.render(inner, quote_depth + 1, list_depth),
+                document::Block::Table { rows, .. } => {
+                    for row in rows {
+                        let text = row.iter().map(|cell| styled_text(cell) + " │ ").collect::<String>();
+                        let text = text.trim_end_matches(" │ ").trim();
+                        if !text.is_empty() {
+                            self.push(BlockKind::Paragraph, indent, text.to_string());
+                        }
+                    }
+                },
+                document::Block::Image { url, alt } => self.push_image(url, alt, indent),
+                document::Block::Rule => self.push(BlockKind::Rule, indent, String::new()),
+                document::Block::Footnote { blocks, .. } => self.render(blocks, quote_depth, list_depth),
+            }
+        }
+    }
+
+    fn push(&mut self, kind: BlockKind, indent: usize, text: String) {
+        self.blocks.push(Block { kind, indent, text, code: None });
+    }
+
+    fn push_image(&mut self, url: &str, alt: &str, indent: usize) {
+        let (alt, width, height) = parse_size_hint(alt);
+        self.blocks.push(Block {
+            kind: BlockKind::Image {
+                path: links::resolve(url, self.context.note_dir, self.context.root),
+                width,
+                height,
+            },
+            indent,
+            text: alt.to_string(),
+            code: None,
+        });
+    }
+}
+
+/// Writes inline content in the markdown subset understood by `StyledText`.
+fn styled_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(plain) => text.push_str(&escape(plain)),
+            Inline::Code(code) if code.contains('`') => text.push_str(&format!("`` {} ``", code)),
+            Inline::Code(code) => text.push_str(&format!("`{}`", code)),
+            Inline::Emphasis(content) => text.push_str(&format!("*{}*", styled_text(content))),
+            Inline::Strong(content) => text.push_str(&format!("**{}**", styled_text(content))),
+            Inline::Strikethrough(content) => text.push_str(&format!("~~{}~~", styled_text(content))),
+            // 空格和括号会截断链接地址
+            Inline::Link { url, content } => text.push_str(&format!("[{}]({})", styled_text(content), links::encode_path(url))),
+            Inline::Image { alt, .. } => text.push_str(&escape(alt)),
+            Inline::FootnoteReference(label) => text.push_str(&escape(&format!("[^{}]", label))),
+            Inline::SoftBreak => text.push(' '),
+            Inline::HardBreak => text.push_str("\\\n"),
+        }
+    }
+    text
+}
+
+/// Splits an Obsidian-style size hint off the alt text:
+/// `alt|300` sets the width, `alt|300x200` both dimensions.
+pub fn parse_size_hint(alt: &str) -> (&str, u32, u32) {
+    if let Some((text, hint)) = alt.rsplit_once('|') {
+        let (w, h) = hint.trim().split_once('x').unwrap_or((hint.trim(), ""));
+        if let Ok(width) = w.parse::<u32>() {
+            return (text.trim(), width, h.parse().unwrap_or(0));
+        }
+    }
+    (alt, 0, 0)
+}
+
+/// Backslash-escapes ASCII punctuation so plain text is not re-read as markup.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_punctuation() {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{Event, Parser, Tag};
+
+    #[test]
+    fn encodes_link_targets_in_styled_text() {
+        let inlines = [
+            Inline::Link { url: "my notes/a (1).md".to_string(), content: vec![Inline::Text("a".to_string())] },
+            Inline::Link { url: "https://example.com/a%20b".to_string(), content: vec![Inline::Text("b".to_string())] },
+        ];
+        let text = styled_text(&inlines);
+        assert_eq!(text, "[a](my%20notes/a%20%281%29.md)[b](https://example.com/a%20b)");
+        let urls = Parser::new(&text).filter_map(|event| match event {
+            Event::Start(Tag::Link(_, url, _)) => Some(links::decode_path(&url)),
+            _ => None,
+        }).collect::<Vec<_>>();
+        assert_eq!(urls, ["my notes/a (1).md", "https://example.com/a b"]);
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+use pulldown_cmark::{Event, Parser, Tag};
+use crate::markdown::front_matter::{self, Property, PropertyValue};
+use crate::markdown::render::parser_options;
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+/// Normalizes a tag written as `#parent/child` or `parent/child/`.
+fn clean(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').trim_matches('/');
+    // 纯数字不算标签，比如 "#1"
+    if tag.is_empty() || tag.chars().all(|c| c.is_ascii_digit() || c == '/') || !tag.chars().all(is_tag_char) {
+        return None;
+    }
+    Some(tag.to_string())
+}
+
+fn inline_tags(text: &str, tags: &mut Vec<String>) {
+    let mut prev: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        if c == '#' && !prev.is_some_and(|p| p.is_alphanumeric() || matches!(p, '#' | '&' | '/')) {
+            let rest = &text[i + 1..];
+            let end = rest.find(|c: char| !is_tag_char(c)).unwrap_or(rest.len());
+            if let Some(tag) = clean(&rest[..end]) {
+                tags.push(tag);
+            }
+        }
+        prev = Some(c);
+    }
+}
+
+/// Collects the tags of a note: `tags` from the front matter followed by
+/// inline `#tags` outside code. Each tag appears once, without the `#`.
+pub fn extract_tags(markdown: &str, properties: &[Property]) -> Vec<String> {
+    let mut tags = Vec::new();
+    if let Some(property) = properties.iter().find(|p| p.key == "tags") {
+        if let PropertyValue::List(items) = &property.value {
+            tags.extend(items.iter().filter_map(|item| clean(item)));
+        }
+    }
+
+    let mut in_code_block = false;
+    for event in Parser::new_ext(front_matter::body(markdown), parser_options()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Text(text) if !in_code_block => inline_tags(&text, &mut tags),
+            _ => {}
+        }
+    }
+
+    let mut seen = HashSet::new();
+    tags.retain(|tag| seen.insert(tag.clone()));
+    tags
+}
+
+/// A tag together with its ancestors, e.g. `a/b/c` -> `a`, `a/b`, `a/b/c`.
+pub fn with_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices('/').map(|(i, _)| &tag[..i]).chain(std::iter::once(tag))
+}
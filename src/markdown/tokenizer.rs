@@ -0,0 +1,150 @@
+use std::ops::{BitOr, BitOrAssign, Range};
+use pulldown_cmark::{Event, Parser, Tag};
+use crate::markdown::front_matter::front_matter_len;
+use crate::markdown::render::parser_options;
+
+/// Syntax classes a byte of markdown source can belong to. Several classes
+/// can apply at once, e.g. strong text inside a heading.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TokenStyle(u16);
+
+impl TokenStyle {
+    pub const PLAIN: TokenStyle = TokenStyle(0);
+    pub const HEADING: TokenStyle = TokenStyle(1 << 0);
+    pub const EMPHASIS: TokenStyle = TokenStyle(1 << 1);
+    pub const STRONG: TokenStyle = TokenStyle(1 << 2);
+    pub const STRIKETHROUGH: TokenStyle = TokenStyle(1 << 3);
+    pub const LINK: TokenStyle = TokenStyle(1 << 4);
+    pub const CODE: TokenStyle = TokenStyle(1 << 5);
+    pub const CODE_BLOCK: TokenStyle = TokenStyle(1 << 6);
+    pub const QUOTE: TokenStyle = TokenStyle(1 << 7);
+    pub const LIST_MARKER: TokenStyle = TokenStyle(1 << 8);
+    pub const FRONT_MATTER: TokenStyle = TokenStyle(1 << 9);
+    pub const HTML: TokenStyle = TokenStyle(1 << 10);
+    pub const RULE: TokenStyle = TokenStyle(1 << 11);
+
+    pub fn contains(self, other: TokenStyle) -> bool {
+        self.0 & other.0 == other.0 && other.0 != 0
+    }
+}
+
+impl BitOr for TokenStyle {
+    type Output = TokenStyle;
+
+    fn bitor(self, rhs: TokenStyle) -> TokenStyle {
+        TokenStyle(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for TokenStyle {
+    fn bitor_assign(&mut self, rhs: TokenStyle) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub style: TokenStyle,
+    /// Markup characters (`#`, `**`, link targets, fences...) rather than content.
+    pub markup: bool,
+}
+
+pub type TokenLine = Vec<Token>;
+
+struct Painter {
+    styles: Vec<TokenStyle>,
+    content: Vec<bool>,
+}
+
+impl Painter {
+    fn paint(&mut self, range: Range<usize>, style: TokenStyle) {
+        for s in &mut self.styles[range] {
+            *s |= style;
+        }
+    }
+
+    fn mark_content(&mut self, range: Range<usize>) {
+        for c in &mut self.content[range] {
+            *c = true;
+        }
+    }
+}
+
+/// Splits markdown source into lines of styled tokens using the offsets
+/// reported by pulldown-cmark. Concatenating a line's tokens gives back the
+/// original line.
+pub fn tokenize(source: &str) -> Vec<TokenLine> {
+    let mut painter = Painter {
+        styles: vec![TokenStyle::PLAIN; source.len()],
+        content: vec![false; source.len()],
+    };
+
+    let body_start = front_matter_len(source).unwrap_or(0);
+    if body_start > 0 {
+        painter.paint(0..body_start, TokenStyle::FRONT_MATTER);
+        let first_line_end = source.find('\n').unwrap_or(0) + 1;
+        let closing_start = source[..body_start].trim_end_matches('\n').rfind('\n').map_or(0, |i| i + 1);
+        painter.mark_content(first_line_end..closing_start);
+    }
+
+    let body = &source[body_start..];
+    for (event, range) in Parser::new_ext(body, parser_options()).into_offset_iter() {
+        let range = range.start + body_start..range.end + body_start;
+        match event {
+            Event::Start(Tag::Heading(..)) => painter.paint(range, TokenStyle::HEADING),
+            Event::Start(Tag::Emphasis) => painter.paint(range, TokenStyle::EMPHASIS),
+            Event::Start(Tag::Strong) => painter.paint(range, TokenStyle::STRONG),
+            Event::Start(Tag::Strikethrough) => painter.paint(range, TokenStyle::STRIKETHROUGH),
+            Event::Start(Tag::Link(..)) | Event::Start(Tag::Image(..)) => painter.paint(range, TokenStyle::LINK),
+            Event::Start(Tag::BlockQuote) => painter.paint(range, TokenStyle::QUOTE),
+            Event::Start(Tag::CodeBlock(_)) => painter.paint(range, TokenStyle::CODE_BLOCK),
+            Event::Start(Tag::Item) => {
+                let marker_len = source[range.clone()]
+                    .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '*' | '+' | '.' | ')')))
+                    .unwrap_or(0);
+                painter.paint(range.start..range.start + marker_len, TokenStyle::LIST_MARKER);
+            },
+            Event::TaskListMarker(_) => painter.paint(range, TokenStyle::LIST_MARKER),
+            Event::Rule => painter.paint(range, TokenStyle::RULE),
+            Event::Html(_) => painter.paint(range, TokenStyle::HTML),
+            Event::Code(_) => {
+                painter.paint(range.clone(), TokenStyle::CODE);
+                let raw = &source[range.clone()];
+                let ticks = raw.len() - raw.trim_start_matches('`').len();
+                if raw.len() >= 2 * ticks {
+                    painter.mark_content(range.start + ticks..range.end - ticks);
+                }
+            },
+            Event::Text(_) => painter.mark_content(range),
+            _ => {}
+        }
+    }
+
+    let mut lines = vec![Vec::new()];
+    let mut current: Option<Token> = None;
+    for (i, c) in source.char_indices() {
+        if c == '\n' {
+            if let Some(token) = current.take() {
+                lines.last_mut().unwrap().push(token);
+            }
+            lines.push(Vec::new());
+            continue;
+        }
+        let style = painter.styles[i];
+        let markup = style != TokenStyle::PLAIN && !painter.content[i];
+        match &mut current {
+            Some(token) if token.style == style && token.markup == markup => token.text.push(c),
+            _ => {
+                if let Some(token) = current.take() {
+                    lines.last_mut().unwrap().push(token);
+                }
+                current = Some(Token { text: c.to_string(), style, markup });
+            },
+        }
+    }
+    if let Some(token) = current {
+        lines.last_mut().unwrap().push(token);
+    }
+    lines
+}
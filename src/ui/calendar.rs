@@ -0,0 +1,178 @@
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveTime};
+use slint::{ModelRc, SharedString, VecModel};
+use std::collections::HashSet;
+use std::rc::Rc;
+use crate::calendar::recurrence::{Frequency, Occurrence, Recurrence};
+use crate::db::calendar::CalendarEvent;
+use crate::ui::{CalendarDay, EventData};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalendarMode {
+    Month,
+    Week,
+}
+
+/// What the calendar tool is showing.
+pub struct CalendarState {
+    pub mode: CalendarMode,
+    /// Any day inside the shown month or week.
+    pub anchor: NaiveDate,
+    pub selected: NaiveDate,
+}
+
+/// A cell of the calendar grid, converted to a `CalendarDay` on the UI thread.
+pub struct DayCell {
+    pub date: NaiveDate,
+    pub in_range: bool,
+    pub is_today: bool,
+    pub has_daily_note: bool,
+    pub events: Vec<Occurrence>,
+}
+
+impl CalendarState {
+    pub fn new(today: NaiveDate) -> Self {
+        CalendarState { mode: CalendarMode::Month, anchor: today, selected: today }
+    }
+
+    /// Moves by one month or week; `0` jumps back to `today`.
+    pub fn navigate(&mut self, step: i32, today: NaiveDate) {
+        self.anchor = match (step, self.mode) {
+            (0, _) => {
+                self.selected = today;
+                Some(today)
+            },
+            (s, CalendarMode::Month) if s < 0 => self.anchor.checked_sub_months(Months::new(1)),
+            (_, CalendarMode::Month) => self.anchor.checked_add_months(Months::new(1)),
+            (s, CalendarMode::Week) if s < 0 => self.anchor.checked_sub_days(Days::new(7)),
+            (_, CalendarMode::Week) => self.anchor.checked_add_days(Days::new(7)),
+        }.unwrap_or(self.anchor);
+    }
+
+    /// First and last day of the grid. Month grids always span six weeks
+    /// starting on Monday so the layout doesn't jump between months.
+    pub fn range(&self) -> (NaiveDate, NaiveDate) {
+        let start = match self.mode {
+            CalendarMode::Month => week_start(self.anchor.with_day(1).unwrap()),
+            CalendarMode::Week => week_start(self.anchor),
+        };
+        let days = if self.mode == CalendarMode::Month { 42 } else { 7 };
+        (start, start + Days::new(days - 1))
+    }
+
+    pub fn title(&self) -> String {
+        match self.mode {
+            CalendarMode::Month => format!("{}年{}月", self.anchor.year(), self.anchor.month()),
+            CalendarMode::Week => {
+                let (start, end) = self.range();
+                format!("{} – {}", start.format("%Y-%m-%d"), end.format("%m-%d"))
+            },
+        }
+    }
+
+    pub fn build_days(&self, events: &[Occurrence], daily_notes: &HashSet<NaiveDate>, today: NaiveDate) -> Vec<DayCell> {
+        let (start, end) = self.range();
+        start.iter_days().take_while(|date| *date <= end).map(|date| DayCell {
+            date,
+            in_range: self.mode == CalendarMode::Week || date.month() == self.anchor.month(),
+            is_today: date == today,
+            has_daily_note: daily_notes.contains(&date),
+            events: events.iter().filter(|occurrence| occurrence.start.date() == date).cloned().collect(),
+        }).collect()
+    }
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday() as u64)
+}
+
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), DATE_FORMAT).ok()
+}
+
+/// Builds an event from the edit form. Exceptions aren't part of the form
+/// and are left empty.
+pub fn event_from_data(data: &EventData) -> Result<CalendarEvent, String> {
+    let date = parse_date(&data.date).ok_or("日期应为 YYYY-MM-DD")?;
+    let parse_time = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| "时间应为 HH:MM".to_string());
+    let start = if data.start.trim().is_empty() { NaiveTime::MIN } else { parse_time(&data.start)? };
+    let end = if data.end.trim().is_empty() { None } else { Some(date.and_time(parse_time(&data.end)?)) };
+    let reminder = match data.reminder.trim() {
+        "" => None,
+        minutes => Some(minutes.parse::<i64>().ok().filter(|m| *m >= 0).ok_or("提醒应为提前的分钟数")?),
+    };
+    Ok(CalendarEvent {
+        id: data.id as i64,
+        title: data.title.trim().to_string(),
+        description: data.description.to_string(),
+        start: date.and_time(start),
+        end,
+        rrule: rrule_from_data(data)?,
+        exceptions: Vec::new(),
+        reminder,
+    })
+}
+
+/// Applies the repeat fields of the form to the event's original rule, so
+/// parts the form can't edit (`BYDAY`, `COUNT`) survive a save.
+fn rrule_from_data(data: &EventData) -> Result<Option<String>, String> {
+    let Some(frequency) = Frequency::from_name(&data.repeat) else {
+        return Ok(None);
+    };
+    let mut rule = Recurrence::parse(&data.rrule).ok()
+        .filter(|rule| rule.frequency == frequency)
+        .unwrap_or_else(|| Recurrence::new(frequency));
+    rule.interval = match data.interval.trim() {
+        "" => 1,
+        interval => interval.parse().ok().filter(|n| *n > 0).ok_or("间隔应为正整数")?,
+    };
+    if !data.until.trim().is_empty() {
+        let until = parse_date(&data.until).ok_or("结束日期应为 YYYY-MM-DD")?;
+        rule.until = until.and_hms_opt(23, 59, 59);
+        rule.count = None;
+    } else {
+        rule.until = None;
+    }
+    Ok(Some(rule.to_rrule()))
+}
+
+pub fn event_data(occurrence: &Occurrence) -> EventData {
+    let event = &occurrence.event;
+    let rule = event.rrule.as_deref().and_then(|rrule| Recurrence::parse(rrule).ok());
+    EventData {
+        id: event.id as i32,
+        title: event.title.as_str().into(),
+        date: event.start.format(DATE_FORMAT).to_string().into(),
+        start: occurrence.start.format("%H:%M").to_string().into(),
+        end: occurrence.end.map(|end| end.format("%H:%M").to_string()).unwrap_or_default().into(),
+        description: event.description.as_str().into(),
+        rrule: event.rrule.clone().unwrap_or_default().into(),
+        repeat: rule.as_ref().map(|rule| rule.frequency.name()).unwrap_or_default().into(),
+        interval: rule.as_ref().map(|rule| rule.interval.to_string()).unwrap_or_default().into(),
+        until: rule.as_ref().and_then(|rule| rule.until).map(|until| until.format(DATE_FORMAT).to_string()).unwrap_or_default().into(),
+        reminder: event.reminder.map(|minutes| minutes.to_string()).unwrap_or_default().into(),
+        occurrence: occurrence.start.format(DATE_FORMAT).to_string().into(),
+    }
+}
+
+/// Text of the in-app notification for a due reminder.
+pub fn reminder_text(occurrence: &Occurrence) -> String {
+    format!("⏰ {} {}", occurrence.start.format("%m-%d %H:%M"), occurrence.event.title)
+}
+
+pub fn date_string(date: NaiveDate) -> SharedString {
+    date.format(DATE_FORMAT).to_string().into()
+}
+
+pub fn to_model(cells: Vec<DayCell>) -> ModelRc<CalendarDay> {
+    let days = cells.into_iter().map(|cell| CalendarDay {
+        date: date_string(cell.date),
+        day: cell.date.day() as i32,
+        in_range: cell.in_range,
+        is_today: cell.is_today,
+        has_daily_note: cell.has_daily_note,
+        events: ModelRc::new(VecModel::from(cell.events.iter().map(event_data).collect::<Vec<EventData>>())),
+    }).collect::<Vec<CalendarDay>>();
+    ModelRc::new(Rc::new(VecModel::from(days)))
+}
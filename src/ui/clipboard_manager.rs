@@ -0,0 +1,78 @@
+use chrono::NaiveDateTime;
+use std::thread;
+use std::time::Duration;
+use crate::db::clipboard::ClipboardEntry;
+use crate::ui::ClipboardItem;
+
+/// Longer copies (e.g. a whole book) are not recorded.
+const MAX_LENGTH: usize = 1 << 20;
+
+pub fn should_record(text: &str) -> bool {
+    !text.trim().is_empty() && text.len() <= MAX_LENGTH
+}
+
+/// The text between the editor's anchor and cursor (byte offsets, in either
+/// order), or `None` when nothing is selected.
+pub fn selection(content: &str, anchor: i32, cursor: i32) -> Option<String> {
+    let (start, end) = (anchor.min(cursor).max(0) as usize, anchor.max(cursor).max(0) as usize);
+    let end = end.min(content.len());
+    if start >= end || !content.is_char_boundary(start) || !content.is_char_boundary(end) {
+        return None;
+    }
+    Some(content[start..end].to_string())
+}
+
+fn age(copied_at: NaiveDateTime, now: NaiveDateTime) -> String {
+    let minutes = (now - copied_at).num_minutes();
+    match minutes {
+        i64::MIN..=0 => "刚刚".to_string(),
+        1..=59 => format!("{} 分钟前", minutes),
+        60..=1439 => format!("{} 小时前", minutes / 60),
+        _ => copied_at.format("%Y-%m-%d %H:%M").to_string(),
+    }
+}
+
+pub fn item(entry: &ClipboardEntry, now: NaiveDateTime) -> ClipboardItem {
+    // 列表里只显示第一行
+    let first_line = entry.content.trim().lines().next().unwrap_or_default();
+    let mut preview = first_line.chars().take(120).collect::<String>();
+    if preview.len() < entry.content.trim().len() {
+        preview.push('…');
+    }
+    ClipboardItem {
+        id: entry.id as i32,
+        content: entry.content.as_str().into(),
+        preview: preview.into(),
+        source: entry.source.clone().unwrap_or_default().into(),
+        time: age(entry.copied_at, now).into(),
+        pinned: entry.pinned,
+    }
+}
+
+/// Polls the system clipboard once a second on a background thread and
+/// calls `on_copy` with new text. Gives up when the clipboard is not
+/// available (e.g. headless sessions).
+pub fn watch(on_copy: impl Fn(String) -> bool + Send + 'static) {
+    thread::spawn(move || {
+        let mut clipboard = match arboard::Clipboard::new() {
+            Ok(clipboard) => clipboard,
+            Err(e) => {
+                eprintln!("System clipboard not available: {}", e);
+                return;
+            },
+        };
+        // 启动前已经在剪贴板里的内容不记录
+        let mut last = clipboard.get_text().unwrap_or_default();
+        loop {
+            thread::sleep(Duration::from_secs(1));
+            let Ok(text) = clipboard.get_text() else { continue };
+            if text != last {
+                last = text.clone();
+                // 返回 false 表示应用已经退出
+                if should_record(&text) && !on_copy(text) {
+                    return;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,232 @@
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+use digest::{FixedOutput, Update};
+use hmac::{Mac, SimpleHmac};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 5] = [Algorithm::Md5, Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512, Algorithm::Blake3];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA-1",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha512 => "SHA-512",
+            Algorithm::Blake3 => "BLAKE3",
+        }
+    }
+
+    fn hasher(self, key: Option<&[u8]>) -> Box<dyn Hasher> {
+        match (self, key) {
+            (Algorithm::Md5, None) => Box::new(md5::Md5::default()),
+            (Algorithm::Sha1, None) => Box::new(sha1::Sha1::default()),
+            (Algorithm::Sha256, None) => Box::new(sha2::Sha256::default()),
+            (Algorithm::Sha512, None) => Box::new(sha2::Sha512::default()),
+            (Algorithm::Blake3, None) => Box::new(blake3::Hasher::new()),
+            (Algorithm::Md5, Some(key)) => hmac::<md5::Md5>(key),
+            (Algorithm::Sha1, Some(key)) => hmac::<sha1::Sha1>(key),
+            (Algorithm::Sha256, Some(key)) => hmac::<sha2::Sha256>(key),
+            (Algorithm::Sha512, Some(key)) => hmac::<sha2::Sha512>(key),
+            (Algorithm::Blake3, Some(key)) => hmac::<blake3::Hasher>(key),
+        }
+    }
+}
+
+/// Lets plain hashers and HMACs sit side by side.
+trait Hasher: Send {
+    fn feed(&mut self, data: &[u8]);
+    fn finish(self: Box<Self>) -> Vec<u8>;
+}
+
+impl<T: Update + FixedOutput + Send> Hasher for T {
+    fn feed(&mut self, data: &[u8]) {
+        self.update(data);
+    }
+
+    fn finish(self: Box<Self>) -> Vec<u8> {
+        self.finalize_fixed().to_vec()
+    }
+}
+
+fn hmac<D>(key: &[u8]) -> Box<dyn Hasher>
+where
+    D: digest::Digest + digest::core_api::BlockSizeUser + Send + 'static,
+{
+    // HMAC 接受任意长度的密钥，不会失败
+    Box::new(<SimpleHmac<D> as Mac>::new_from_slice(key).expect("HMAC accepts any key length"))
+}
+
+/// Hex digests of every algorithm, in `Algorithm::ALL` order.
+pub type Digests = Vec<(Algorithm, String)>;
+
+/// Feeds the same bytes to all algorithms at once, so a file is read only once.
+struct Hashers(Vec<(Algorithm, Box<dyn Hasher>)>);
+
+impl Hashers {
+    /// With a key every algorithm becomes its HMAC.
+    fn new(key: Option<&[u8]>) -> Self {
+        Hashers(Algorithm::ALL.iter().map(|algorithm| (*algorithm, algorithm.hasher(key))).collect())
+    }
+
+    fn feed(&mut self, data: &[u8]) {
+        for (_, hasher) in &mut self.0 {
+            hasher.feed(data);
+        }
+    }
+
+    fn finish(self) -> Digests {
+        self.0.into_iter().map(|(algorithm, hasher)| (algorithm, hex::encode(hasher.finish()))).collect()
+    }
+}
+
+pub fn hash_text(text: &str, key: Option<&[u8]>) -> Digests {
+    let mut hashers = Hashers::new(key);
+    hashers.feed(text.as_bytes());
+    hashers.finish()
+}
+
+/// Reads the file in chunks, so large files never have to fit in memory.
+/// Returns the digests and the number of bytes read.
+pub fn hash_file(path: &Path, key: Option<&[u8]>) -> io::Result<(Digests, u64)> {
+    let mut file = File::open(path)?;
+    let mut hashers = Hashers::new(key);
+    let mut buffer = vec![0; 1 << 16];
+    let mut total = 0;
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hashers.feed(&buffer[..read]);
+        total += read as u64;
+    }
+    Ok((hashers.finish(), total))
+}
+
+/// The algorithm whose digest equals `expected`, ignoring case, spaces and
+/// the colons some tools print between bytes.
+pub fn find_match(digests: &Digests, expected: &str) -> Option<Algorithm> {
+    let expected = expected.chars().filter(|c| !c.is_whitespace() && *c != ':').collect::<String>().to_lowercase();
+    if expected.is_empty() {
+        return None;
+    }
+    digests.iter().find(|(_, digest)| *digest == expected).map(|(algorithm, _)| *algorithm)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Base64,
+    Hex,
+    Url,
+}
+
+impl Encoding {
+    /// Index in the view's ComboBox.
+    pub fn from_index(index: i32) -> Encoding {
+        match index {
+            1 => Encoding::Hex,
+            2 => Encoding::Url,
+            _ => Encoding::Base64,
+        }
+    }
+
+    pub fn encode(self, text: &str) -> String {
+        match self {
+            Encoding::Base64 => STANDARD.encode(text),
+            Encoding::Hex => hex::encode(text),
+            Encoding::Url => urlencoding::encode(text).into_owned(),
+        }
+    }
+
+    pub fn decode(self, text: &str) -> Result<String, String> {
+        let bytes = match self {
+            Encoding::Base64 => {
+                // 也接受 URL 安全字母表和省略的填充
+                let text = text.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+                [STANDARD, URL_SAFE, STANDARD_NO_PAD, URL_SAFE_NO_PAD].iter()
+                    .find_map(|engine| engine.decode(&text).ok())
+                    .ok_or("不是有效的 Base64")?
+            },
+            Encoding::Hex => {
+                let text = text.chars().filter(|c| !c.is_whitespace() && *c != ':').collect::<String>();
+                hex::decode(text).map_err(|e| format!("不是有效的十六进制: {}", e))?
+            },
+            Encoding::Url => return urlencoding::decode(text.trim()).map(|text| text.into_owned()).map_err(|_| "解码结果不是 UTF-8 文本".to_string()),
+        };
+        String::from_utf8(bytes).map_err(|_| "解码结果不是 UTF-8 文本".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(digests: &Digests, algorithm: Algorithm) -> &str {
+        &digests.iter().find(|(a, _)| *a == algorithm).unwrap().1
+    }
+
+    #[test]
+    fn matches_published_vectors() {
+        // RFC 1321, FIPS 180 和 BLAKE3 官方测试向量
+        let empty = hash_text("", None);
+        assert_eq!(digest(&empty, Algorithm::Md5), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(digest(&empty, Algorithm::Blake3), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+        let abc = hash_text("abc", None);
+        assert_eq!(digest(&abc, Algorithm::Md5), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(digest(&abc, Algorithm::Sha1), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(digest(&abc, Algorithm::Sha256), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            digest(&abc, Algorithm::Sha512),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        );
+        assert_eq!(digest(&abc, Algorithm::Blake3), "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+        assert_eq!(find_match(&abc, "A9:99:3E:36 4706816aba3e25717850c26c9cd0d89d"), Some(Algorithm::Sha1));
+        assert_eq!(find_match(&abc, ""), None);
+    }
+
+    #[test]
+    fn matches_hmac_vectors() {
+        // RFC 2104 (MD5)、RFC 2202 (SHA-1) 和 RFC 4231 的第 1、2 组
+        let md5 = hash_text("Hi There", Some(&[0x0b; 16]));
+        assert_eq!(digest(&md5, Algorithm::Md5), "9294727a3638bb1c13f48ef8158bfc9d");
+        let first = hash_text("Hi There", Some(&[0x0b; 20]));
+        assert_eq!(digest(&first, Algorithm::Sha1), "b617318655057264e28bc0b6fb378c8ef146be00");
+        assert_eq!(digest(&first, Algorithm::Sha256), "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+        assert_eq!(
+            digest(&first, Algorithm::Sha512),
+            "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+        );
+        let second = hash_text("what do ya want for nothing?", Some(b"Jefe"));
+        assert_eq!(digest(&second, Algorithm::Md5), "750c783e6ab0b503eaa86e310a5db738");
+        assert_eq!(digest(&second, Algorithm::Sha256), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn streams_files_across_the_buffer() {
+        let path = std::env::temp_dir().join(format!("nodian-hash-{}", std::process::id()));
+        let text = (0..70_000).map(|i| (b'a' + (i % 26) as u8) as char).collect::<String>();
+        for len in [0, 65_535, 65_536, 65_537, 70_000] {
+            std::fs::write(&path, &text[..len]).unwrap();
+            for key in [None, Some(&b"key"[..])] {
+                let (digests, read) = hash_file(&path, key).unwrap();
+                assert_eq!(read, len as u64);
+                assert_eq!(digests, hash_text(&text[..len], key), "{} bytes", len);
+            }
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
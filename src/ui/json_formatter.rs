@@ -0,0 +1,268 @@
+use serde::Serialize;
+use serde_json::ser::{PrettyFormatter, Serializer};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use crate::ui::JsonNode;
+
+/// A parse error with 1-based line and column, as reported by serde_json.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "第 {} 行，第 {} 列: {}", self.line, self.column, self.message)
+    }
+}
+
+pub fn parse(input: &str) -> Result<Value, JsonError> {
+    serde_json::from_str(input).map_err(|e| {
+        // Display 会带上 " at line x column y"，这里单独显示
+        let message = e.to_string();
+        let message = message.rsplit_once(" at line ").map_or(message.as_str(), |(message, _)| message).to_string();
+        JsonError { line: e.line(), column: e.column(), message }
+    })
+}
+
+/// Recursively sorts object keys.
+pub fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(entries.into_iter().map(|(key, value)| (key, sort_keys(value))).collect::<Map<_, _>>())
+        },
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+/// Pretty-prints with `indent` (spaces, or a tab when `None`).
+pub fn pretty(value: &Value, indent: Option<usize>) -> String {
+    let indent = match indent {
+        Some(width) => " ".repeat(width),
+        None => "\t".to_string(),
+    };
+    let mut out = Vec::new();
+    let mut serializer = Serializer::with_formatter(&mut out, PrettyFormatter::with_indent(indent.as_bytes()));
+    // 写入 Vec 不会失败
+    value.serialize(&mut serializer).unwrap_or_default();
+    String::from_utf8(out).unwrap_or_default()
+}
+
+pub fn minify(value: &Value) -> String {
+    value.to_string()
+}
+
+/// JSONPath-style path of a child, e.g. `$.items[0]["odd key"]`.
+pub fn child_path(parent: &str, key: &str) -> String {
+    let simple = key.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if simple {
+        format!("{}.{}", parent, key)
+    } else {
+        format!("{}[{}]", parent, Value::String(key.to_string()))
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Which nodes of the tree are folded and which match the current query.
+#[derive(Default)]
+pub struct TreeState {
+    pub collapsed: HashSet<String>,
+    pub matches: HashSet<String>,
+}
+
+impl TreeState {
+    /// Unfolds the ancestors of the matched nodes so they are visible.
+    pub fn reveal_matches(&mut self) {
+        let matches = &self.matches;
+        self.collapsed.retain(|path| !matches.iter().any(|m| m.len() > path.len() && m.starts_with(path.as_str())
+            && matches!(m.as_bytes()[path.len()], b'.' | b'[')));
+    }
+}
+
+/// Flattens `value` into tree rows, leaving out the children of collapsed paths.
+pub fn tree(value: &Value, state: &TreeState) -> Vec<JsonNode> {
+    let mut nodes = Vec::new();
+    push_node(&mut nodes, "", "$".to_string(), value, 0, state);
+    nodes
+}
+
+fn push_node(nodes: &mut Vec<JsonNode>, key: &str, path: String, value: &Value, depth: i32, state: &TreeState) {
+    let children = match value {
+        Value::Array(items) => items.len(),
+        Value::Object(map) => map.len(),
+        _ => 0,
+    };
+    let summary = match value {
+        Value::Array(_) => format!("[{}]", children),
+        Value::Object(_) => format!("{{{}}}", children),
+        scalar => scalar.to_string(),
+    };
+    let expanded = !state.collapsed.contains(&path);
+    nodes.push(JsonNode {
+        key: key.into(),
+        value: summary.into(),
+        kind: kind(value).into(),
+        depth,
+        path: path.as_str().into(),
+        expandable: children > 0,
+        expanded,
+        matched: state.matches.contains(&path),
+    });
+    if !expanded {
+        return;
+    }
+    match value {
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                push_node(nodes, &format!("[{}]", i), format!("{}[{}]", path, i), item, depth + 1, state);
+            }
+        },
+        Value::Object(map) => {
+            for (key, item) in map {
+                push_node(nodes, key, child_path(&path, key), item, depth + 1, state);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Paths of every object and array, used to collapse the whole tree.
+pub fn container_paths(value: &Value) -> HashSet<String> {
+    fn walk(paths: &mut HashSet<String>, path: String, value: &Value) {
+        match value {
+            Value::Array(items) if !items.is_empty() => {
+                for (i, item) in items.iter().enumerate() {
+                    walk(paths, format!("{}[{}]", path, i), item);
+                }
+                paths.insert(path);
+            },
+            Value::Object(map) if !map.is_empty() => {
+                for (key, item) in map {
+                    walk(paths, child_path(&path, key), item);
+                }
+                paths.insert(path);
+            },
+            _ => {},
+        }
+    }
+    let mut paths = HashSet::new();
+    walk(&mut paths, "$".to_string(), value);
+    paths
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Change {
+    pub fn name(self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Changed => "changed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    pub path: String,
+    pub change: Change,
+    /// Compact JSON of the value on each side, `None` when it is missing.
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// Structural diff: objects are compared key by key and arrays index by
+/// index; anything else that differs is reported as changed.
+pub fn diff(left: &Value, right: &Value) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_at("$", left, right, &mut differences);
+    differences
+}
+
+fn diff_at(path: &str, left: &Value, right: &Value, out: &mut Vec<Difference>) {
+    let entry = |path: String, change, left: Option<&Value>, right: Option<&Value>| Difference {
+        path,
+        change,
+        left: left.map(Value::to_string),
+        right: right.map(Value::to_string),
+    };
+    match (left, right) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                match b.get(key) {
+                    Some(other) => diff_at(&child_path(path, key), value, other, out),
+                    None => out.push(entry(child_path(path, key), Change::Removed, Some(value), None)),
+                }
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                out.push(entry(child_path(path, key), Change::Added, None, Some(value)));
+            }
+        },
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let item_path = format!("{}[{}]", path, i);
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => diff_at(&item_path, x, y, out),
+                    (Some(x), None) => out.push(entry(item_path, Change::Removed, Some(x), None)),
+                    (None, y) => out.push(entry(item_path, Change::Added, None, y)),
+                }
+            }
+        },
+        (a, b) if a != b => out.push(entry(path.to_string(), Change::Changed, Some(a), Some(b))),
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_by_structure() {
+        let left = parse(r#"{"id": 1, "name": "a", "items": [1, 2, 3], "meta": {"x": true}}"#).unwrap();
+        let right = parse(r#"{"id": 1, "name": "b", "items": [1, 5], "meta": {"x": true, "odd key": null}}"#).unwrap();
+        let found = diff(&left, &right).into_iter()
+            .map(|difference| (difference.path, difference.change, difference.left, difference.right))
+            .collect::<Vec<_>>();
+        let some = |text: &str| Some(text.to_string());
+        assert_eq!(found, [
+            ("$.name".to_string(), Change::Changed, some("\"a\""), some("\"b\"")),
+            ("$.items[1]".to_string(), Change::Changed, some("2"), some("5")),
+            ("$.items[2]".to_string(), Change::Removed, some("3"), None),
+            ("$.meta[\"odd key\"]".to_string(), Change::Added, None, some("null")),
+        ]);
+        assert!(diff(&left, &left).is_empty());
+        // 类型不同也算修改
+        assert_eq!(diff(&parse("[1]").unwrap(), &parse("{}").unwrap())[0].change, Change::Changed);
+    }
+
+    #[test]
+    fn keeps_numbers_as_written() {
+        let input = r#"{"b": 12345678901234567890123, "a": [1.10, -0.0, 9007199254740993]}"#;
+        let value = parse(input).unwrap();
+        assert_eq!(minify(&value), r#"{"b":12345678901234567890123,"a":[1.10,-0.0,9007199254740993]}"#);
+        assert_eq!(minify(&sort_keys(value.clone())), r#"{"a":[1.10,-0.0,9007199254740993],"b":12345678901234567890123}"#);
+        assert_eq!(pretty(&value, Some(2)), "{\n  \"b\": 12345678901234567890123,\n  \"a\": [\n    1.10,\n    -0.0,\n    9007199254740993\n  ]\n}");
+    }
+}
@@ -0,0 +1,339 @@
+use serde_json::Value;
+use crate::ui::json_formatter::child_path;
+
+/// A JSONPath subset: `$`, `.name`, `['name']`, `[0]`, `[-1]`, `[1:3]`,
+/// `*`, `..` (recursive descent), unions like `[0,2]` and simple filters
+/// such as `[?(@.price < 10)]` or `[?(@.tags)]`.
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Filter(Filter),
+}
+
+#[derive(Debug, PartialEq)]
+struct Filter {
+    /// Keys after `@`.
+    path: Vec<String>,
+    /// `None` only tests that the key exists.
+    comparison: Option<(String, Value)>,
+}
+
+const OPERATORS: [&str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+
+fn parse(query: &str) -> Result<Vec<Segment>, String> {
+    let query = query.trim();
+    let mut rest = query.strip_prefix('$').ok_or("查询应以 $ 开头")?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        let (descendant, after) = match rest.strip_prefix("..") {
+            Some(after) => (true, after),
+            None => (false, rest),
+        };
+        let (selectors, after) = if let Some(bracket) = after.strip_prefix('[') {
+            let end = closing_bracket(bracket).ok_or("缺少 ]")?;
+            (parse_bracket(&bracket[..end])?, &bracket[end + 1..])
+        } else {
+            let after = if descendant { after } else { after.strip_prefix('.').ok_or_else(|| format!("无法解析: {}", after))? };
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let name = &after[..end];
+            let selector = match name {
+                "" => return Err("缺少键名".to_string()),
+                "*" => Selector::Wildcard,
+                name => Selector::Name(name.to_string()),
+            };
+            (vec![selector], &after[end..])
+        };
+        segments.push(if descendant { Segment::Descendant(selectors) } else { Segment::Child(selectors) });
+        rest = after;
+    }
+    Ok(segments)
+}
+
+/// Index of the `]` closing a bracket, skipping quoted strings and filter parentheses.
+fn closing_bracket(source: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut depth = 0;
+    for (i, c) in source.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {},
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ']') if depth == 0 => return Some(i),
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Splits on commas outside of quotes.
+fn split_union(source: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in source.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {},
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ',') => {
+                parts.push(&source[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    parts.push(&source[start..]);
+    parts
+}
+
+fn parse_bracket(content: &str) -> Result<Vec<Selector>, String> {
+    let content = content.trim();
+    if let Some(filter) = content.strip_prefix('?') {
+        let filter = filter.trim().strip_prefix('(').and_then(|f| f.strip_suffix(')')).ok_or("过滤器应写成 ?(...)")?;
+        return Ok(vec![Selector::Filter(parse_filter(filter)?)]);
+    }
+    split_union(content).into_iter().map(|part| {
+        let part = part.trim();
+        if part == "*" {
+            Ok(Selector::Wildcard)
+        } else if let Some(name) = unquote(part) {
+            Ok(Selector::Name(name))
+        } else if let Some((start, end)) = part.split_once(':') {
+            let bound = |s: &str| -> Result<Option<i64>, String> {
+                let s = s.trim();
+                if s.is_empty() { Ok(None) } else { s.parse().map(Some).map_err(|_| format!("无效的切片: {}", part)) }
+            };
+            Ok(Selector::Slice(bound(start)?, bound(end)?))
+        } else {
+            part.parse().map(Selector::Index).map_err(|_| format!("无效的下标: {}", part))
+        }
+    }).collect()
+}
+
+/// `'name'` or `"name"` without the quotes.
+fn unquote(part: &str) -> Option<String> {
+    let quote = part.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let inner = part.strip_prefix(quote)?.strip_suffix(quote)?;
+    if quote == '"' {
+        serde_json::from_str(part).ok()
+    } else {
+        Some(inner.replace("\\'", "'"))
+    }
+}
+
+/// The first comparison operator outside of quotes, with its index.
+fn find_operator(filter: &str) -> Option<(usize, &'static str)> {
+    let mut quote = None;
+    for (i, c) in filter.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {},
+            (None, '\'' | '"') => quote = Some(c),
+            (None, _) => {
+                if let Some(op) = OPERATORS.iter().find(|op| filter[i..].starts_with(**op)) {
+                    return Some((i, op));
+                }
+            },
+        }
+    }
+    None
+}
+
+fn parse_filter(filter: &str) -> Result<Filter, String> {
+    let (left, comparison) = match find_operator(filter) {
+        Some((i, op)) => {
+            let literal = filter[i + op.len()..].trim();
+            let value = match unquote(literal) {
+                Some(text) => Value::String(text),
+                None => serde_json::from_str(literal).map_err(|_| format!("无效的值: {}", literal))?,
+            };
+            (&filter[..i], Some((op.to_string(), value)))
+        },
+        None => (filter, None),
+    };
+    let path = left.trim().strip_prefix('@').ok_or("过滤器应以 @ 开头")?;
+    Ok(Filter {
+        path: path.split('.').filter(|key| !key.is_empty()).map(String::from).collect(),
+        comparison,
+    })
+}
+
+fn children<'a>(path: &str, value: &'a Value) -> Vec<(String, &'a Value)> {
+    match value {
+        Value::Array(items) => items.iter().enumerate().map(|(i, item)| (format!("{}[{}]", path, i), item)).collect(),
+        Value::Object(map) => map.iter().map(|(key, item)| (child_path(path, key), item)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn descendants<'a>(path: String, value: &'a Value, out: &mut Vec<(String, &'a Value)>) {
+    let nodes = children(&path, value);
+    out.push((path, value));
+    for (path, child) in nodes {
+        descendants(path, child, out);
+    }
+}
+
+fn compare(left: &Value, op: &str, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ if op == "==" => return left == right,
+        _ if op == "!=" => return left != right,
+        _ => None,
+    };
+    let Some(ordering) = ordering else { return false };
+    match op {
+        "==" => ordering.is_eq(),
+        "!=" => ordering.is_ne(),
+        "<" => ordering.is_lt(),
+        "<=" => ordering.is_le(),
+        ">" => ordering.is_gt(),
+        _ => ordering.is_ge(),
+    }
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        let mut current = value;
+        for key in &self.path {
+            match current.get(key.as_str()) {
+                Some(next) => current = next,
+                None => return false,
+            }
+        }
+        match &self.comparison {
+            Some((op, literal)) => compare(current, op, literal),
+            None => true,
+        }
+    }
+}
+
+impl Selector {
+    fn select<'a>(&self, path: &str, value: &'a Value) -> Vec<(String, &'a Value)> {
+        match (self, value) {
+            (Selector::Name(name), Value::Object(map)) => {
+                map.get(name).map(|item| (child_path(path, name), item)).into_iter().collect()
+            },
+            (Selector::Wildcard, _) => children(path, value),
+            (Selector::Index(index), Value::Array(items)) => {
+                let i = if *index < 0 { items.len() as i64 + index } else { *index };
+                items.get(i as usize).filter(|_| i >= 0).map(|item| (format!("{}[{}]", path, i), item)).into_iter().collect()
+            },
+            (Selector::Slice(start, end), Value::Array(items)) => {
+                let len = items.len() as i64;
+                let clamp = |i: i64| if i < 0 { (len + i).max(0) } else { i.min(len) };
+                let (start, end) = (clamp(start.unwrap_or(0)), clamp(end.unwrap_or(len)));
+                (start..end.max(start)).map(|i| (format!("{}[{}]", path, i), &items[i as usize])).collect()
+            },
+            (Selector::Filter(filter), _) => {
+                children(path, value).into_iter().filter(|(_, item)| filter.matches(item)).collect()
+            },
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Paths (in the tree's notation) of the nodes matched by `query`.
+pub fn query(value: &Value, query: &str) -> Result<Vec<String>, String> {
+    let mut nodes = vec![("$".to_string(), value)];
+    for segment in parse(query)? {
+        let (selectors, descendant) = match &segment {
+            Segment::Child(selectors) => (selectors, false),
+            Segment::Descendant(selectors) => (selectors, true),
+        };
+        let mut candidates = Vec::new();
+        for (path, value) in nodes {
+            if descendant {
+                descendants(path, value, &mut candidates);
+            } else {
+                candidates.push((path, value));
+            }
+        }
+        nodes = candidates.iter()
+            .flat_map(|(path, value)| selectors.iter().flat_map(move |selector| selector.select(path, value)))
+            .collect();
+    }
+    Ok(nodes.into_iter().map(|(path, _)| path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORE: &str = r#"{
+        "store": {
+            "book": [
+                {"title": "A", "price": 8, "tags": ["x"]},
+                {"title": "B", "price": 12},
+                {"title": "a<=b", "price": 20, "tags": []}
+            ],
+            "odd key": {"price": 1}
+        }
+    }"#;
+
+    fn run(query_text: &str) -> Result<Vec<String>, String> {
+        query(&serde_json::from_str(STORE).unwrap(), query_text)
+    }
+
+    #[test]
+    fn selects_names_indexes_and_wildcards() {
+        assert_eq!(run("$").unwrap(), ["$"]);
+        assert_eq!(run("$.store.book[0].title").unwrap(), ["$.store.book[0].title"]);
+        assert_eq!(run("$['store'][\"odd key\"].price").unwrap(), ["$.store[\"odd key\"].price"]);
+        assert_eq!(run("$.store.book[-1]").unwrap(), ["$.store.book[2]"]);
+        assert_eq!(run("$.store.book[5]").unwrap(), Vec::<String>::new());
+        assert_eq!(run("$.store.*").unwrap(), ["$.store.book", "$.store[\"odd key\"]"]);
+    }
+
+    #[test]
+    fn selects_slices_and_unions() {
+        assert_eq!(run("$.store.book[1:]").unwrap(), ["$.store.book[1]", "$.store.book[2]"]);
+        assert_eq!(run("$.store.book[:-2]").unwrap(), ["$.store.book[0]"]);
+        assert_eq!(run("$.store.book[2:1]").unwrap(), Vec::<String>::new());
+        assert_eq!(run("$.store.book[0,2].title").unwrap(), ["$.store.book[0].title", "$.store.book[2].title"]);
+        assert_eq!(run("$.store['book','odd key']").unwrap(), ["$.store.book", "$.store[\"odd key\"]"]);
+    }
+
+    #[test]
+    fn selects_descendants() {
+        assert_eq!(run("$..price").unwrap(), [
+            "$.store.book[0].price",
+            "$.store.book[1].price",
+            "$.store.book[2].price",
+            "$.store[\"odd key\"].price",
+        ]);
+        assert_eq!(run("$.store..tags[0]").unwrap(), ["$.store.book[0].tags[0]"]);
+    }
+
+    #[test]
+    fn filters_by_comparison_and_existence() {
+        assert_eq!(run("$.store.book[?(@.price < 10)].title").unwrap(), ["$.store.book[0].title"]);
+        assert_eq!(run("$.store.book[?(@.price >= 12)]").unwrap(), ["$.store.book[1]", "$.store.book[2]"]);
+        assert_eq!(run("$.store.book[?(@.title != \"B\")]").unwrap(), ["$.store.book[0]", "$.store.book[2]"]);
+        assert_eq!(run("$.store.book[?(@.tags)]").unwrap(), ["$.store.book[0]", "$.store.book[2]"]);
+        // 字面量里的运算符不能当成比较
+        assert_eq!(run("$.store.book[?(@.title == 'a<=b')]").unwrap(), ["$.store.book[2]"]);
+        assert_eq!(run("$.store.book[?(@.title == 'a]b')]").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_invalid_queries() {
+        assert!(run("store").is_err());
+        assert!(run("$.store.book[0").is_err());
+        assert!(run("$.store.book[x]").is_err());
+        assert!(run("$.store.book[?(price > 1)]").is_err());
+        assert!(run("$.store.book[?(@.price > abc)]").is_err());
+    }
+}
@@ -1,38 +1,131 @@
-use slint::{self, ComponentHandle, Model, ModelRc, SharedString, Weak, VecModel};
+use slint::{self, ComponentHandle, ModelRc, SharedString, Weak, VecModel};
+use slint::winit_030::{winit::event::WindowEvent, EventResult, WinitWindowAccessor};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use std::rc::Rc;
+use std::io;
 use tokio::sync::mpsc;
+use chrono::{Days, Local, NaiveDate, NaiveDateTime, Utc};
+use sqlx::sqlite::SqlitePool;
+use crate::calendar::ics;
+use crate::calendar::recurrence::{self, Occurrence};
+use crate::config::Config;
+use crate::db;
+use crate::export::{self, Exporter};
+use crate::export::pdf::PdfOptions;
+use crate::import::{self, ImportReport};
+use crate::markdown::from_html;
+use crate::markdown::front_matter::{self, Property, PropertyValue};
+use crate::markdown::tags;
+use crate::markdown::outline;
+use crate::markdown::render::{render_blocks, Block, BlockKind, RenderContext};
+use crate::markdown::tokenizer::{tokenize, TokenLine};
 use crate::ui::markdown_editor::MarkdownEditor;
-use pulldown_cmark::{Parser, html};
+use crate::ui::preview;
+use crate::ui::source_editor::{self, LineDiff};
 use crate::ui::AppWindow;
 use crate::ui::Callbacks;
 use crate::ui::OpenFileData;
+use crate::ui::calendar::{self, CalendarMode, CalendarState};
+use crate::ui::json_formatter::{self, TreeState};
+use crate::ui::json_query;
+use crate::ui::timestamp_converter;
+use crate::ui::hash_tool::{self, Digests, Encoding};
+use crate::ui::clipboard_manager;
+use crate::ui::{AttachmentIssue, CalendarCallbacks, EventData, JsonCallbacks, JsonDiffRow, TimestampCallbacks, HashCallbacks, HashRow, ClipboardCallbacks, NoteProperty, OutlineItem, TagEntry, TreeEntry};
+use crate::vault::{self, attachments, daily, index};
+use crate::db::notes::NoteRecord;
+use crate::vault::templates::{self, Expanded};
+use crate::vault::links::LinkIndex;
 use std::path::{PathBuf, Path};
 use std::fs;
-use tokio::time::{sleep, Duration};
 
 pub struct MainWindow {
     window: Rc<AppWindow>,
-    markdown_editor: Arc<Mutex<MarkdownEditor>>,
     tx: mpsc::Sender<UIMessage>,
 }
 
 enum UIMessage {
-    UpdateFileTree(Vec<String>),
+    UpdateFileTree(Vec<TreeEntry>),
     UpdateOpenFiles(Vec<OpenFileData>),
-    UpdatePreview(String),
+    UpdatePreview(Vec<Block>, Vec<OutlineItem>),
+    UpdateEditorLines(LineDiff),
     CreateFile(String),
     OpenFile(String),
     CloseFile(String),
     SaveFile,
-    UpdateEditorContent(String),
     UpdateEditorContentFromUI(String),
+    ThemeChanged(bool),
+    AttachmentAdded(PathBuf),
+    CheckAttachments,
+    Export(String, bool),
+    UpdateProperty(usize, NoteProperty),
+    RemoveProperty(usize),
+    AddProperty,
+    RefreshTags,
+    FilterByTag(String),
+    OpenDailyNote(i32),
+    LoadTemplates,
+    CreateFromTemplate(String, String),
+    CreateNote(String, Expanded),
+    SetEditorCursor(usize),
+    OpenDailyNoteOn(NaiveDate),
+    CalendarRefresh,
+    CalendarNavigate(i32),
+    CalendarSetMode(String),
+    CalendarSelectDay(String),
+    CalendarSaveEvent(EventData),
+    CalendarDeleteEvent(i32),
+    CalendarDeleteOccurrence(i32, NaiveDate),
+    CheckReminders,
+    JsonValidate(String),
+    /// Text, indent (0 for tabs), sort keys.
+    JsonFormat(String, i32, bool),
+    JsonMinify(String, bool),
+    JsonToggleNode(String),
+    JsonExpandAll(bool),
+    JsonQuery(String),
+    JsonDiff(String, String),
+    /// Input, input zone, output zone.
+    ConvertTimestamp(String, String, String),
+    TimestampNow(String),
+    /// Text or file path, HMAC key (empty for none).
+    HashText(String, String),
+    HashFile(String, String),
+    HashDone(Result<Digests, String>, String),
+    HashCompare(String),
+    Encode(String, i32),
+    Decode(String, i32),
+    /// Copied text and the note it came from.
+    ClipboardRecord(String, Option<String>),
+    ClipboardSearch(String),
+    ClipboardPin(i32, bool),
+    ClipboardDelete(i32),
+    ClipboardClear,
+    CalendarImport(PathBuf),
+    CalendarExport(PathBuf),
+    /// Source name and the path of the export.
+    ImportNotes(String, String),
+    ImportDone(Result<ImportReport, String>),
 }
 
 impl MainWindow {
-    pub fn new() -> Result<Self, slint::PlatformError> {
+    pub async fn new() -> Result<Self, slint::PlatformError> {
         let window = Rc::new(AppWindow::new()?);
         let markdown_editor = Arc::new(Mutex::new(MarkdownEditor::new()));
+        let root = markdown_editor.lock().unwrap().get_root_dir().to_path_buf();
+        let config = Arc::new(Config::load(&root));
+        // 整个应用共用一个连接池，没有数据库时相关功能不可用
+        let database = config.database_path(&root);
+        // 旧版本只会在默认位置用到工作目录下的数据库
+        let adopt_legacy = config.database_path == Config::default().database_path;
+        let pool = match db::init_database(&database, adopt_legacy).await {
+            Ok(pool) => Some(pool),
+            Err(e) => {
+                eprintln!("Failed to open database {}: {}", database.display(), e);
+                None
+            }
+        };
         let (tx, rx) = mpsc::channel(100);
 
         // 设置初始的 editor_content
@@ -99,249 +192,675 @@ impl MainWindow {
             });
         });
 
-        let window_weak = window.as_weak();
-        let md_editor = markdown_editor.clone();
         let tx_clone = tx.clone();
-        tokio::spawn(async move {
-            Self::run_event_loop(window_weak, md_editor, rx, tx_clone).await;
+        window.global::<Callbacks>().on_theme_changed(move |dark: bool| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::ThemeChanged(dark)).await.unwrap();
+            });
         });
 
-        println!("MainWindow created successfully");
-        Ok(Self { window, markdown_editor, tx })
-    }
+        let md_editor = markdown_editor.clone();
+        window.global::<Callbacks>().on_open_attachment(move |path: SharedString| {
+            let path = md_editor.lock().unwrap().get_root_dir().join(path.as_str());
+            if let Err(e) = attachments::open_externally(&path) {
+                eprintln!("Failed to open attachment {:?}: {}", path, e);
+            }
+        });
 
-    pub async fn run(&self) -> Result<(), slint::PlatformError> {
-        // println!("Updating file tree");
-        self.tx.send(UIMessage::UpdateFileTree(Vec::new())).await.unwrap();
-        // println!("Updating open files");
-        self.tx.send(UIMessage::UpdateOpenFiles(Vec::new())).await.unwrap();
-        // println!("Attempting to show window");
-        
-        self.window.show()?;
-        // println!("Window shown, starting event loop");
-        slint::run_event_loop()?;
-        
-        // println!("Event loop finished");
-        Ok(())
-    }
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_check_attachments(move || {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::CheckAttachments).await.unwrap();
+            });
+        });
 
-    async fn run_event_loop(
-        window: Weak<AppWindow>,
-        markdown_editor: Arc<Mutex<MarkdownEditor>>,
-        mut rx: mpsc::Receiver<UIMessage>,
-        tx: mpsc::Sender<UIMessage>,
-    ) {
-        // Load initial directory tree
-        if let Ok(files) = Self::load_directory_tree(Path::new("nodian")) {
-            tx.send(UIMessage::UpdateFileTree(files)).await.unwrap();
-        }
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_export(move |format: SharedString, folder: bool| {
+            let tx = tx_clone.clone();
+            let format = format.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::Export(format, folder)).await.unwrap();
+            });
+        });
 
-        // Load initial open files
-        let open_files_data = {
-            let editor = markdown_editor.lock().unwrap();
-            let open_files = editor.get_open_files();
-            let current_file = editor.get_current_file();
-            open_files.iter().map(|f| OpenFileData {
-                path: f.path.to_string_lossy().to_string().into(),
-                is_dir: f.is_dir,
-                is_modified: f.is_modified,
-                is_active: current_file.as_ref().map(|cf| cf == &f.path).unwrap_or(false),
-            }).collect::<Vec<OpenFileData>>()
-        };
-        tx.send(UIMessage::UpdateOpenFiles(open_files_data)).await.unwrap();
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_import_notes(move |source: SharedString, path: SharedString| {
+            let tx = tx_clone.clone();
+            let source = source.to_string();
+            let path = path.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::ImportNotes(source, path)).await.unwrap();
+            });
+        });
 
-        while let Some(msg) = rx.recv().await {
-            let window = window.clone();
-            match msg {
-                UIMessage::UpdateFileTree(files) => {
-                    let files_clone = files.clone();
-                    window.upgrade_in_event_loop(move |handle| {
-                        let file_model = Rc::new(VecModel::from(
-                            files_clone.into_iter().map(SharedString::from).collect::<Vec<SharedString>>()
-                        ));
-                        handle.set_file_tree(ModelRc::new(file_model));
-                    }).ok();
-                },
-                UIMessage::UpdateOpenFiles(open_files) => {
-                    let open_files_clone = open_files.clone();
-                    window.upgrade_in_event_loop(move |handle| {
-                        let open_files_model = Rc::new(VecModel::from(open_files_clone));
-                        handle.set_open_files(ModelRc::new(open_files_model));
-                    }).ok();
-                },
-                UIMessage::UpdatePreview(html) => {
-                    let html_clone = html.clone();
-                    window.upgrade_in_event_loop(move |handle| {
-                        handle.set_preview_content(html_clone.into());
-                    }).ok();
-                },
-                UIMessage::CreateFile(name) => {
-                    if let Err(e) = markdown_editor.lock().unwrap().create_file(&name) {
-                        eprintln!("Failed to create file: {}", e);
-                    }
-                    // Update the file tree after creating a new file
-                    if let Ok(files) = Self::load_directory_tree(Path::new("nodian")) {
-                        tx.send(UIMessage::UpdateFileTree(files)).await.unwrap();
-                    }
-                },
-                UIMessage::OpenFile(path) => {
-                    // println!("Attempting to open file: {}", path);
-                    let full_path = if Path::new(&path).is_absolute() {
-                        PathBuf::from(&path)
-                    } else {
-                        Path::new("nodian").join(&path)
-                    };
-                    // println!("Full path: {:?}", full_path);
-                    
-                    let content = {
-                        let mut editor = markdown_editor.lock().unwrap();
-                        match editor.open_file(&full_path) {
-                            Ok(()) => {
-                                // println!("File opened successfully");
-                                editor.get_content().to_string()
-                            },
-                            Err(e) => {
-                                eprintln!("Failed to open file: {}", e);
-                                String::new()
-                            }
-                        }
-                    };
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_update_property(move |index: i32, property: NoteProperty| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::UpdateProperty(index as usize, property)).await.unwrap();
+            });
+        });
 
-                    // Update UI outside of the lock
-                    let content_clone = content.clone();
-                    window.upgrade_in_event_loop(move |handle| {
-                        handle.set_editor_content(content_clone.into());
-                    }).ok();
-                    
-                    // Update preview
-                    let parser = Parser::new(&content);
-                    let mut html_output = String::new();
-                    html::push_html(&mut html_output, parser);
-                    tx.send(UIMessage::UpdatePreview(html_output)).await.unwrap();
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_remove_property(move |index: i32| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::RemoveProperty(index as usize)).await.unwrap();
+            });
+        });
 
-                    // Send a message to update open files
-                    let open_files_data = {
-                        let editor = markdown_editor.lock().unwrap();
-                        let open_files = editor.get_open_files();
-                        let current_file = editor.get_current_file();
-                        open_files.iter().map(|f| OpenFileData {
-                            path: f.path.strip_prefix(&editor.get_root_dir())
-                                .unwrap_or(&f.path)
-                                .to_string_lossy()
-                                .to_string()
-                                .into(),
-                            is_dir: f.is_dir,
-                            is_modified: f.is_modified,
-                            is_active: current_file.as_ref().map(|cf| cf == f.path.as_path()).unwrap_or(false),
-                        }).collect::<Vec<OpenFileData>>()
-                    };
-                    tx.send(UIMessage::UpdateOpenFiles(open_files_data)).await.unwrap();
-                },
-                UIMessage::CloseFile(path) => {
-                    // println!("Attempting to close file: {}", path);
-                    let result = markdown_editor.lock().unwrap().close_file(&path);
-                    match result {
-                        Ok(()) => {
-                            // println!("File closed successfully: {}", path);
-                            
-                            // Send a message to update open files
-                            let open_files_data: Vec<OpenFileData> = {
-                                let editor = markdown_editor.lock().unwrap();
-                                let open_files = editor.get_open_files();
-                                let current_file = editor.get_current_file();
-                                open_files.iter().map(|f| OpenFileData {
-                                    path: f.path.strip_prefix(&editor.get_root_dir())
-                                        .unwrap_or(&f.path)
-                                        .to_string_lossy()
-                                        .to_string()
-                                        .into(),
-                                    is_dir: f.is_dir,
-                                    is_modified: f.is_modified,
-                                    is_active: current_file.as_ref().map(|cf| cf == f.path.as_path()).unwrap_or(false),
-                                }).collect()
-                            };
-                            tx.send(UIMessage::UpdateOpenFiles(open_files_data)).await.unwrap();
-                            
-                            // Update the file tree
-                            if let Ok(files) = Self::load_directory_tree(Path::new("nodian")) {
-                                tx.send(UIMessage::UpdateFileTree(files)).await.unwrap();
-                            }
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_add_property(move || {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::AddProperty).await.unwrap();
+            });
+        });
 
-                            // Clear editor content if the closed file was the current file
-                            if markdown_editor.lock().unwrap().get_current_file().is_none() {
-                                window.upgrade_in_event_loop(|handle| {
-                                    handle.set_editor_content("".into());
-                                    handle.set_preview_content("".into());
-                                }).ok();
-                            }
-                        },
-                        Err(e) => {
-                            eprintln!("Failed to close file: {}", e);
-                        }
-                    }
-                },
-                UIMessage::SaveFile => {
-                    // println!("Save file command received");
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_filter_by_tag(move |tag: SharedString| {
+            let tx = tx_clone.clone();
+            let tag = tag.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::FilterByTag(tag)).await.unwrap();
+            });
+        });
 
-                    let content = {
-                        let editor = markdown_editor.lock().unwrap();
-                        editor.get_content().to_string()
-                    };
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_open_daily_note(move |step: i32| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::OpenDailyNote(step)).await.unwrap();
+            });
+        });
 
-                    let result = {
-                        let mut editor = markdown_editor.lock().unwrap();
-                        editor.save_file()
-                    };
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_load_templates(move || {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::LoadTemplates).await.unwrap();
+            });
+        });
 
-                    match result {
-                        Ok(()) => {
-                            println!("File saved successfully");
-                            // 更新打开文件的状态
-                            let open_files_data: Vec<OpenFileData> = {
-                                let editor = markdown_editor.lock().unwrap();
-                                let open_files = editor.get_open_files();
-                                let current_file = editor.get_current_file();
-                                open_files.iter().map(|f| OpenFileData {
-                                    path: f.path.strip_prefix(&editor.get_root_dir())
-                                        .unwrap_or(&f.path)
-                                        .to_string_lossy()
-                                        .to_string()
-                                        .into(),
-                                    is_dir: f.is_dir,
-                                    is_modified: f.is_modified,
-                                    is_active: current_file.as_ref().map(|cf| cf == f.path.as_path()).unwrap_or(false),
-                                }).collect()
-                            };
-                            tx.send(UIMessage::UpdateOpenFiles(open_files_data)).await.unwrap();
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_create_from_template(move |name: SharedString, template: SharedString| {
+            let tx = tx_clone.clone();
+            let (name, template) = (name.to_string(), template.to_string());
+            tokio::spawn(async move {
+                tx.send(UIMessage::CreateFromTemplate(name, template)).await.unwrap();
+            });
+        });
 
-                            // 更新 UI 以反映文件已保存
-                            window.upgrade_in_event_loop(|handle| {
-                                handle.window().request_redraw();
-                            }).ok();
-                        }
-                        Err(e) => {
-                            eprintln!("Failed to save file: {}", e);
-                            // 可以在这里添加错误提示的 UI 更新
-                        }
-                    }
-                },
-                UIMessage::UpdateEditorContent(content) => {
-                    // println!("Updating editor content, length: {}", content.len());
-                    {
-                        let mut editor = markdown_editor.lock().unwrap();
-                        editor.update_content(content.clone());
-                    }
-                    window.upgrade_in_event_loop(move |handle| {
-                        handle.set_editor_content(content.into());
-                    }).ok();
+        let calendar_callbacks = window.global::<CalendarCallbacks>();
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_refresh(move || {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::CalendarRefresh).await.unwrap();
+            });
+        });
 
-                    // 更新打开文件的状态
-                    let open_files_data: Vec<OpenFileData> = {
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_navigate(move |step: i32| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::CalendarNavigate(step)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_set_mode(move |mode: SharedString| {
+            let tx = tx_clone.clone();
+            let mode = mode.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::CalendarSetMode(mode)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_select_day(move |date: SharedString| {
+            let tx = tx_clone.clone();
+            let date = date.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::CalendarSelectDay(date)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_save_event(move |event: EventData| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::CalendarSaveEvent(event)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_delete_event(move |id: i32| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::CalendarDeleteEvent(id)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_delete_occurrence(move |id: i32, date: SharedString| {
+            let tx = tx_clone.clone();
+            let Some(date) = calendar::parse_date(&date) else { return };
+            tokio::spawn(async move {
+                tx.send(UIMessage::CalendarDeleteOccurrence(id, date)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_open_daily_note(move |date: SharedString| {
+            let tx = tx_clone.clone();
+            let Some(date) = calendar::parse_date(&date) else { return };
+            tokio::spawn(async move {
+                tx.send(UIMessage::OpenDailyNoteOn(date)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_import_ics(move |path: SharedString| {
+            let tx = tx_clone.clone();
+            let path = PathBuf::from(path.trim());
+            tokio::spawn(async move {
+                tx.send(UIMessage::CalendarImport(path)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        calendar_callbacks.on_export_ics(move |path: SharedString| {
+            let tx = tx_clone.clone();
+            let path = PathBuf::from(path.trim());
+            tokio::spawn(async move {
+                tx.send(UIMessage::CalendarExport(path)).await.unwrap();
+            });
+        });
+
+        let json_callbacks = window.global::<JsonCallbacks>();
+        let tx_clone = tx.clone();
+        json_callbacks.on_validate(move |text: SharedString| {
+            let tx = tx_clone.clone();
+            let text = text.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::JsonValidate(text)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        json_callbacks.on_format(move |text: SharedString, indent: i32, sort: bool| {
+            let tx = tx_clone.clone();
+            let text = text.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::JsonFormat(text, indent, sort)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        json_callbacks.on_minify(move |text: SharedString, sort: bool| {
+            let tx = tx_clone.clone();
+            let text = text.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::JsonMinify(text, sort)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        json_callbacks.on_toggle_node(move |path: SharedString| {
+            let tx = tx_clone.clone();
+            let path = path.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::JsonToggleNode(path)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        json_callbacks.on_expand_all(move |expand: bool| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::JsonExpandAll(expand)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        json_callbacks.on_query(move |query: SharedString| {
+            let tx = tx_clone.clone();
+            let query = query.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::JsonQuery(query)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        json_callbacks.on_diff(move |left: SharedString, right: SharedString| {
+            let tx = tx_clone.clone();
+            let (left, right) = (left.to_string(), right.to_string());
+            tokio::spawn(async move {
+                tx.send(UIMessage::JsonDiff(left, right)).await.unwrap();
+            });
+        });
+
+        let timestamp_callbacks = window.global::<TimestampCallbacks>();
+        let tx_clone = tx.clone();
+        timestamp_callbacks.on_convert(move |input: SharedString, from: SharedString, to: SharedString| {
+            let tx = tx_clone.clone();
+            let (input, from, to) = (input.to_string(), from.to_string(), to.to_string());
+            tokio::spawn(async move {
+                tx.send(UIMessage::ConvertTimestamp(input, from, to)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        timestamp_callbacks.on_now(move |zone: SharedString| {
+            let tx = tx_clone.clone();
+            let zone = zone.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::TimestampNow(zone)).await.unwrap();
+            });
+        });
+
+        // 替换编辑器中的选区（没有选区时插入到光标处）并切回编辑器
+        let window_weak = window.as_weak();
+        let md_editor = markdown_editor.clone();
+        let tx_clone = tx.clone();
+        timestamp_callbacks.on_insert(move |text: SharedString| {
+            let Some(window) = window_weak.upgrade() else { return };
+            if md_editor.lock().unwrap().get_current_file().is_none() {
+                window.set_timestamp_error("没有打开的笔记".into());
+                return;
+            }
+            let content = source_editor::replace_selection(&window, window.get_editor_selection_anchor(), window.get_editor_selection_cursor(), &text);
+            window.set_active_tool("editor".into());
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::UpdateEditorContentFromUI(content)).await.unwrap();
+            });
+        });
+
+        let hash_callbacks = window.global::<HashCallbacks>();
+        let tx_clone = tx.clone();
+        hash_callbacks.on_hash_text(move |text: SharedString, key: SharedString| {
+            let tx = tx_clone.clone();
+            let (text, key) = (text.to_string(), key.to_string());
+            tokio::spawn(async move {
+                tx.send(UIMessage::HashText(text, key)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        hash_callbacks.on_hash_file(move |path: SharedString, key: SharedString| {
+            let tx = tx_clone.clone();
+            let (path, key) = (path.to_string(), key.to_string());
+            tokio::spawn(async move {
+                tx.send(UIMessage::HashFile(path, key)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        hash_callbacks.on_compare(move |expected: SharedString| {
+            let tx = tx_clone.clone();
+            let expected = expected.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::HashCompare(expected)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        hash_callbacks.on_encode(move |text: SharedString, encoding: i32| {
+            let tx = tx_clone.clone();
+            let text = text.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::Encode(text, encoding)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        hash_callbacks.on_decode(move |text: SharedString, encoding: i32| {
+            let tx = tx_clone.clone();
+            let text = text.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::Decode(text, encoding)).await.unwrap();
+            });
+        });
+
+        hash_callbacks.on_copy(move |text: SharedString| {
+            if let Err(e) = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text.as_str())) {
+                eprintln!("Failed to copy to clipboard: {}", e);
+            }
+        });
+
+        // 编辑器中复制或剪切时记录选中的文本
+        let clipboard_callbacks = window.global::<ClipboardCallbacks>();
+        let window_weak = window.as_weak();
+        let md_editor = markdown_editor.clone();
+        let tx_clone = tx.clone();
+        clipboard_callbacks.on_copied(move |anchor: i32, cursor: i32| {
+            let Some(window) = window_weak.upgrade() else { return };
+            let Some(text) = clipboard_manager::selection(&window.get_editor_content(), anchor, cursor) else { return };
+            if !clipboard_manager::should_record(&text) {
+                return;
+            }
+            let source = {
+                let editor = md_editor.lock().unwrap();
+                editor.get_current_file().map(|file| {
+                    file.strip_prefix(editor.get_root_dir()).unwrap_or(&file).to_string_lossy().into_owned()
+                })
+            };
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::ClipboardRecord(text, source)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        clipboard_callbacks.on_search(move |query: SharedString| {
+            let tx = tx_clone.clone();
+            let query = query.to_string();
+            tokio::spawn(async move {
+                tx.send(UIMessage::ClipboardSearch(query)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        clipboard_callbacks.on_toggle_pin(move |id: i32, pinned: bool| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::ClipboardPin(id, pinned)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        clipboard_callbacks.on_delete(move |id: i32| {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::ClipboardDelete(id)).await.unwrap();
+            });
+        });
+
+        let tx_clone = tx.clone();
+        clipboard_callbacks.on_clear(move || {
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::ClipboardClear).await.unwrap();
+            });
+        });
+
+        // 从历史中选择的内容替换选区或插入到光标处
+        let window_weak = window.as_weak();
+        let md_editor = markdown_editor.clone();
+        let tx_clone = tx.clone();
+        clipboard_callbacks.on_paste(move |text: SharedString| {
+            let Some(window) = window_weak.upgrade() else { return };
+            if md_editor.lock().unwrap().get_current_file().is_none() {
+                return;
+            }
+            let content = source_editor::replace_selection(&window, window.get_editor_selection_anchor(), window.get_editor_selection_cursor(), &text);
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::UpdateEditorContentFromUI(content)).await.unwrap();
+            });
+        });
+
+        // 粘贴剪贴板中的图片，或把网页、文档中复制的 HTML 转为 markdown
+        let window_weak = window.as_weak();
+        let md_editor = markdown_editor.clone();
+        let config_clone = config.clone();
+        let tx_clone = tx.clone();
+        window.global::<Callbacks>().on_paste_into_editor(move |plain: bool, anchor: i32, cursor: i32| {
+            let Some(window) = window_weak.upgrade() else { return false };
+            let Ok(mut clipboard) = arboard::Clipboard::new() else { return false };
+            let text = if plain {
+                clipboard.get_text().ok()
+            } else {
+                clipboard.get().html().ok()
+                    .map(|html| from_html::to_markdown(&html).trim_end().to_string())
+                    .filter(|markdown| !markdown.is_empty())
+            };
+            // 网页和文档复制时常常同时带着 HTML 和截图，只有图片时才当作附件
+            if text.is_none() && !plain {
+                if let Ok(image) = clipboard.get_image() {
+                    return Self::attach_to_current_note(&window, &md_editor, &config_clone, &tx_clone, |dir| {
+                        attachments::save_png(dir, image.width as u32, image.height as u32, &image.bytes)
+                    });
+                }
+            }
+            // 没有 HTML 时交给 TextInput 照常粘贴
+            let Some(text) = text else { return false };
+            let content = source_editor::replace_selection(&window, anchor, cursor, &text);
+            let tx = tx_clone.clone();
+            tokio::spawn(async move {
+                tx.send(UIMessage::UpdateEditorContentFromUI(content)).await.unwrap();
+            });
+            true
+        });
+
+        // 拖放文件到窗口
+        let window_weak = window.as_weak();
+        let md_editor = markdown_editor.clone();
+        let config_clone = config.clone();
+        let tx_clone = tx.clone();
+        window.window().on_winit_window_event(move |_, event| {
+            if let WindowEvent::DroppedFile(path) = event {
+                if let Some(window) = window_weak.upgrade() {
+                    // 导入向导打开时拖入的是要导入的文件
+                    if window.get_show_import() {
+                        window.set_import_path(path.to_string_lossy().to_string().into());
+                        return EventResult::PreventDefault;
+                    }
+                    // 日历页面中拖入的 .ics 文件直接导入
+                    let is_ics = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ics"));
+                    if is_ics && window.get_active_tool() == "calendar" {
+                        let tx = tx_clone.clone();
+                        let path = path.clone();
+                        tokio::spawn(async move {
+                            tx.send(UIMessage::CalendarImport(path)).await.unwrap();
+                        });
+                        return EventResult::PreventDefault;
+                    }
+                    Self::attach_to_current_note(&window, &md_editor, &config_clone, &tx_clone, |dir| {
+                        attachments::import_file(dir, path)
+                    });
+                }
+                return EventResult::PreventDefault;
+            }
+            EventResult::Propagate
+        });
+
+        let window_weak = window.as_weak();
+        let tx_clone = tx.clone();
+        tokio::spawn(async move {
+            Self::run_event_loop(window_weak, markdown_editor, config, pool, rx, tx_clone).await;
+        });
+
+        println!("MainWindow created successfully");
+        Ok(Self { window, tx })
+    }
+
+    pub async fn run(&self) -> Result<(), slint::PlatformError> {
+        self.tx.send(UIMessage::ThemeChanged(self.window.get_dark_mode())).await.unwrap();
+        // println!("Updating file tree");
+        self.tx.send(UIMessage::UpdateFileTree(Vec::new())).await.unwrap();
+        // println!("Updating open files");
+        self.tx.send(UIMessage::UpdateOpenFiles(Vec::new())).await.unwrap();
+        // println!("Attempting to show window");
+        
+        self.window.show()?;
+        // println!("Window shown, starting event loop");
+        slint::run_event_loop()?;
+        
+        // println!("Event loop finished");
+        Ok(())
+    }
+
+    async fn run_event_loop(
+        window: Weak<AppWindow>,
+        markdown_editor: Arc<Mutex<MarkdownEditor>>,
+        config: Arc<Config>,
+        pool: Option<SqlitePool>,
+        mut rx: mpsc::Receiver<UIMessage>,
+        tx: mpsc::Sender<UIMessage>,
+    ) {
+        let mut dark_mode = false;
+        let mut editor_lines: Vec<TokenLine> = Vec::new();
+        let root = markdown_editor.lock().unwrap().get_root_dir().to_path_buf();
+        if let Err(e) = fs::create_dir_all(&root) {
+            eprintln!("Failed to create vault directory: {}", e);
+        }
+        let mut link_index = LinkIndex::build(&root).unwrap_or_else(|e| {
+            eprintln!("Failed to index vault: {}", e);
+            LinkIndex::new(&root)
+        });
+
+        let mut properties: Vec<Property> = Vec::new();
+        let mut calendar = CalendarState::new(daily::today());
+        // 按标签过滤文件树时，带有该标签的笔记
+        let mut tag_filter = String::new();
+        let mut tag_notes: Option<HashSet<PathBuf>> = None;
+        if let Some(pool) = &pool {
+            let pool_clone = pool.clone();
+            let root_clone = root.clone();
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                Self::index_notes(&pool_clone, &root_clone, true).await;
+                tx_clone.send(UIMessage::RefreshTags).await.unwrap();
+            });
+        }
+
+        // JSON 工具最后一次解析成功的值、折叠和匹配的节点、当前查询
+        let mut json_value: Option<serde_json::Value> = None;
+        let mut json_tree = TreeState::default();
+        let mut json_query = String::new();
+        // 哈希工具最近一次的结果和比较框的内容
+        let mut digests: Digests = Vec::new();
+        let mut digest_status = String::new();
+        let mut expected_digest = String::new();
+        // 剪贴板历史弹窗当前的搜索
+        let mut clipboard_query = String::new();
+        if let Some(pool) = &pool {
+            Self::prune_clipboard(pool, &config).await;
+        }
+        if config.clipboard_poll {
+            let tx_clone = tx.clone();
+            clipboard_manager::watch(move |text| tx_clone.blocking_send(UIMessage::ClipboardRecord(text, None)).is_ok());
+        }
+        // 运行期间定时检查事件提醒，已提醒过的不再重复
+        let mut notified: HashSet<(i64, NaiveDateTime)> = HashSet::new();
+        let tx_clone = tx.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+            loop {
+                interval.tick().await;
+                if tx_clone.send(UIMessage::CheckReminders).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Load initial directory tree
+        Self::refresh_file_tree(&tx, &root, &link_index, tag_notes.as_ref(), None).await;
+
+        // Load initial open files
+        let open_files_data = {
+            let editor = markdown_editor.lock().unwrap();
+            let open_files = editor.get_open_files();
+            let current_file = editor.get_current_file();
+            open_files.iter().map(|f| OpenFileData {
+                path: f.path.to_string_lossy().to_string().into(),
+                is_dir: f.is_dir,
+                is_modified: f.is_modified,
+                is_active: current_file.as_ref().map(|cf| cf == &f.path).unwrap_or(false),
+            }).collect::<Vec<OpenFileData>>()
+        };
+        tx.send(UIMessage::UpdateOpenFiles(open_files_data)).await.unwrap();
+
+        while let Some(msg) = rx.recv().await {
+            let window = window.clone();
+            match msg {
+                UIMessage::UpdateFileTree(files) => {
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_file_tree(ModelRc::new(Rc::new(VecModel::from(files))));
+                    }).ok();
+                },
+                UIMessage::UpdateOpenFiles(open_files) => {
+                    let open_files_clone = open_files.clone();
+                    window.upgrade_in_event_loop(move |handle| {
+                        let open_files_model = Rc::new(VecModel::from(open_files_clone));
+                        handle.set_open_files(ModelRc::new(open_files_model));
+                    }).ok();
+                },
+                UIMessage::UpdatePreview(blocks, outline) => {
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_preview_blocks(preview::to_model(blocks));
+                        handle.set_outline(ModelRc::new(Rc::new(VecModel::from(outline))));
+                    }).ok();
+                },
+                UIMessage::UpdateEditorLines(diff) => {
+                    window.upgrade_in_event_loop(move |handle| {
+                        source_editor::apply_diff(&handle, diff, dark_mode);
+                    }).ok();
+                },
+                UIMessage::CreateFile(name) => {
+                    if let Err(e) = markdown_editor.lock().unwrap().create_file(&name, "") {
+                        eprintln!("Failed to create file: {}", e);
+                    }
+                    link_index.update_note(&root.join(&name), "");
+                    if let Some(pool) = &pool {
+                        Self::sync_note_record(&window, pool, &root, &root.join(&name), "").await;
+                    }
+                    // Update the file tree after creating a new file
+                    Self::refresh_file_tree(&tx, &root, &link_index, tag_notes.as_ref(), pool.as_ref()).await;
+                },
+                UIMessage::OpenFile(path) => {
+                    // println!("Attempting to open file: {}", path);
+                    let full_path = if Path::new(&path).is_absolute() {
+                        PathBuf::from(&path)
+                    } else {
+                        Path::new("nodian").join(&path)
+                    };
+                    // println!("Full path: {:?}", full_path);
+                    
+                    let content = {
+                        let mut editor = markdown_editor.lock().unwrap();
+                        match editor.open_file(&full_path) {
+                            Ok(()) => {
+                                // println!("File opened successfully");
+                                editor.get_content().to_string()
+                            },
+                            Err(e) => {
+                                eprintln!("Failed to open file: {}", e);
+                                String::new()
+                            }
+                        }
+                    };
+
+                    // Update UI outside of the lock
+                    let content_clone = content.clone();
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_editor_content(content_clone.into());
+                    }).ok();
+                    
+                    // Update preview
+                    tx.send(Self::render_preview(&markdown_editor, &content, dark_mode)).await.unwrap();
+                    Self::update_editor_lines(&tx, &mut editor_lines, &content).await;
+                    Self::sync_properties(&window, &content, &mut properties, false);
+                    if let Some(pool) = &pool {
+                        let note = db::notes::get(pool, &vault::relative_name(&root, &full_path)).await.unwrap_or_else(|e| {
+                            eprintln!("Failed to load note record: {}", e);
+                            None
+                        });
+                        Self::show_note_info(&window, note.as_ref());
+                    }
+
+                    // Send a message to update open files
+                    let open_files_data = {
                         let editor = markdown_editor.lock().unwrap();
                         let open_files = editor.get_open_files();
                         let current_file = editor.get_current_file();
                         open_files.iter().map(|f| OpenFileData {
-                            path: f.path.strip_prefix(&editor.get_root_dir())
+                            path: f.path.strip_prefix(editor.get_root_dir())
                                 .unwrap_or(&f.path)
                                 .to_string_lossy()
                                 .to_string()
@@ -349,10 +868,109 @@ impl MainWindow {
                             is_dir: f.is_dir,
                             is_modified: f.is_modified,
                             is_active: current_file.as_ref().map(|cf| cf == f.path.as_path()).unwrap_or(false),
-                        }).collect()
+                        }).collect::<Vec<OpenFileData>>()
                     };
                     tx.send(UIMessage::UpdateOpenFiles(open_files_data)).await.unwrap();
                 },
+                UIMessage::CloseFile(path) => {
+                    // println!("Attempting to close file: {}", path);
+                    let result = markdown_editor.lock().unwrap().close_file(&path);
+                    match result {
+                        Ok(()) => {
+                            // println!("File closed successfully: {}", path);
+                            
+                            // Send a message to update open files
+                            let open_files_data: Vec<OpenFileData> = {
+                                let editor = markdown_editor.lock().unwrap();
+                                let open_files = editor.get_open_files();
+                                let current_file = editor.get_current_file();
+                                open_files.iter().map(|f| OpenFileData {
+                                    path: f.path.strip_prefix(editor.get_root_dir())
+                                        .unwrap_or(&f.path)
+                                        .to_string_lossy()
+                                        .to_string()
+                                        .into(),
+                                    is_dir: f.is_dir,
+                                    is_modified: f.is_modified,
+                                    is_active: current_file.as_ref().map(|cf| cf == f.path.as_path()).unwrap_or(false),
+                                }).collect()
+                            };
+                            tx.send(UIMessage::UpdateOpenFiles(open_files_data)).await.unwrap();
+                            
+                            // Update the file tree
+                            Self::refresh_file_tree(&tx, &root, &link_index, tag_notes.as_ref(), pool.as_ref()).await;
+
+                            // Clear editor content if the closed file was the current file
+                            if markdown_editor.lock().unwrap().get_current_file().is_none() {
+                                window.upgrade_in_event_loop(|handle| {
+                                    handle.set_editor_content("".into());
+                                    handle.set_preview_blocks(ModelRc::default());
+                                    handle.set_outline(ModelRc::default());
+                                }).ok();
+                                Self::update_editor_lines(&tx, &mut editor_lines, "").await;
+                                Self::sync_properties(&window, "", &mut properties, false);
+                            }
+                        },
+                        Err(e) => {
+                            eprintln!("Failed to close file: {}", e);
+                        }
+                    }
+                },
+                UIMessage::SaveFile => {
+                    // println!("Save file command received");
+
+                    let result = {
+                        let mut editor = markdown_editor.lock().unwrap();
+                        editor.save_file()
+                    };
+
+                    match result {
+                        Ok(()) => {
+                            println!("File saved successfully");
+                            // 更新链接索引，附件的引用状态可能已经改变
+                            let (current_file, content) = {
+                                let editor = markdown_editor.lock().unwrap();
+                                (editor.get_current_file(), editor.get_content())
+                            };
+                            if let Some(path) = current_file {
+                                link_index.update_note(&path, &content);
+                                Self::refresh_file_tree(&tx, &root, &link_index, tag_notes.as_ref(), pool.as_ref()).await;
+                                if let Some(pool) = &pool {
+                                    Self::index_note(pool, &vault::relative_name(&root, &path), &content).await;
+                                    Self::sync_note_record(&window, pool, &root, &path, &content).await;
+                                    tx.send(UIMessage::RefreshTags).await.unwrap();
+                                }
+                            }
+
+                            // 更新打开文件的状态
+                            let open_files_data: Vec<OpenFileData> = {
+                                let editor = markdown_editor.lock().unwrap();
+                                let open_files = editor.get_open_files();
+                                let current_file = editor.get_current_file();
+                                open_files.iter().map(|f| OpenFileData {
+                                    path: f.path.strip_prefix(editor.get_root_dir())
+                                        .unwrap_or(&f.path)
+                                        .to_string_lossy()
+                                        .to_string()
+                                        .into(),
+                                    is_dir: f.is_dir,
+                                    is_modified: f.is_modified,
+                                    is_active: current_file.as_ref().map(|cf| cf == f.path.as_path()).unwrap_or(false),
+                                }).collect()
+                            };
+                            tx.send(UIMessage::UpdateOpenFiles(open_files_data)).await.unwrap();
+
+                            // 更新 UI 以反映文件已保存
+                            window.upgrade_in_event_loop(|handle| {
+                                handle.window().request_redraw();
+                            }).ok();
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to save file: {}", e);
+                            // 可以在这里添加错误提示的 UI 更新
+                        }
+                    }
+                },
                 UIMessage::UpdateEditorContentFromUI(content) => {
                     {
                         let mut editor = markdown_editor.lock().unwrap();
@@ -360,16 +978,15 @@ impl MainWindow {
                     }
 
                     // 更新预览
-                    let parser = Parser::new(&content);
-                    let mut html_output = String::new();
-                    html::push_html(&mut html_output, parser);
-                    tx.send(UIMessage::UpdatePreview(html_output)).await.unwrap();
+                    tx.send(Self::render_preview(&markdown_editor, &content, dark_mode)).await.unwrap();
+                    Self::update_editor_lines(&tx, &mut editor_lines, &content).await;
+                    Self::sync_properties(&window, &content, &mut properties, false);
 
                     // 更新打开文件的状态
                     let open_files_data = {
                         let editor = markdown_editor.lock().unwrap();
                         editor.get_open_files().iter().map(|f| OpenFileData {
-                            path: f.path.strip_prefix(&editor.get_root_dir())
+                            path: f.path.strip_prefix(editor.get_root_dir())
                                 .unwrap_or(&f.path)
                                 .to_string_lossy()
                                 .to_string()
@@ -381,33 +998,1051 @@ impl MainWindow {
                     };
                     tx.send(UIMessage::UpdateOpenFiles(open_files_data)).await.unwrap();
                 },
+                UIMessage::ThemeChanged(dark) => {
+                    dark_mode = dark;
+                    // 重新渲染预览和编辑器高亮以切换主题
+                    editor_lines.clear();
+                    window.upgrade_in_event_loop(|handle| {
+                        handle.set_editor_lines(ModelRc::default());
+                    }).ok();
+                    let content = markdown_editor.lock().unwrap().get_content();
+                    tx.send(Self::render_preview(&markdown_editor, &content, dark_mode)).await.unwrap();
+                    Self::update_editor_lines(&tx, &mut editor_lines, &content).await;
+                },
+                UIMessage::AttachmentAdded(path) => {
+                    link_index.add_file(&path);
+                    Self::refresh_file_tree(&tx, &root, &link_index, tag_notes.as_ref(), pool.as_ref()).await;
+                },
+                UIMessage::UpdateProperty(index, property) => {
+                    let key = property.key.trim().to_string();
+                    let duplicate = properties.iter().enumerate().any(|(i, p)| i != index && p.key == key);
+                    if index < properties.len() && !key.is_empty() && !duplicate {
+                        properties[index] = Property {
+                            key,
+                            value: PropertyValue::from_input(&property.kind, &property.value),
+                        };
+                        let content = Self::write_properties(&window, &markdown_editor, &tx, &properties).await;
+                        Self::sync_properties(&window, &content, &mut properties, false);
+                    } else {
+                        // 键为空或重复时恢复面板
+                        let content = markdown_editor.lock().unwrap().get_content();
+                        Self::sync_properties(&window, &content, &mut properties, true);
+                    }
+                },
+                UIMessage::RemoveProperty(index) => {
+                    if index < properties.len() {
+                        properties.remove(index);
+                        let content = Self::write_properties(&window, &markdown_editor, &tx, &properties).await;
+                        Self::sync_properties(&window, &content, &mut properties, true);
+                    }
+                },
+                UIMessage::AddProperty => {
+                    let mut key = "property".to_string();
+                    let mut n = 1;
+                    while properties.iter().any(|p| p.key == key) {
+                        key = format!("property {}", n);
+                        n += 1;
+                    }
+                    properties.push(Property { key, value: PropertyValue::Text(String::new()) });
+                    let content = Self::write_properties(&window, &markdown_editor, &tx, &properties).await;
+                    Self::sync_properties(&window, &content, &mut properties, true);
+                },
+                UIMessage::RefreshTags => {
+                    let Some(pool) = &pool else { continue };
+                    match db::tags::all_tags(pool).await {
+                        Ok(rows) => {
+                            let entries = Self::tag_entries(&rows);
+                            window.upgrade_in_event_loop(move |handle| {
+                                handle.set_tags(ModelRc::new(Rc::new(VecModel::from(entries))));
+                            }).ok();
+                        },
+                        Err(e) => eprintln!("Failed to load tags: {}", e),
+                    }
+                    // 保存或重命名后，过滤中的标签对应的笔记可能变了
+                    if tag_notes.is_some() {
+                        match db::tags::notes_with_tag(pool, &tag_filter).await {
+                            Ok(notes) => tag_notes = Some(notes.iter().map(|note| root.join(note)).collect()),
+                            Err(e) => eprintln!("Failed to filter by tag: {}", e),
+                        }
+                        Self::refresh_file_tree(&tx, &root, &link_index, tag_notes.as_ref(), Some(pool)).await;
+                    }
+                },
+                UIMessage::FilterByTag(tag) => {
+                    tag_notes = match (&pool, tag.is_empty()) {
+                        (Some(pool), false) => match db::tags::notes_with_tag(pool, &tag).await {
+                            Ok(notes) => Some(notes.iter().map(|note| root.join(note)).collect()),
+                            Err(e) => {
+                                eprintln!("Failed to filter by tag: {}", e);
+                                None
+                            }
+                        },
+                        _ => None,
+                    };
+                    let tag = if tag_notes.is_some() { tag } else { String::new() };
+                    tag_filter = tag.clone();
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_tag_filter(tag.into());
+                    }).ok();
+                    Self::refresh_file_tree(&tx, &root, &link_index, tag_notes.as_ref(), pool.as_ref()).await;
+                },
+                UIMessage::OpenDailyNote(step) => {
+                    // 当前打开的是日记时以它的日期为准，否则以今天为准
+                    let current = markdown_editor.lock().unwrap().get_current_file();
+                    let from = current.as_deref()
+                        .and_then(|path| daily::date_of(&config, &root, path))
+                        .filter(|_| step != 0)
+                        .unwrap_or_else(daily::today);
+                    if let Some(date) = daily::adjacent(&config, &root, from, step) {
+                        tx.send(UIMessage::OpenDailyNoteOn(date)).await.unwrap();
+                    }
+                },
+                UIMessage::OpenDailyNoteOn(date) => {
+                    let name = daily::note_name(&config, date);
+                    if root.join(&name).exists() {
+                        tx.send(UIMessage::OpenFile(name)).await.unwrap();
+                    } else {
+                        tx.send(UIMessage::CreateNote(name, daily::initial_content(&config, &root, date))).await.unwrap();
+                    }
+                },
+                UIMessage::LoadTemplates => {
+                    let names = templates::list_templates(&config, &root).iter()
+                        .map(|path| SharedString::from(vault::relative_name(&config.templates_dir(&root), path)))
+                        .collect::<Vec<SharedString>>();
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_templates(ModelRc::new(Rc::new(VecModel::from(names))));
+                    }).ok();
+                },
+                UIMessage::CreateFromTemplate(name, template) => {
+                    let name = name.trim().trim_start_matches('/');
+                    if name.is_empty() {
+                        continue;
+                    }
+                    let name = if vault::is_note(Path::new(name)) { name.to_string() } else { format!("{}.md", name) };
+                    let title = Path::new(&name).file_stem().unwrap_or_default().to_string_lossy().to_string();
+                    let expanded = if template.is_empty() {
+                        Expanded { content: String::new(), cursor: None }
+                    } else {
+                        templates::expand_file(&config.templates_dir(&root).join(&template), &title, Local::now().naive_local())
+                    };
+                    tx.send(UIMessage::CreateNote(name, expanded)).await.unwrap();
+                },
+                UIMessage::CreateNote(name, expanded) => {
+                    // 不覆盖已有的笔记
+                    if root.join(&name).exists() {
+                        eprintln!("Note already exists: {}", name);
+                        tx.send(UIMessage::OpenFile(name)).await.unwrap();
+                        continue;
+                    }
+                    if let Err(e) = markdown_editor.lock().unwrap().create_file(&name, &expanded.content) {
+                        eprintln!("Failed to create note: {}", e);
+                        continue;
+                    }
+                    link_index.update_note(&root.join(&name), &expanded.content);
+                    if let Some(pool) = &pool {
+                        Self::sync_note_record(&window, pool, &root, &root.join(&name), &expanded.content).await;
+                    }
+                    Self::refresh_file_tree(&tx, &root, &link_index, tag_notes.as_ref(), pool.as_ref()).await;
+                    tx.send(UIMessage::OpenFile(name)).await.unwrap();
+                    // 在打开文件之后再移动光标
+                    if let Some(cursor) = expanded.cursor {
+                        tx.send(UIMessage::SetEditorCursor(cursor)).await.unwrap();
+                    }
+                },
+                UIMessage::SetEditorCursor(cursor) => {
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.invoke_set_editor_cursor(cursor as i32);
+                    }).ok();
+                },
+                UIMessage::CalendarRefresh => {
+                    Self::refresh_calendar(&window, pool.as_ref(), &config, &root, &calendar).await;
+                },
+                UIMessage::CalendarNavigate(step) => {
+                    calendar.navigate(step, daily::today());
+                    Self::refresh_calendar(&window, pool.as_ref(), &config, &root, &calendar).await;
+                },
+                UIMessage::CalendarSetMode(mode) => {
+                    calendar.mode = if mode == "week" { CalendarMode::Week } else { CalendarMode::Month };
+                    calendar.anchor = calendar.selected;
+                    Self::refresh_calendar(&window, pool.as_ref(), &config, &root, &calendar).await;
+                },
+                UIMessage::CalendarSelectDay(date) => {
+                    if let Some(date) = calendar::parse_date(&date) {
+                        calendar.selected = date;
+                        Self::refresh_calendar(&window, pool.as_ref(), &config, &root, &calendar).await;
+                    }
+                },
+                UIMessage::CalendarSaveEvent(data) => {
+                    let Some(pool) = &pool else { continue };
+                    let mut event = match calendar::event_from_data(&data) {
+                        Ok(event) => event,
+                        Err(e) => {
+                            window.upgrade_in_event_loop(move |handle| {
+                                handle.set_calendar_error(e.into());
+                            }).ok();
+                            continue;
+                        },
+                    };
+                    match db::calendar::save_event(pool, &event).await {
+                        Ok(id) => {
+                            event.id = id;
+                            calendar.selected = event.start.date();
+                            let start = event.start;
+                            let end = event.end;
+                            let data = calendar::event_data(&Occurrence { event, start, end });
+                            window.upgrade_in_event_loop(move |handle| {
+                                handle.invoke_calendar_edit(data);
+                            }).ok();
+                        },
+                        Err(e) => eprintln!("Failed to save event: {}", e),
+                    }
+                    Self::refresh_calendar(&window, Some(pool), &config, &root, &calendar).await;
+                },
+                UIMessage::CalendarDeleteEvent(id) => {
+                    let Some(pool) = &pool else { continue };
+                    if let Err(e) = db::calendar::delete_event(pool, id as i64).await {
+                        eprintln!("Failed to delete event: {}", e);
+                    }
+                    Self::refresh_calendar(&window, Some(pool), &config, &root, &calendar).await;
+                },
+                UIMessage::CalendarDeleteOccurrence(id, date) => {
+                    let Some(pool) = &pool else { continue };
+                    if let Err(e) = db::calendar::add_exception(pool, id as i64, date).await {
+                        eprintln!("Failed to delete occurrence: {}", e);
+                    }
+                    Self::refresh_calendar(&window, Some(pool), &config, &root, &calendar).await;
+                },
+                UIMessage::CheckReminders => {
+                    let Some(pool) = &pool else { continue };
+                    let now = Local::now().naive_local();
+                    // 只需要看到提醒提前得最多的那个事件
+                    let minutes = db::calendar::max_reminder(pool).await.ok().flatten().unwrap_or(0).max(0) as u64;
+                    let to = now.date().checked_add_days(Days::new(minutes / (24 * 60) + 1)).unwrap_or(NaiveDate::MAX);
+                    let events = db::calendar::events_between(pool, now.date(), to).await.unwrap_or_default();
+                    let due = recurrence::expand(&events, now.date(), to)
+                        .into_iter()
+                        .filter(|occurrence| occurrence.start > now && occurrence.reminder_at().is_some_and(|at| at <= now))
+                        .filter(|occurrence| notified.insert((occurrence.event.id, occurrence.start)))
+                        .map(|occurrence| calendar::reminder_text(&occurrence))
+                        .collect::<Vec<_>>();
+                    if !due.is_empty() {
+                        window.upgrade_in_event_loop(move |handle| {
+                            let mut lines = handle.get_notification().to_string();
+                            for line in due {
+                                if !lines.is_empty() {
+                                    lines.push('\n');
+                                }
+                                lines.push_str(&line);
+                            }
+                            handle.set_notification(lines.into());
+                        }).ok();
+                    }
+                },
+                UIMessage::JsonValidate(text) => {
+                    let error = if text.trim().is_empty() {
+                        json_value = None;
+                        String::new()
+                    } else {
+                        match json_formatter::parse(&text) {
+                            Ok(value) => {
+                                json_value = Some(value);
+                                String::new()
+                            },
+                            Err(e) => e.to_string(),
+                        }
+                    };
+                    Self::set_json_input(&window, error, None);
+                    Self::update_json_tree(&window, json_value.as_ref(), &mut json_tree, &json_query);
+                },
+                UIMessage::JsonFormat(text, _, _) | UIMessage::JsonMinify(text, _) if text.trim().is_empty() => {},
+                UIMessage::JsonFormat(text, indent, sort) => {
+                    match json_formatter::parse(&text) {
+                        Ok(value) => {
+                            let value = if sort { json_formatter::sort_keys(value) } else { value };
+                            let indent = if indent > 0 { Some(indent as usize) } else { None };
+                            Self::set_json_input(&window, String::new(), Some(json_formatter::pretty(&value, indent)));
+                            json_value = Some(value);
+                            Self::update_json_tree(&window, json_value.as_ref(), &mut json_tree, &json_query);
+                        },
+                        Err(e) => Self::set_json_input(&window, e.to_string(), None),
+                    }
+                },
+                UIMessage::JsonMinify(text, sort) => {
+                    match json_formatter::parse(&text) {
+                        Ok(value) => {
+                            let value = if sort { json_formatter::sort_keys(value) } else { value };
+                            Self::set_json_input(&window, String::new(), Some(json_formatter::minify(&value)));
+                            json_value = Some(value);
+                            Self::update_json_tree(&window, json_value.as_ref(), &mut json_tree, &json_query);
+                        },
+                        Err(e) => Self::set_json_input(&window, e.to_string(), None),
+                    }
+                },
+                UIMessage::JsonToggleNode(path) => {
+                    if !json_tree.collapsed.remove(&path) {
+                        json_tree.collapsed.insert(path);
+                    }
+                    let nodes = json_value.as_ref().map(|value| json_formatter::tree(value, &json_tree)).unwrap_or_default();
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_json_nodes(ModelRc::new(Rc::new(VecModel::from(nodes))));
+                    }).ok();
+                },
+                UIMessage::JsonExpandAll(expand) => {
+                    json_tree.collapsed = match (&json_value, expand) {
+                        (Some(value), false) => json_formatter::container_paths(value),
+                        _ => HashSet::new(),
+                    };
+                    let nodes = json_value.as_ref().map(|value| json_formatter::tree(value, &json_tree)).unwrap_or_default();
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_json_nodes(ModelRc::new(Rc::new(VecModel::from(nodes))));
+                    }).ok();
+                },
+                UIMessage::JsonQuery(query) => {
+                    json_query = query.trim().to_string();
+                    Self::update_json_tree(&window, json_value.as_ref(), &mut json_tree, &json_query);
+                },
+                UIMessage::JsonDiff(left, right) => {
+                    let parsed = json_formatter::parse(&left).map_err(|e| format!("左侧 {}", e))
+                        .and_then(|left| json_formatter::parse(&right).map(|right| (left, right)).map_err(|e| format!("右侧 {}", e)));
+                    let (rows, error) = match parsed {
+                        Ok((left, right)) => {
+                            let rows = json_formatter::diff(&left, &right).into_iter().map(|difference| JsonDiffRow {
+                                path: difference.path.into(),
+                                change: difference.change.name().into(),
+                                left: difference.left.unwrap_or_default().into(),
+                                right: difference.right.unwrap_or_default().into(),
+                            }).collect::<Vec<_>>();
+                            (rows, String::new())
+                        },
+                        Err(_) if left.trim().is_empty() || right.trim().is_empty() => (Vec::new(), "在两侧分别粘贴 JSON".to_string()),
+                        Err(e) => (Vec::new(), e),
+                    };
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_json_diff_rows(ModelRc::new(Rc::new(VecModel::from(rows))));
+                        handle.set_json_diff_error(error.into());
+                    }).ok();
+                },
+                UIMessage::ConvertTimestamp(input, from, to) => {
+                    let result = timestamp_converter::Zone::parse(&from).and_then(|from| {
+                        let to = timestamp_converter::Zone::parse(&to)?;
+                        let (kind, instant) = timestamp_converter::parse(&input, from)?;
+                        Ok((kind, timestamp_converter::rows(instant, to, Utc::now())))
+                    });
+                    let (kind, rows, error) = match result {
+                        Ok((kind, rows)) => (kind.label().to_string(), rows, String::new()),
+                        Err(_) if input.trim().is_empty() => (String::new(), Vec::new(), String::new()),
+                        Err(e) => (String::new(), Vec::new(), e),
+                    };
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_timestamp_kind(kind.into());
+                        handle.set_timestamp_error(error.into());
+                        handle.set_timestamp_rows(ModelRc::new(Rc::new(VecModel::from(rows))));
+                    }).ok();
+                },
+                UIMessage::TimestampNow(zone) => {
+                    let now = Utc::now();
+                    let rows = timestamp_converter::Zone::parse(&zone).map(|zone| timestamp_converter::rows(now, zone, now));
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_timestamp_input(now.timestamp().to_string().into());
+                        match rows {
+                            Ok(rows) => {
+                                handle.set_timestamp_kind(timestamp_converter::InputKind::UnixSeconds.label().into());
+                                handle.set_timestamp_error(SharedString::default());
+                                handle.set_timestamp_rows(ModelRc::new(Rc::new(VecModel::from(rows))));
+                            },
+                            Err(e) => handle.set_timestamp_error(e.into()),
+                        }
+                    }).ok();
+                },
+                UIMessage::HashText(text, key) => {
+                    let key = (!key.is_empty()).then_some(key.as_bytes());
+                    digests = hash_tool::hash_text(&text, key);
+                    digest_status = match key {
+                        Some(_) => format!("HMAC，{} 字节", text.len()),
+                        None => format!("{} 字节", text.len()),
+                    };
+                    Self::show_digests(&window, &digests, &digest_status, &expected_digest);
+                },
+                UIMessage::HashFile(path, key) => {
+                    if path.trim().is_empty() {
+                        continue;
+                    }
+                    let path = root.join(path.trim());
+                    window.upgrade_in_event_loop(|handle| handle.set_hash_status("计算中…".into())).ok();
+                    // 大文件可能要好几秒，不阻塞其他消息
+                    let tx = tx.clone();
+                    tokio::spawn(async move {
+                        let result = tokio::task::spawn_blocking(move || {
+                            let key = (!key.is_empty()).then_some(key.as_bytes());
+                            hash_tool::hash_file(&path, key)
+                                .map(|(digests, size)| (digests, format!("{}，{} 字节{}", path.display(), size, if key.is_some() { "，HMAC" } else { "" })))
+                                .map_err(|e| format!("无法读取 {}: {}", path.display(), e))
+                        }).await.unwrap_or_else(|e| Err(e.to_string()));
+                        let (result, status) = match result {
+                            Ok((digests, status)) => (Ok(digests), status),
+                            Err(e) => (Err(e), String::new()),
+                        };
+                        tx.send(UIMessage::HashDone(result, status)).await.unwrap();
+                    });
+                },
+                UIMessage::HashDone(result, status) => {
+                    (digests, digest_status) = match result {
+                        Ok(result) => (result, status),
+                        Err(e) => (Vec::new(), e),
+                    };
+                    Self::show_digests(&window, &digests, &digest_status, &expected_digest);
+                },
+                UIMessage::HashCompare(expected) => {
+                    expected_digest = expected;
+                    Self::show_digests(&window, &digests, &digest_status, &expected_digest);
+                },
+                UIMessage::Encode(text, encoding) => {
+                    let output = Encoding::from_index(encoding).encode(&text);
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_hash_output(output.into());
+                        handle.set_hash_error(SharedString::default());
+                    }).ok();
+                },
+                UIMessage::Decode(text, encoding) => {
+                    let (output, error) = match Encoding::from_index(encoding).decode(&text) {
+                        Ok(output) => (output, String::new()),
+                        Err(e) => (String::new(), e),
+                    };
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_hash_output(output.into());
+                        handle.set_hash_error(error.into());
+                    }).ok();
+                },
+                UIMessage::ClipboardRecord(text, source) => {
+                    let Some(pool) = &pool else { continue };
+                    if let Err(e) = db::clipboard::record(pool, &text, source.as_deref()).await {
+                        eprintln!("Failed to record clipboard entry: {}", e);
+                    }
+                    Self::prune_clipboard(pool, &config).await;
+                    Self::refresh_clipboard(&window, pool, &clipboard_query).await;
+                },
+                UIMessage::ClipboardSearch(query) => {
+                    let Some(pool) = &pool else { continue };
+                    clipboard_query = query;
+                    Self::refresh_clipboard(&window, pool, &clipboard_query).await;
+                },
+                UIMessage::ClipboardPin(id, pinned) => {
+                    let Some(pool) = &pool else { continue };
+                    if let Err(e) = db::clipboard::set_pinned(pool, id as i64, pinned).await {
+                        eprintln!("Failed to pin clipboard entry: {}", e);
+                    }
+                    Self::refresh_clipboard(&window, pool, &clipboard_query).await;
+                },
+                UIMessage::ClipboardDelete(id) => {
+                    let Some(pool) = &pool else { continue };
+                    if let Err(e) = db::clipboard::delete_entry(pool, id as i64).await {
+                        eprintln!("Failed to delete clipboard entry: {}", e);
+                    }
+                    Self::refresh_clipboard(&window, pool, &clipboard_query).await;
+                },
+                UIMessage::ClipboardClear => {
+                    let Some(pool) = &pool else { continue };
+                    if let Err(e) = db::clipboard::clear(pool).await {
+                        eprintln!("Failed to clear clipboard history: {}", e);
+                    }
+                    Self::refresh_clipboard(&window, pool, &clipboard_query).await;
+                },
+                UIMessage::CalendarImport(path) => {
+                    let Some(pool) = &pool else { continue };
+                    let path = root.join(path);
+                    let status = match fs::read_to_string(&path) {
+                        Ok(source) => {
+                            let events = ics::parse(&source);
+                            match db::calendar::import_events(pool, &events).await {
+                                Ok(added) => format!("从 {} 导入了 {} 个事件（共 {} 个）", path.display(), added, events.len()),
+                                Err(e) => format!("导入失败: {}", e),
+                            }
+                        },
+                        Err(e) => format!("无法读取 {}: {}", path.display(), e),
+                    };
+                    Self::refresh_calendar(&window, Some(pool), &config, &root, &calendar).await;
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_calendar_status(status.into());
+                    }).ok();
+                },
+                UIMessage::CalendarExport(path) => {
+                    let Some(pool) = &pool else { continue };
+                    let path = root.join(path);
+                    let (start, end) = calendar.range();
+                    let status = match db::calendar::events_between(pool, start, end).await {
+                        Ok(events) => match fs::write(&path, ics::export(&events)) {
+                            Ok(()) => format!("已导出 {} 个事件到 {}", events.len(), path.display()),
+                            Err(e) => format!("无法写入 {}: {}", path.display(), e),
+                        },
+                        Err(e) => format!("导出失败: {}", e),
+                    };
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_calendar_status(status.into());
+                    }).ok();
+                },
+                UIMessage::ImportNotes(source, path) => {
+                    let Some(source) = import::Source::from_name(&source) else { continue };
+                    window.upgrade_in_event_loop(|handle| handle.set_import_status("导入中…".into())).ok();
+                    // 大的导出要转换好一会儿，只在写文件时锁住编辑器
+                    let (markdown_editor, tx) = (markdown_editor.clone(), tx.clone());
+                    tokio::spawn(async move {
+                        let result = tokio::task::spawn_blocking(move || {
+                            import::import(&markdown_editor, source, Path::new(path.trim())).map_err(|e| e.to_string())
+                        }).await.unwrap_or_else(|e| Err(e.to_string()));
+                        tx.send(UIMessage::ImportDone(result)).await.unwrap();
+                    });
+                },
+                UIMessage::ImportDone(result) => {
+                    let status = match result {
+                        Ok(report) => {
+                            match LinkIndex::build(&root) {
+                                Ok(index) => link_index = index,
+                                Err(e) => eprintln!("Failed to index vault: {}", e),
+                            }
+                            if let Some(pool) = &pool {
+                                Self::index_notes(pool, &root, true).await;
+                                tx.send(UIMessage::RefreshTags).await.unwrap();
+                            }
+                            Self::refresh_file_tree(&tx, &root, &link_index, tag_notes.as_ref(), None).await;
+                            for skipped in &report.skipped {
+                                eprintln!("Skipped during import: {}", skipped);
+                            }
+                            let mut status = format!(
+                                "已导入 {} 篇笔记、{} 个附件到 {}",
+                                report.notes, report.attachments, vault::relative_name(&root, &report.folder),
+                            );
+                            if !report.skipped.is_empty() {
+                                status.push_str(&format!("，跳过 {} 项", report.skipped.len()));
+                            }
+                            status
+                        },
+                        Err(e) => format!("导入失败: {}", e),
+                    };
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_import_status(status.into());
+                    }).ok();
+                },
+                UIMessage::CheckAttachments => {
+                    let issues = Self::attachment_issues(&root, &link_index);
+                    window.upgrade_in_event_loop(move |handle| {
+                        handle.set_attachment_issues(ModelRc::new(Rc::new(VecModel::from(issues))));
+                    }).ok();
+                },
+                UIMessage::Export(format, folder) => {
+                    let (current_file, current_content) = {
+                        let editor = markdown_editor.lock().unwrap();
+                        (editor.get_current_file(), editor.get_content())
+                    };
+                    window.upgrade_in_event_loop(|handle| handle.set_export_status("导出中…".into())).ok();
+                    // PDF 和整个站点可能要好几秒，不阻塞其他消息
+                    let (root, link_index, config, window) = (root.clone(), link_index.clone(), config.clone(), window.clone());
+                    tokio::spawn(async move {
+                        let status = tokio::task::spawn_blocking(move || {
+                            match Self::export_notes(&root, &link_index, &config, &format, folder, current_file.as_deref(), &current_content) {
+                                Ok((0, _)) => "没有可导出的笔记".to_string(),
+                                Ok((count, out_dir)) => format!("已导出 {} 个文件到 {}", count, out_dir.display()),
+                                Err(e) => format!("导出失败: {}", e),
+                            }
+                        }).await.unwrap_or_else(|e| format!("导出失败: {}", e));
+                        window.upgrade_in_event_loop(move |handle| {
+                            handle.set_export_status(status.into());
+                        }).ok();
+                    });
+                },
+            }
+        }
+    }
+
+    /// Renders the preview blocks and the heading outline of `content`.
+    fn render_preview(markdown_editor: &Arc<Mutex<MarkdownEditor>>, content: &str, dark: bool) -> UIMessage {
+        let (root, note_dir) = {
+            let editor = markdown_editor.lock().unwrap();
+            let root = editor.get_root_dir().to_path_buf();
+            let note_dir = editor.get_current_file()
+                .and_then(|file| file.parent().map(Path::to_path_buf))
+                .unwrap_or_else(|| root.clone());
+            (root, note_dir)
+        };
+        let blocks = render_blocks(content, &RenderContext { dark, note_dir: &note_dir, root: &root });
+
+        // 第 n 个标题对应第 n 个标题块
+        let mut heading_blocks = blocks.iter().enumerate()
+            .filter(|(_, block)| matches!(block.kind, BlockKind::Heading(_)))
+            .map(|(index, _)| index as i32);
+        let headings = outline::headings(content);
+        let ends = headings.iter().skip(1).map(|heading| heading.offset as i32).chain(std::iter::once(i32::MAX));
+        let items = headings.iter().zip(ends).map(|(heading, end)| OutlineItem {
+            text: heading.text.as_str().into(),
+            level: heading.level as i32,
+            offset: heading.offset as i32,
+            end,
+            block: heading_blocks.next().unwrap_or(-1),
+        }).collect();
+
+        UIMessage::UpdatePreview(blocks, items)
+    }
+
+    /// Stores a file in the attachments folder via `store` and links it at the
+    /// editor cursor. Returns `false` when no note is open or storing fails.
+    fn attach_to_current_note(
+        window: &AppWindow,
+        markdown_editor: &Arc<Mutex<MarkdownEditor>>,
+        config: &Config,
+        tx: &mpsc::Sender<UIMessage>,
+        store: impl FnOnce(&Path) -> io::Result<PathBuf>,
+    ) -> bool {
+        let (note, root) = {
+            let editor = markdown_editor.lock().unwrap();
+            (editor.get_current_file(), editor.get_root_dir().to_path_buf())
+        };
+        let Some(note) = note else {
+            return false;
+        };
+        match store(&config.attachments_dir(&root)) {
+            Ok(path) => {
+                let note_dir = note.parent().unwrap_or(&root);
+                let content = source_editor::insert_at_cursor(window, &attachments::markdown_link(note_dir, &path));
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    tx.send(UIMessage::AttachmentAdded(path)).await.unwrap();
+                    tx.send(UIMessage::UpdateEditorContentFromUI(content)).await.unwrap();
+                });
+                true
+            },
+            Err(e) => {
+                eprintln!("Failed to attach file: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn update_editor_lines(tx: &mpsc::Sender<UIMessage>, editor_lines: &mut Vec<TokenLine>, content: &str) {
+        let lines = tokenize(content);
+        if let Some(diff) = source_editor::diff_lines(editor_lines, &lines) {
+            *editor_lines = lines;
+            tx.send(UIMessage::UpdateEditorLines(diff)).await.unwrap();
+        }
+    }
+
+    /// Re-parses the front matter of `content` and refreshes the properties
+    /// panel when the properties changed (or always with `force`).
+    fn sync_properties(window: &Weak<AppWindow>, content: &str, properties: &mut Vec<Property>, force: bool) {
+        let (changed, error) = match front_matter::parse(content) {
+            Ok(parsed) => {
+                let changed = force || parsed != *properties;
+                *properties = parsed;
+                (changed, String::new())
+            },
+            Err(e) => (false, format!("front matter 解析失败: {}", e)),
+        };
+        let rows = changed.then(|| properties.iter().map(|p| NoteProperty {
+            key: p.key.as_str().into(),
+            kind: p.value.kind().into(),
+            value: p.value.display().into(),
+        }).collect::<Vec<NoteProperty>>());
+        window.upgrade_in_event_loop(move |handle| {
+            if let Some(rows) = rows {
+                handle.set_note_properties(ModelRc::new(Rc::new(VecModel::from(rows))));
+            }
+            handle.set_properties_error(error.into());
+        }).ok();
+    }
+
+    /// Writes edited properties back into the note's front matter and returns
+    /// the new note content.
+    async fn write_properties(
+        window: &Weak<AppWindow>,
+        markdown_editor: &Arc<Mutex<MarkdownEditor>>,
+        tx: &mpsc::Sender<UIMessage>,
+        properties: &[Property],
+    ) -> String {
+        let content = markdown_editor.lock().unwrap().get_content();
+        if front_matter::parse(&content).is_err() {
+            return content;
+        }
+        let content = front_matter::with_properties(&content, properties);
+        markdown_editor.lock().unwrap().update_content(content.clone());
+        let content_clone = content.clone();
+        window.upgrade_in_event_loop(move |handle| {
+            handle.set_editor_content(content_clone.into());
+        }).ok();
+        tx.send(UIMessage::UpdateEditorContentFromUI(content.clone())).await.unwrap();
+        content
+    }
+
+    async fn refresh_calendar(
+        window: &Weak<AppWindow>,
+        pool: Option<&SqlitePool>,
+        config: &Config,
+        root: &Path,
+        calendar: &CalendarState,
+    ) {
+        let (start, end) = calendar.range();
+        let events = match pool {
+            Some(pool) => db::calendar::events_between(pool, start, end).await.unwrap_or_else(|e| {
+                eprintln!("Failed to load events: {}", e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        let events = recurrence::expand(&events, start, end);
+        let daily_notes = daily::existing_dates(config, root).into_iter().collect::<HashSet<_>>();
+        let today = daily::today();
+        let days = calendar.build_days(&events, &daily_notes, today);
+
+        // 选中日期不在当前范围内时单独查询
+        let selected_events = if calendar.selected >= start && calendar.selected <= end {
+            events.iter().filter(|event| event.start.date() == calendar.selected).cloned().collect()
+        } else if let Some(pool) = pool {
+            let events = db::calendar::events_between(pool, calendar.selected, calendar.selected).await.unwrap_or_default();
+            recurrence::expand(&events, calendar.selected, calendar.selected)
+        } else {
+            Vec::new()
+        };
+        let selected_events = selected_events.iter().map(calendar::event_data).collect::<Vec<EventData>>();
+
+        let title = calendar.title();
+        let mode = if calendar.mode == CalendarMode::Week { "week" } else { "month" };
+        let selected = calendar::date_string(calendar.selected);
+        window.upgrade_in_event_loop(move |handle| {
+            handle.set_calendar_days(calendar::to_model(days));
+            handle.set_calendar_events(ModelRc::new(Rc::new(VecModel::from(selected_events))));
+            handle.set_calendar_title(title.into());
+            handle.set_calendar_mode(mode.into());
+            handle.set_calendar_selected(selected);
+            handle.set_calendar_error(SharedString::default());
+        }).ok();
+    }
+
+    /// Shows the validation error; `text` replaces the input after formatting.
+    fn set_json_input(window: &Weak<AppWindow>, error: String, text: Option<String>) {
+        window.upgrade_in_event_loop(move |handle| {
+            if let Some(text) = text {
+                handle.set_json_text(text.into());
+            }
+            handle.set_json_error(error.into());
+        }).ok();
+    }
+
+    /// Exports the open note, or with `folder` every note next to it (the
+    /// whole vault when nothing is open). Unsaved edits are included. Word
+    /// and EPUB put a folder into one file with a chapter per note. The
+    /// `site` format always publishes the whole vault from disk.
+    fn export_notes(
+        root: &Path,
+        link_index: &LinkIndex,
+        config: &Config,
+        format: &str,
+        folder: bool,
+        current_file: Option<&Path>,
+        current_content: &str,
+    ) -> io::Result<(usize, PathBuf)> {
+        if format == "site" {
+            let out_dir = config.site_dir(root);
+            let report = export::site::publish(root, config, &out_dir)?;
+            return Ok((report.pages, out_dir));
+        }
+        let base = current_file.and_then(Path::parent).unwrap_or(root).to_path_buf();
+        let notes = match current_file {
+            Some(file) if !folder => vec![file.to_path_buf()],
+            _ if folder => export::notes_in(&base)?,
+            _ => Vec::new(),
+        };
+        let published = export::published(&notes);
+        let exporter = Exporter {
+            root,
+            index: link_index,
+            base,
+            out_dir: config.export_dir(root),
+            published: Some(&published),
+            copy_assets: false,
+        };
+        let content = |note: &Path| {
+            if Some(note) == current_file {
+                Ok(current_content.to_string())
+            } else {
+                fs::read_to_string(note)
+            }
+        };
+        let written = match format {
+            "html" => export::html::export(&exporter, &notes, content)?,
+            "pdf" => export::pdf::export(&exporter, &notes, content, &PdfOptions::from_config(config, root))?,
+            "docx" => export::docx::export(&exporter, &notes, content)?,
+            "epub" => export::epub::export(&exporter, &notes, content)?,
+            other => return Err(io::Error::new(io::ErrorKind::Unsupported, format!("不支持的格式 {}", other))),
+        };
+        Ok((written.len(), exporter.out_dir))
+    }
+
+    /// Updates the notes index after a note was written and shows the result.
+    async fn sync_note_record(window: &Weak<AppWindow>, pool: &SqlitePool, root: &Path, path: &Path, content: &str) {
+        match index::sync_note(pool, root, path, content).await {
+            Ok(note) => Self::show_note_info(window, Some(&note)),
+            Err(e) => eprintln!("Failed to index note {}: {}", path.display(), e),
+        }
+    }
+
+    fn show_note_info(window: &Weak<AppWindow>, note: Option<&NoteRecord>) {
+        let info = note.map(|note| format!(
+            "创建于 {} · 编辑于 {} · {} 字",
+            note.created_at.format("%Y-%m-%d %H:%M"),
+            note.updated_at.format("%Y-%m-%d %H:%M"),
+            note.word_count,
+        )).unwrap_or_default();
+        window.upgrade_in_event_loop(move |handle| handle.set_note_info(info.into())).ok();
+    }
+
+    async fn prune_clipboard(pool: &SqlitePool, config: &Config) {
+        let max_age = (config.clipboard_days > 0).then(|| chrono::Duration::days(config.clipboard_days));
+        if let Err(e) = db::clipboard::prune(pool, config.clipboard_limit, max_age).await {
+            eprintln!("Failed to prune clipboard history: {}", e);
+        }
+    }
+
+    async fn refresh_clipboard(window: &Weak<AppWindow>, pool: &SqlitePool, query: &str) {
+        let entries = db::clipboard::search(pool, query, 100).await.unwrap_or_else(|e| {
+            eprintln!("Failed to load clipboard history: {}", e);
+            Vec::new()
+        });
+        let now = Local::now().naive_local();
+        let items = entries.iter().map(|entry| clipboard_manager::item(entry, now)).collect::<Vec<_>>();
+        window.upgrade_in_event_loop(move |handle| {
+            handle.set_clipboard_items(ModelRc::new(Rc::new(VecModel::from(items))));
+        }).ok();
+    }
+
+    fn show_digests(window: &Weak<AppWindow>, digests: &Digests, status: &str, expected: &str) {
+        let matched = hash_tool::find_match(digests, expected);
+        let rows = digests.iter().map(|(algorithm, digest)| HashRow {
+            algorithm: algorithm.name().into(),
+            value: digest.as_str().into(),
+            matched: matched == Some(*algorithm),
+        }).collect::<Vec<_>>();
+        let compare_status = match matched {
+            Some(algorithm) => format!("与 {} 一致", algorithm.name()),
+            None => "没有匹配的摘要".to_string(),
+        };
+        let status = status.to_string();
+        window.upgrade_in_event_loop(move |handle| {
+            handle.set_hash_rows(ModelRc::new(Rc::new(VecModel::from(rows))));
+            handle.set_hash_status(status.into());
+            handle.set_hash_compare_status(compare_status.into());
+            handle.set_hash_compare_matched(matched.is_some());
+        }).ok();
+    }
+
+    /// Re-runs the JSONPath query and shows the tree with its matches.
+    fn update_json_tree(window: &Weak<AppWindow>, value: Option<&serde_json::Value>, tree: &mut TreeState, query: &str) {
+        let status = match value {
+            Some(value) if !query.is_empty() => match json_query::query(value, query) {
+                Ok(paths) => {
+                    let status = format!("{} 个匹配", paths.len());
+                    tree.matches = paths.into_iter().collect();
+                    tree.reveal_matches();
+                    status
+                },
+                Err(e) => {
+                    tree.matches.clear();
+                    e
+                },
+            },
+            _ => {
+                tree.matches.clear();
+                String::new()
+            },
+        };
+        let nodes = value.map(|value| json_formatter::tree(value, tree)).unwrap_or_default();
+        window.upgrade_in_event_loop(move |handle| {
+            handle.set_json_nodes(ModelRc::new(Rc::new(VecModel::from(nodes))));
+            handle.set_json_query_status(status.into());
+        }).ok();
+    }
+
+    /// Stores the properties and tags of a note in the database.
+    async fn index_note(pool: &SqlitePool, note_path: &str, content: &str) {
+        let note_properties = match front_matter::parse(content) {
+            Ok(note_properties) => {
+                if let Err(e) = db::properties::save_properties(pool, note_path, &note_properties).await {
+                    eprintln!("Failed to index properties of {}: {}", note_path, e);
+                }
+                note_properties
+            },
+            Err(e) => {
+                eprintln!("Invalid front matter in {}: {}", note_path, e);
+                Vec::new()
             }
+        };
+        let note_tags = tags::extract_tags(content, &note_properties);
+        if let Err(e) = db::tags::save_tags(pool, note_path, &note_tags).await {
+            eprintln!("Failed to index tags of {}: {}", note_path, e);
+        }
+    }
+
+    /// Indexes the notes so properties and tags can be queried without
+    /// opening files. With `all` every note is re-read, otherwise only those
+    /// changed on disk since the last scan. Returns whether anything changed.
+    async fn index_notes(pool: &SqlitePool, root: &Path, all: bool) -> bool {
+        let files = match vault::vault_files(root) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("Failed to list vault files: {}", e);
+                return false;
+            }
+        };
+        let mut contents = HashMap::new();
+        let changed = match index::scan(pool, root, &files, &mut contents).await {
+            Ok(report) => report != index::ScanReport::default(),
+            Err(e) => {
+                eprintln!("Failed to sync note index: {}", e);
+                false
+            }
+        };
+        let mut note_paths = Vec::new();
+        for path in files.iter().filter(|path| vault::is_note(path)) {
+            let note_path = vault::relative_name(root, path);
+            // scan 读过的不再读一遍
+            let content = match contents.remove(path) {
+                Some(content) => Some(content),
+                None if all => match fs::read_to_string(path) {
+                    Ok(content) => Some(content),
+                    Err(_) => continue,
+                },
+                None => None,
+            };
+            if let Some(content) = content {
+                Self::index_note(pool, &note_path, &content).await;
+            }
+            note_paths.push(note_path);
+        }
+        if let Err(e) = db::prune_notes(pool, &note_paths).await {
+            eprintln!("Failed to prune index: {}", e);
+        }
+        changed
+    }
+
+    /// Builds the tag pane rows; a parent tag counts the notes carrying it or
+    /// any of its nested tags.
+    fn tag_entries(rows: &[(String, String)]) -> Vec<TagEntry> {
+        let mut notes_by_tag: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+        for (note_path, tag) in rows {
+            for ancestor in tags::with_ancestors(tag) {
+                notes_by_tag.entry(ancestor).or_default().insert(note_path);
+            }
+        }
+        notes_by_tag.into_iter().map(|(tag, notes)| {
+            let depth = tag.matches('/').count();
+            TagEntry {
+                name: tag.rsplit('/').next().unwrap_or(tag).into(),
+                tag: tag.into(),
+                depth: depth as i32,
+                count: notes.len() as i32,
+            }
+        }).collect()
+    }
+
+    /// Reloads the file tree; with `filter` only those notes (and the folders
+    /// containing them) are listed. With `pool` notes changed outside the
+    /// app are re-indexed first.
+    async fn refresh_file_tree(
+        tx: &mpsc::Sender<UIMessage>,
+        root: &Path,
+        link_index: &LinkIndex,
+        filter: Option<&HashSet<PathBuf>>,
+        pool: Option<&SqlitePool>,
+    ) {
+        if let Some(pool) = pool {
+            if Self::index_notes(pool, root, false).await {
+                tx.send(UIMessage::RefreshTags).await.unwrap();
+            }
+        }
+        let orphans = attachments::report(link_index).orphans.into_iter().collect::<HashSet<_>>();
+        match Self::load_directory_tree(root, &orphans, filter) {
+            Ok(files) => tx.send(UIMessage::UpdateFileTree(files)).await.unwrap(),
+            Err(e) => eprintln!("Failed to load directory tree: {}", e),
         }
     }
 
-    fn load_directory_tree(root: &Path) -> std::io::Result<Vec<String>> {
+    fn attachment_issues(root: &Path, link_index: &LinkIndex) -> Vec<AttachmentIssue> {
+        let report = attachments::report(link_index);
+        let name = |path: &Path| SharedString::from(vault::relative_name(root, path));
+
+        // 先列出失效的链接和孤立的附件，再列出被引用的附件
+        let mut issues = report.broken.iter().map(|broken| AttachmentIssue {
+            kind: "broken".into(),
+            path: name(&broken.note),
+            detail: format!("找不到 {}", broken.target).into(),
+            line: broken.line as i32,
+        }).collect::<Vec<AttachmentIssue>>();
+        issues.extend(report.orphans.iter().map(|orphan| AttachmentIssue {
+            kind: "orphan".into(),
+            path: name(orphan),
+            detail: "没有笔记引用".into(),
+            line: 0,
+        }));
+        issues.extend(report.references.iter().filter(|(_, notes)| !notes.is_empty()).map(|(path, notes)| {
+            AttachmentIssue {
+                kind: "attachment".into(),
+                path: name(path),
+                detail: notes.iter().map(|note| vault::relative_name(root, note)).collect::<Vec<_>>().join(", ").into(),
+                line: 0,
+            }
+        }));
+        issues
+    }
+
+    fn load_directory_tree(
+        root: &Path,
+        orphans: &HashSet<PathBuf>,
+        filter: Option<&HashSet<PathBuf>>,
+    ) -> std::io::Result<Vec<TreeEntry>> {
         let mut result = Vec::new();
         if !root.exists() {
             fs::create_dir_all(root)?;
         }
-        Self::load_directory_tree_recursive(root, &mut result, 0)?;
-        println!("{:?}", result.clone());
+        Self::load_directory_tree_recursive(root, root, orphans, filter, &mut result, 0)?;
         Ok(result)
     }
 
-    fn load_directory_tree_recursive(dir: &Path, result: &mut Vec<String>, depth: usize) -> std::io::Result<()> {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let file_name = path.file_name().unwrap().to_string_lossy();
-            
-            if path.is_dir() {
-                result.push(format!("{}{}/", "  ".repeat(depth), file_name));
-                Self::load_directory_tree_recursive(&path, result, depth + 1)?;
-            } else if path.extension().map_or(false, |ext| ext == "md") {
-                result.push(format!("{}{}", "  ".repeat(depth), file_name));
+    fn load_directory_tree_recursive(
+        root: &Path,
+        dir: &Path,
+        orphans: &HashSet<PathBuf>,
+        filter: Option<&HashSet<PathBuf>>,
+        result: &mut Vec<TreeEntry>,
+        depth: usize,
+    ) -> std::io::Result<()> {
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()?;
+        // 目录在前，文件在后
+        paths.sort_by_key(|path| (!path.is_dir(), path.file_name().map(|name| name.to_ascii_lowercase())));
+
+        for path in paths {
+            let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+            if file_name.starts_with('.') {
+                continue;
+            }
+
+            let is_dir = path.is_dir();
+            if !is_dir && filter.is_some_and(|filter| !filter.contains(&path)) {
+                continue;
+            }
+            let len = result.len();
+            result.push(TreeEntry {
+                name: file_name.into(),
+                path: vault::relative_name(root, &path).into(),
+                depth: depth as i32,
+                is_dir,
+                is_note: vault::is_note(&path),
+                orphan: orphans.contains(&path),
+            });
+            if is_dir {
+                Self::load_directory_tree_recursive(root, &path, orphans, filter, result, depth + 1)?;
+                // 过滤时隐藏没有匹配笔记的目录
+                if filter.is_some() && result.len() == len + 1 {
+                    result.truncate(len);
+                }
             }
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -3,10 +3,13 @@ slint::include_modules!();
 mod main_window;
 
 pub use main_window::MainWindow;
+pub mod calendar;
 pub mod markdown_editor;
+pub mod preview;
+pub mod source_editor;
 
-// pub mod calendar;
-// pub mod json_formatter;
-// pub mod timestamp_converter;
-// pub mod hash_tool;
-// pub mod clipboard_manager;
\ No newline at end of file
+pub mod json_formatter;
+pub mod json_query;
+pub mod timestamp_converter;
+pub mod hash_tool;
+pub mod clipboard_manager;
\ No newline at end of file
@@ -0,0 +1,72 @@
+use slint::{Color, Image, ModelRc, SharedString, StyledText, VecModel};
+use std::rc::Rc;
+use crate::markdown::render::{Block, BlockKind};
+use crate::ui::{HighlightLine, HighlightSpan, PreviewBlock};
+
+fn rgb((r, g, b): (u8, u8, u8)) -> Color {
+    Color::from_rgb_u8(r, g, b)
+}
+
+fn styled_text(markdown: &str) -> StyledText {
+    StyledText::from_markdown(markdown).unwrap_or_else(|e| {
+        eprintln!("Failed to style preview text: {}", e);
+        StyledText::from_plain_text(markdown)
+    })
+}
+
+fn to_preview_block(block: Block) -> PreviewBlock {
+    let mut image = Image::default();
+    let mut image_size = (0, 0);
+    let (kind, level, marker) = match block.kind {
+        BlockKind::Heading(level) => ("heading", level as i32, String::new()),
+        BlockKind::Paragraph => ("paragraph", 0, String::new()),
+        BlockKind::ListItem(marker) => ("list-item", 0, marker),
+        BlockKind::CodeBlock => ("code", 0, String::new()),
+        BlockKind::Rule => ("rule", 0, String::new()),
+        BlockKind::Image { path, width, height } => match Image::load_from_path(&path) {
+            Ok(loaded) => {
+                image = loaded;
+                image_size = (width as i32, height as i32);
+                ("image", 0, String::new())
+            },
+            Err(_) => {
+                eprintln!("Failed to load image: {:?}", path);
+                ("missing-image", 0, format!("🖼 {}", path.display()))
+            },
+        },
+    };
+
+    let (background, lines) = match block.code {
+        Some(code) => {
+            let lines = code.lines.into_iter().map(|spans| {
+                let spans = spans.into_iter().map(|span| HighlightSpan {
+                    text: span.text.into(),
+                    color: rgb(span.color),
+                    bold: span.bold,
+                    italic: span.italic,
+                }).collect::<Vec<HighlightSpan>>();
+                HighlightLine { spans: ModelRc::new(VecModel::from(spans)) }
+            }).collect::<Vec<HighlightLine>>();
+            (rgb(code.background), lines)
+        },
+        None => (Color::default(), Vec::new()),
+    };
+
+    PreviewBlock {
+        kind: kind.into(),
+        level,
+        indent: block.indent as i32,
+        marker: SharedString::from(marker),
+        text: styled_text(&block.text),
+        background,
+        lines: ModelRc::new(VecModel::from(lines)),
+        image,
+        image_width: image_size.0,
+        image_height: image_size.1,
+    }
+}
+
+pub fn to_model(blocks: Vec<Block>) -> ModelRc<PreviewBlock> {
+    let blocks = blocks.into_iter().map(to_preview_block).collect::<Vec<PreviewBlock>>();
+    ModelRc::new(Rc::new(VecModel::from(blocks)))
+}
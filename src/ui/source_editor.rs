@@ -0,0 +1,134 @@
+use slint::{Color, Model, ModelRc, VecModel};
+use std::rc::Rc;
+use crate::markdown::tokenizer::{Token, TokenLine, TokenStyle};
+use crate::ui::{AppWindow, HighlightLine, HighlightSpan};
+
+/// Replaces `removed` lines starting at `start` with `inserted`. Computed on
+/// the tokio side so the UI thread only touches rows that actually changed.
+pub struct LineDiff {
+    pub start: usize,
+    pub removed: usize,
+    pub inserted: Vec<TokenLine>,
+}
+
+pub fn diff_lines(old: &[TokenLine], new: &[TokenLine]) -> Option<LineDiff> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    if prefix == old.len() && prefix == new.len() {
+        return None;
+    }
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    Some(LineDiff {
+        start: prefix,
+        removed: old.len() - prefix - suffix,
+        inserted: new[prefix..new.len() - suffix].to_vec(),
+    })
+}
+
+fn token_color(token: &Token, dark: bool) -> Color {
+    let style = token.style;
+    let hex = if token.markup {
+        if dark { 0x6a6f78 } else { 0xb0b0b0 }
+    } else if style.contains(TokenStyle::FRONT_MATTER) {
+        if dark { 0xc3a6ff } else { 0x7a5ea8 }
+    } else if style.contains(TokenStyle::CODE) || style.contains(TokenStyle::CODE_BLOCK) {
+        if dark { 0xe8917a } else { 0xc7254e }
+    } else if style.contains(TokenStyle::LINK) {
+        if dark { 0x6cb6ff } else { 0x0b62c4 }
+    } else if style.contains(TokenStyle::HEADING) {
+        if dark { 0x8fb8ff } else { 0x1f4e9a }
+    } else if style.contains(TokenStyle::STRIKETHROUGH) {
+        if dark { 0x8a8f98 } else { 0x888888 }
+    } else if style.contains(TokenStyle::QUOTE) {
+        if dark { 0x9ac47b } else { 0x5a7a3a }
+    } else if style.contains(TokenStyle::HTML) {
+        if dark { 0xd7a36b } else { 0xa05a2c }
+    } else if dark {
+        0xd0d0d0
+    } else {
+        0x333333
+    };
+    Color::from_argb_encoded(0xff000000 | hex)
+}
+
+fn to_highlight_line(line: &TokenLine, dark: bool) -> HighlightLine {
+    let spans = line.iter().map(|token| HighlightSpan {
+        text: token.text.as_str().into(),
+        color: token_color(token, dark),
+        bold: token.style.contains(TokenStyle::HEADING) || token.style.contains(TokenStyle::STRONG),
+        italic: token.style.contains(TokenStyle::EMPHASIS),
+    }).collect::<Vec<HighlightSpan>>();
+    HighlightLine { spans: ModelRc::new(VecModel::from(spans)) }
+}
+
+pub fn apply_diff(window: &AppWindow, diff: LineDiff, dark: bool) {
+    let model = window.get_editor_lines();
+    let Some(lines) = model.as_any().downcast_ref::<VecModel<HighlightLine>>() else {
+        let lines = diff.inserted.iter().map(|line| to_highlight_line(line, dark)).collect::<Vec<_>>();
+        window.set_editor_lines(ModelRc::new(Rc::new(VecModel::from(lines))));
+        return;
+    };
+
+    let mut inserted = diff.inserted.iter();
+    let mut row = diff.start;
+    // 先原地替换重叠的行，再处理增加或删除的行
+    for _ in 0..diff.removed.min(diff.inserted.len()) {
+        lines.set_row_data(row, to_highlight_line(inserted.next().unwrap(), dark));
+        row += 1;
+    }
+    for line in inserted {
+        lines.insert(row, to_highlight_line(line, dark));
+        row += 1;
+    }
+    for _ in diff.inserted.len()..diff.removed {
+        lines.remove(row);
+    }
+}
+
+/// Inserts `text` at the editor cursor, moves the cursor after it and
+/// returns the new editor content.
+pub fn insert_at_cursor(window: &AppWindow, text: &str) -> String {
+    let cursor = window.get_editor_cursor();
+    replace_selection(window, cursor, cursor, text)
+}
+
+/// Replaces the text between the byte offsets `anchor` and `cursor` (in
+/// either order) with `text`, moves the cursor after it and returns the
+/// new editor content.
+pub fn replace_selection(window: &AppWindow, anchor: i32, cursor: i32, text: &str) -> String {
+    let (content, end) = splice(&window.get_editor_content(), anchor, cursor, text);
+    window.set_editor_content(content.as_str().into());
+    window.invoke_set_editor_cursor(end as i32);
+    content
+}
+
+/// `content` with the selection replaced, and the offset after `text`.
+fn splice(content: &str, anchor: i32, cursor: i32, text: &str) -> (String, usize) {
+    let offset = |position: i32| {
+        let mut offset = (position.max(0) as usize).min(content.len());
+        while !content.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    };
+    let (start, end) = (offset(anchor.min(cursor)), offset(anchor.max(cursor)));
+    (format!("{}{}{}", &content[..start], text, &content[end..]), start + text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splices_the_selection() {
+        assert_eq!(splice("hello world", 5, 5, ","), ("hello, world".to_string(), 6));
+        // 选区可以从后往前选
+        assert_eq!(splice("hello world", 11, 6, "there"), ("hello there".to_string(), 11));
+        assert_eq!(splice("你好世界", 3, 6, "们"), ("你们世界".to_string(), 6));
+        // 越界或落在字符中间时收回到边界
+        assert_eq!(splice("你好", 4, 99, "!"), ("你!".to_string(), 4));
+        assert_eq!(splice("", -1, 3, "a"), ("a".to_string(), 1));
+    }
+}
@@ -0,0 +1,262 @@
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use crate::ui::TimestampRow;
+
+/// A time zone picked in the converter: the system's local zone or an
+/// IANA name from the bundled tz database.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    Local,
+    Named(Tz),
+}
+
+impl Zone {
+    /// Empty input and `local` mean the system zone.
+    pub fn parse(name: &str) -> Result<Zone, String> {
+        let name = name.trim();
+        if name.is_empty() || name.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+        name.parse::<Tz>().map(Zone::Named).or_else(|_| {
+            // 大小写不敏感，比如 asia/shanghai
+            chrono_tz::TZ_VARIANTS.iter()
+                .find(|tz| tz.name().eq_ignore_ascii_case(name))
+                .map(|tz| Zone::Named(*tz))
+                .ok_or_else(|| format!("未知时区: {}", name))
+        })
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Zone::Local => "本地".to_string(),
+            Zone::Named(tz) => tz.name().to_string(),
+        }
+    }
+
+    fn at(&self, instant: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Zone::Local => instant.with_timezone(&Local).fixed_offset(),
+            Zone::Named(tz) => {
+                let time = instant.with_timezone(tz);
+                time.with_timezone(&time.offset().fix())
+            },
+        }
+    }
+
+    /// Interprets a wall-clock time in this zone. Ambiguous times (DST
+    /// fold) resolve to the earlier one.
+    fn resolve(&self, time: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::Local => Local.from_local_datetime(&time).earliest().map(|t| t.with_timezone(&Utc)),
+            Zone::Named(tz) => tz.from_local_datetime(&time).earliest().map(|t| t.with_timezone(&Utc)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputKind {
+    UnixSeconds,
+    UnixMillis,
+    UnixMicros,
+    UnixNanos,
+    Rfc3339,
+    Rfc2822,
+    /// A date/time without offset, read in the input zone.
+    Local,
+}
+
+impl InputKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            InputKind::UnixSeconds => "Unix 秒",
+            InputKind::UnixMillis => "Unix 毫秒",
+            InputKind::UnixMicros => "Unix 微秒",
+            InputKind::UnixNanos => "Unix 纳秒",
+            InputKind::Rfc3339 => "RFC 3339 / ISO 8601",
+            InputKind::Rfc2822 => "RFC 2822",
+            InputKind::Local => "本地时间",
+        }
+    }
+}
+
+/// Formats tried for date/times without an offset.
+const LOCAL_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+    "%Y年%m月%d日 %H:%M:%S",
+    "%Y年%m月%d日 %H:%M",
+    "%d.%m.%Y %H:%M:%S",
+    "%d.%m.%Y %H:%M",
+    "%b %d, %Y %H:%M:%S",
+    "%b %d, %Y %I:%M %p",
+    "%B %d, %Y %H:%M:%S",
+    "%B %d, %Y %I:%M %p",
+];
+
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d", "%Y年%m月%d日", "%d.%m.%Y", "%b %d, %Y", "%B %d, %Y"];
+
+/// Unix timestamps are told apart by their number of digits: up to 11 are
+/// seconds (good until year 5138), then milli-, micro- and nanoseconds.
+/// Four bare digits are a year, not seconds after 1970.
+fn parse_unix(input: &str) -> Option<(InputKind, DateTime<Utc>)> {
+    if is_year(input) {
+        return None;
+    }
+    if let Some((whole, fraction)) = input.split_once('.') {
+        // 带小数的按秒处理
+        let seconds: i64 = whole.parse().ok()?;
+        let digits = fraction.chars().take(9).collect::<String>();
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let nanos: u32 = format!("{:0<9}", digits).parse().ok()?;
+        // "-0.5" 的整数部分是 0，符号要看原文
+        let nanos = if whole.starts_with('-') { -(nanos as i64) } else { nanos as i64 };
+        let instant = DateTime::from_timestamp(seconds, 0)?.checked_add_signed(chrono::Duration::nanoseconds(nanos))?;
+        return Some((InputKind::UnixSeconds, instant));
+    }
+    let value: i64 = input.parse().ok()?;
+    let digits = input.trim_start_matches('-').len();
+    match digits {
+        0..=11 => Some((InputKind::UnixSeconds, DateTime::from_timestamp(value, 0)?)),
+        12..=14 => Some((InputKind::UnixMillis, DateTime::from_timestamp_millis(value)?)),
+        15..=17 => Some((InputKind::UnixMicros, DateTime::from_timestamp_micros(value)?)),
+        _ => Some((InputKind::UnixNanos, DateTime::from_timestamp_nanos(value))),
+    }
+}
+
+fn is_year(input: &str) -> bool {
+    input.len() == 4 && input.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Detects what `input` is and turns it into an instant. Times without an
+/// offset are read in `zone`.
+pub fn parse(input: &str, zone: Zone) -> Result<(InputKind, DateTime<Utc>), String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("输入时间戳或日期".to_string());
+    }
+    if let Some(parsed) = parse_unix(input) {
+        return Ok(parsed);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok((InputKind::Rfc3339, time.with_timezone(&Utc)));
+    }
+    // ISO 8601 允许用空格代替 T
+    if let Ok(time) = DateTime::parse_from_str(input, "%Y-%m-%d %H:%M:%S%.f%:z") {
+        return Ok((InputKind::Rfc3339, time.with_timezone(&Utc)));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc2822(input) {
+        return Ok((InputKind::Rfc2822, time.with_timezone(&Utc)));
+    }
+    let local = LOCAL_FORMATS.iter().find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .or_else(|| DATE_FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(input, format).ok()).and_then(|date| date.and_hms_opt(0, 0, 0)))
+        .or_else(|| is_year(input).then(|| NaiveDate::from_ymd_opt(input.parse().ok()?, 1, 1)?.and_hms_opt(0, 0, 0)).flatten());
+    match local {
+        Some(time) => zone.resolve(time)
+            .map(|time| (InputKind::Local, time))
+            .ok_or_else(|| format!("{} 在 {} 不存在（夏令时跳过）", input, zone.name())),
+        None => Err(format!("无法识别: {}", input)),
+    }
+}
+
+fn relative(instant: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - instant).num_seconds();
+    let (amount, suffix) = if seconds >= 0 { (seconds, "前") } else { (-seconds, "后") };
+    let text = match amount {
+        0..=59 => format!("{} 秒", amount),
+        60..=3599 => format!("{} 分钟", amount / 60),
+        3600..=86399 => format!("{} 小时", amount / 3600),
+        86400..=2_591_999 => format!("{} 天", amount / 86400),
+        2_592_000..=31_535_999 => format!("{} 个月", amount / 2_592_000),
+        _ => format!("{} 年", amount / 31_536_000),
+    };
+    format!("{}{}", text, suffix)
+}
+
+/// The instant in every supported format, shown in `zone` where it matters.
+pub fn rows(instant: DateTime<Utc>, zone: Zone, now: DateTime<Utc>) -> Vec<TimestampRow> {
+    let zoned = zone.at(instant);
+    let nanos = instant.timestamp_nanos_opt().map(|n| n.to_string()).unwrap_or_else(|| "超出范围".to_string());
+    let weekday = ["一", "二", "三", "四", "五", "六", "日"][zoned.weekday().num_days_from_monday() as usize];
+    [
+        ("Unix 秒", instant.timestamp().to_string()),
+        ("Unix 毫秒", instant.timestamp_millis().to_string()),
+        ("Unix 纳秒", nanos),
+        ("RFC 3339", zoned.to_rfc3339()),
+        ("ISO 8601 UTC", instant.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()),
+        // RFC 2822 只能表示 0 到 9999 年，to_rfc2822() 超出时会 panic
+        ("RFC 2822", if (0..=9999).contains(&zoned.year()) { zoned.to_rfc2822() } else { "—".to_string() }),
+        (
+            "日期时间",
+            format!("{} 星期{} ({} {})", zoned.format("%Y-%m-%d %H:%M:%S"), weekday, zone.name(), zoned.format("%:z")),
+        ),
+        ("相对时间", relative(instant, now)),
+    ].into_iter().map(|(label, value)| TimestampRow { label: label.into(), value: value.into() }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(input: &str) -> (InputKind, String) {
+        let (kind, instant) = parse(input, Zone::Named(Tz::UTC)).unwrap();
+        (kind, instant.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+    }
+
+    #[test]
+    fn tells_unix_units_apart_by_digits() {
+        assert_eq!(utc("99999999999"), (InputKind::UnixSeconds, "5138-11-16T09:46:39Z".to_string()));
+        assert_eq!(utc("100000000000"), (InputKind::UnixMillis, "1973-03-03T09:46:40Z".to_string()));
+        assert_eq!(utc("99999999999999"), (InputKind::UnixMillis, "5138-11-16T09:46:39.999Z".to_string()));
+        assert_eq!(utc("100000000000000"), (InputKind::UnixMicros, "1973-03-03T09:46:40Z".to_string()));
+        assert_eq!(utc("99999999999999999"), (InputKind::UnixMicros, "5138-11-16T09:46:39.999999Z".to_string()));
+        assert_eq!(utc("100000000000000000"), (InputKind::UnixNanos, "1973-03-03T09:46:40Z".to_string()));
+        assert_eq!(utc("-86400"), (InputKind::UnixSeconds, "1969-12-31T00:00:00Z".to_string()));
+    }
+
+    #[test]
+    fn keeps_the_sign_of_fractional_seconds() {
+        assert_eq!(utc("-0.5").1, "1969-12-31T23:59:59.500Z");
+        assert_eq!(utc("-1.25").1, "1969-12-31T23:59:58.750Z");
+        assert_eq!(utc("1.5").1, "1970-01-01T00:00:01.500Z");
+        assert!(parse("1.", Zone::Local).is_err());
+    }
+
+    #[test]
+    fn reads_four_digits_as_a_year() {
+        assert_eq!(utc("2024"), (InputKind::Local, "2024-01-01T00:00:00Z".to_string()));
+        assert_eq!(utc("12345").0, InputKind::UnixSeconds);
+    }
+
+    #[test]
+    fn reads_dates_and_offsets() {
+        assert_eq!(utc("2024-05-01T09:00:00+02:00"), (InputKind::Rfc3339, "2024-05-01T07:00:00Z".to_string()));
+        assert_eq!(utc("Wed, 01 May 2024 09:00:00 +0200"), (InputKind::Rfc2822, "2024-05-01T07:00:00Z".to_string()));
+        let shanghai = Zone::parse("asia/shanghai").unwrap();
+        assert_eq!(parse("2024/05/01 08:00", shanghai).unwrap().1.to_rfc3339(), "2024-05-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn reports_times_skipped_by_dst() {
+        let new_york = Zone::parse("America/New_York").unwrap();
+        let error = parse("2024-03-10 02:30", new_york).unwrap_err();
+        assert!(error.contains("不存在（夏令时跳过）"), "{}", error);
+        // 回拨时取较早的一个
+        assert_eq!(parse("2024-11-03 01:30", new_york).unwrap().1.to_rfc3339(), "2024-11-03T05:30:00+00:00");
+    }
+
+    #[test]
+    fn leaves_rfc_2822_empty_past_year_9999() {
+        let zone = Zone::Named(Tz::UTC);
+        let now = Utc::now();
+        let last = DateTime::from_timestamp(253_402_300_799, 0).unwrap();
+        assert_eq!(rows(last, zone, now)[5].value.as_str(), "Fri, 31 Dec 9999 23:59:59 +0000");
+        let beyond = DateTime::from_timestamp(253_402_300_800, 0).unwrap();
+        assert_eq!(rows(beyond, zone, now)[5].value.as_str(), "—");
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::vault;
+use crate::vault::links::{self, LinkIndex};
+
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg"];
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Picks a free file name in `dir`, appending ` 1`, ` 2`... to the stem
+/// when `file_name` is already taken.
+pub fn unique_path(dir: &Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let path = Path::new(file_name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| dir.join(format!("{} {}{}", stem, n, ext)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+/// Copies `source` into the attachments folder and returns the new path.
+pub fn import_file(attachments_dir: &Path, source: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(attachments_dir)?;
+    let file_name = source.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a file"))?
+        .to_string_lossy();
+    let target = unique_path(attachments_dir, &file_name);
+    fs::copy(source, &target)?;
+    Ok(target)
+}
+
+/// Saves RGBA pixels (as provided by the clipboard) as a PNG in the attachments folder.
+pub fn save_png(attachments_dir: &Path, width: u32, height: u32, rgba: &[u8]) -> io::Result<PathBuf> {
+    fs::create_dir_all(attachments_dir)?;
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default();
+    let target = unique_path(attachments_dir, &format!("Pasted image {}.png", stamp));
+    image::save_buffer_with_format(&target, rgba, width, height, image::ExtendedColorType::Rgba8, image::ImageFormat::Png)
+        .map_err(io::Error::other)?;
+    Ok(target)
+}
+
+/// Builds the markdown that references `target` from a note in `note_dir`:
+/// an embed for images, a plain link for anything else.
+pub fn markdown_link(note_dir: &Path, target: &Path) -> String {
+    let link = links::encode_path(&links::relative_path(note_dir, target).to_string_lossy().replace('\\', "/"));
+    let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    if is_image(target) {
+        format!("![]({})", link)
+    } else {
+        format!("[{}]({})", name, link)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BrokenLink {
+    pub note: PathBuf,
+    pub target: String,
+    pub line: usize,
+}
+
+/// Which notes reference each attachment, plus the attachments nobody
+/// references and attachment links pointing at missing files.
+#[derive(Clone, Debug, Default)]
+pub struct AttachmentReport {
+    pub references: BTreeMap<PathBuf, Vec<PathBuf>>,
+    pub orphans: Vec<PathBuf>,
+    pub broken: Vec<BrokenLink>,
+}
+
+pub fn report(index: &LinkIndex) -> AttachmentReport {
+    let mut report = AttachmentReport::default();
+    for file in index.files().filter(|path| !vault::is_note(path)) {
+        report.references.insert(file.clone(), Vec::new());
+    }
+
+    let mut notes = index.notes().collect::<Vec<_>>();
+    notes.sort_by(|a, b| a.0.cmp(b.0));
+    for (note, note_links) in notes {
+        for link in note_links {
+            let target = index.resolve(note, link);
+            if vault::is_note(&target) {
+                continue;
+            }
+            match report.references.get_mut(&target) {
+                Some(referenced_by) if !referenced_by.contains(note) => referenced_by.push(note.clone()),
+                Some(_) => {}
+                None if !index.exists(&target) && target.extension().is_some() => {
+                    report.broken.push(BrokenLink {
+                        note: note.clone(),
+                        target: link.target.clone(),
+                        line: link.line,
+                    });
+                },
+                None => {}
+            }
+        }
+    }
+
+    report.orphans = report.references.iter()
+        .filter(|(_, referenced_by)| referenced_by.is_empty())
+        .map(|(path, _)| path.clone())
+        .collect();
+    report
+}
+
+/// Opens a file with the system's default application.
+pub fn open_externally(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+
+    command.arg(path).spawn().map(|_| ())
+}
@@ -0,0 +1,65 @@
+use chrono::{Local, NaiveDate};
+use std::fs;
+use std::path::Path;
+use crate::config::Config;
+use crate::vault;
+use crate::vault::templates::{self, Expanded};
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Vault-relative name of the daily note for `date`, e.g. `Daily/2024-05-01.md`.
+pub fn note_name(config: &Config, date: NaiveDate) -> String {
+    let file_name = format!("{}.md", date.format(&config.daily_format));
+    if config.daily_folder.is_empty() {
+        file_name
+    } else {
+        format!("{}/{}", config.daily_folder.trim_end_matches('/'), file_name)
+    }
+}
+
+/// The date of a daily note, or `None` when `path` is not one.
+pub fn date_of(config: &Config, root: &Path, path: &Path) -> Option<NaiveDate> {
+    let daily_dir = root.join(&config.daily_folder);
+    if path.parent()? != daily_dir || !vault::is_note(path) {
+        return None;
+    }
+    let stem = path.file_stem()?.to_string_lossy();
+    NaiveDate::parse_from_str(&stem, &config.daily_format).ok()
+}
+
+/// Dates of all existing daily notes, sorted.
+pub fn existing_dates(config: &Config, root: &Path) -> Vec<NaiveDate> {
+    let Ok(entries) = fs::read_dir(root.join(&config.daily_folder)) else {
+        return Vec::new();
+    };
+    let mut dates = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| date_of(config, root, &entry.path()))
+        .collect::<Vec<_>>();
+    dates.sort();
+    dates
+}
+
+/// The daily note `step` notes away from `from`: `-1` is the closest earlier
+/// existing daily note, `1` the closest later one, `0` is `from` itself.
+pub fn adjacent(config: &Config, root: &Path, from: NaiveDate, step: i32) -> Option<NaiveDate> {
+    let dates = existing_dates(config, root);
+    match step {
+        0 => Some(from),
+        s if s < 0 => dates.into_iter().rev().find(|date| *date < from),
+        _ => dates.into_iter().find(|date| *date > from),
+    }
+}
+
+/// Initial content of a new daily note, expanded from the configured
+/// template with the note's date.
+pub fn initial_content(config: &Config, root: &Path, date: NaiveDate) -> Expanded {
+    if config.daily_template.is_empty() {
+        return Expanded { content: String::new(), cursor: None };
+    }
+    let title = date.format(&config.daily_format).to_string();
+    let now = date.and_time(Local::now().time());
+    templates::expand_file(&root.join(&config.daily_template), &title, now)
+}
@@ -0,0 +1,210 @@
+use chrono::{DateTime, Local, NaiveDateTime};
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::db::notes::{self, NoteRecord};
+use crate::markdown::front_matter;
+use crate::vault;
+
+/// Words in the body (front matter excluded). Every CJK character counts as
+/// a word, as word processors do.
+pub fn word_count(content: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for c in front_matter::body(content).chars() {
+        if is_cjk(c) {
+            count += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() || c == '\'' || c == '_' {
+            if !in_word {
+                count += 1;
+            }
+            in_word = true;
+        } else {
+            in_word = false;
+        }
+    }
+    count
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF      // 平假名、片假名
+        | 0x3400..=0x4DBF    // 扩展 A
+        | 0x4E00..=0x9FFF    // 基本汉字
+        | 0xAC00..=0xD7AF    // 谚文
+        | 0xF900..=0xFAFF
+        | 0x20000..=0x2FFFF)
+}
+
+pub fn content_hash(content: &str) -> String {
+    blake3::hash(content.as_bytes()).to_hex().to_string()
+}
+
+fn local_time(time: SystemTime) -> NaiveDateTime {
+    DateTime::<Local>::from(time).naive_local()
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    DateTime::<Local>::from(time).timestamp()
+}
+
+fn title(note_path: &str) -> String {
+    Path::new(note_path).file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Brings the index entry of one note up to date after it was written.
+/// The creation time already in the index wins over the file system's,
+/// which copies and syncs often reset.
+pub async fn sync_note(pool: &SqlitePool, root: &Path, path: &Path, content: &str) -> Result<NoteRecord, sqlx::Error> {
+    let note_path = vault::relative_name(root, path);
+    let metadata = fs::metadata(path).ok();
+    let modified = metadata.as_ref().and_then(|metadata| metadata.modified().ok()).unwrap_or_else(SystemTime::now);
+    let hash = content_hash(content);
+    let existing = notes::get(pool, &note_path).await?;
+    let record = match existing {
+        Some(existing) if existing.hash == hash => NoteRecord { mtime: unix_seconds(modified), ..existing },
+        existing => NoteRecord {
+            title: title(&note_path),
+            hash,
+            mtime: unix_seconds(modified),
+            word_count: word_count(content) as i64,
+            created_at: existing.map(|existing| existing.created_at).unwrap_or_else(|| {
+                metadata.and_then(|metadata| metadata.created().ok()).map_or_else(|| local_time(modified), local_time)
+            }),
+            updated_at: local_time(modified),
+            path: note_path,
+        },
+    };
+    notes::save(pool, &record).await?;
+    Ok(record)
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ScanReport {
+    pub added: usize,
+    pub updated: usize,
+    pub renamed: usize,
+    pub removed: usize,
+}
+
+/// Reconciles the index with the notes among `files`. Files whose mtime
+/// didn't change are not read. A new path whose content matches a note
+/// that disappeared is treated as a rename and keeps that note's history.
+/// The content of every note it read is left in `contents`.
+pub async fn scan(
+    pool: &SqlitePool,
+    root: &Path,
+    files: &[PathBuf],
+    contents: &mut HashMap<PathBuf, String>,
+) -> Result<ScanReport, sqlx::Error> {
+    let mut report = ScanReport::default();
+    let mut indexed: HashMap<String, NoteRecord> = notes::all(pool).await?.into_iter()
+        .map(|note| (note.path.clone(), note))
+        .collect();
+    let mut new_files = Vec::new();
+    for path in files.iter().filter(|path| vault::is_note(path)) {
+        let note_path = vault::relative_name(root, path);
+        let mtime = fs::metadata(path).and_then(|metadata| metadata.modified()).map(unix_seconds).unwrap_or_default();
+        match indexed.remove(&note_path) {
+            Some(note) if note.mtime == mtime => {},
+            Some(note) => {
+                let Some(content) = read(contents, path) else { continue };
+                if note.hash != content_hash(content) {
+                    report.updated += 1;
+                }
+                sync_note(pool, root, path, content).await?;
+            },
+            None => new_files.push((path, note_path)),
+        }
+    }
+
+    // 剩下的是文件已经不存在的记录，按内容找回改名的笔记
+    let mut missing: Vec<NoteRecord> = indexed.into_values().collect();
+    for (path, note_path) in new_files {
+        let Some(content) = read(contents, path) else { continue };
+        let hash = content_hash(content);
+        if let Some(i) = missing.iter().position(|note| note.hash == hash) {
+            let old = missing.swap_remove(i);
+            notes::rename(pool, &old.path, &note_path, &title(&note_path)).await?;
+            report.renamed += 1;
+        } else {
+            report.added += 1;
+        }
+        sync_note(pool, root, path, content).await?;
+    }
+    for note in &missing {
+        notes::delete(pool, &note.path).await?;
+        report.removed += 1;
+    }
+    Ok(report)
+}
+
+fn read<'a>(contents: &'a mut HashMap<PathBuf, String>, path: &Path) -> Option<&'a String> {
+    if !contents.contains_key(path) {
+        contents.insert(path.to_path_buf(), fs::read_to_string(path).ok()?);
+    }
+    contents.get(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+    use crate::db::memory_pool;
+
+    #[test]
+    fn counts_words() {
+        assert_eq!(word_count(""), 0);
+        assert_eq!(word_count("Don't panic, it's snake_case & 42 words."), 6);
+        // 每个汉字算一个词，夹在中间的英文算一个
+        assert_eq!(word_count("# 你好 world，世界"), 5);
+        assert_eq!(word_count("---\ntags: [a, b]\ntitle: Ignored words\n---\nOnly these three"), 3);
+    }
+
+    fn write(path: &Path, content: &str, mtime: u64) {
+        fs::write(path, content).unwrap();
+        File::options().write(true).open(path).unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(mtime)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn scan_tracks_edits_renames_and_removals() {
+        let root = std::env::temp_dir().join(format!("nodian-index-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let pool = memory_pool().await;
+        let files = || vault::vault_files(&root).unwrap();
+
+        write(&root.join("a.md"), "alpha note", 1_700_000_000);
+        write(&root.join("b.md"), "beta note", 1_700_000_000);
+        fs::write(root.join("image.png"), b"not a note").unwrap();
+        assert_eq!(scan(&pool, &root, &files(), &mut HashMap::new()).await.unwrap(), ScanReport { added: 2, ..ScanReport::default() });
+        let created = notes::get(&pool, "b.md").await.unwrap().unwrap().created_at;
+
+        // mtime 没变时不读文件
+        let mut contents = HashMap::new();
+        assert_eq!(scan(&pool, &root, &files(), &mut contents).await.unwrap(), ScanReport::default());
+        assert!(contents.is_empty());
+
+        write(&root.join("a.md"), "alpha note, edited", 1_700_000_100);
+        fs::rename(root.join("b.md"), root.join("c.md")).unwrap();
+        let report = scan(&pool, &root, &files(), &mut contents).await.unwrap();
+        assert_eq!(report, ScanReport { updated: 1, renamed: 1, ..ScanReport::default() });
+        let mut read = contents.into_iter().map(|(path, content)| (vault::relative_name(&root, &path), content)).collect::<Vec<_>>();
+        read.sort();
+        assert_eq!(read, [("a.md".to_string(), "alpha note, edited".to_string()), ("c.md".to_string(), "beta note".to_string())]);
+        assert_eq!(notes::get(&pool, "a.md").await.unwrap().unwrap().word_count, 3);
+        let renamed = notes::get(&pool, "c.md").await.unwrap().unwrap();
+        assert_eq!((renamed.title.as_str(), renamed.created_at), ("c", created));
+
+        fs::remove_file(root.join("a.md")).unwrap();
+        assert_eq!(scan(&pool, &root, &files(), &mut HashMap::new()).await.unwrap(), ScanReport { removed: 1, ..ScanReport::default() });
+        let paths = notes::all(&pool).await.unwrap().into_iter().map(|note| note.path).collect::<Vec<_>>();
+        assert_eq!(paths, ["c.md"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use pulldown_cmark::{Event, Parser, Tag};
+use crate::markdown::render::parser_options;
+use crate::vault;
+
+/// Percent-encodes the characters that would break a markdown link target.
+pub fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' => encoded.push_str("%20"),
+            '(' => encoded.push_str("%28"),
+            ')' => encoded.push_str("%29"),
+            '<' => encoded.push_str("%3C"),
+            '>' => encoded.push_str("%3E"),
+            _ => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// Decodes `%XX` escapes in a link target. Invalid escapes are kept as-is.
+pub fn decode_path(link: &str) -> String {
+    let bytes = link.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| (b as char).to_digit(16);
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Whether a link target points outside the vault (`https:`, `mailto:`...).
+pub fn is_external(link: &str) -> bool {
+    link.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1 && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Resolves a link target found in a note located in `note_dir`. Paths
+/// starting with `/` are taken relative to the vault root.
+pub fn resolve(link: &str, note_dir: &Path, root: &Path) -> PathBuf {
+    let target = decode_path(link.split('#').next().unwrap_or(link));
+    match target.strip_prefix('/') {
+        Some(from_root) => root.join(from_root),
+        None => note_dir.join(target),
+    }
+}
+
+/// Computes the relative path leading from directory `from` to `to`.
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from = normalize(from);
+    let to = normalize(to);
+    let common = from.components().zip(to.components()).take_while(|(a, b)| a == b).count();
+    let mut result = PathBuf::new();
+    for _ in from.components().skip(common) {
+        result.push("..");
+    }
+    for component in to.components().skip(common) {
+        result.push(component);
+    }
+    result
+}
+
+/// Removes `.` and folds `..` components without touching the filesystem.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !result.pop() {
+                    result.push("..");
+                }
+            },
+            other => result.push(other),
+        }
+    }
+    result
+}
+
+/// A link found in a note, either `[text](target)` / `![alt](target)` or a
+/// wikilink `[[target|alias]]` / `![[target]]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteLink {
+    /// The decoded target without `#heading` fragment or `|alias`.
+    pub target: String,
+    pub embed: bool,
+    pub wiki: bool,
+    /// 1-based line number of the link in the note.
+    pub line: usize,
+}
+
+/// Extracts all links from a note, ignoring anything inside code.
+pub fn extract_links(markdown: &str) -> Vec<NoteLink> {
+    let line_of = |offset: usize| markdown[..offset].matches('\n').count() + 1;
+    let mut links = Vec::new();
+    let mut code_ranges = Vec::new();
+
+    for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => code_ranges.push(range),
+            Event::Start(Tag::Link(_, url, _)) | Event::Start(Tag::Image(_, url, _)) => {
+                if url.is_empty() || url.starts_with('#') || is_external(&url) {
+                    continue;
+                }
+                let embed = markdown[range.clone()].starts_with('!');
+                links.push(NoteLink {
+                    target: decode_path(url.split('#').next().unwrap_or(&url)),
+                    embed,
+                    wiki: false,
+                    line: line_of(range.start),
+                });
+            },
+            _ => {}
+        }
+    }
+
+    let mut search_from = 0;
+    while let Some(start) = markdown[search_from..].find("[[").map(|i| i + search_from) {
+        let Some(end) = markdown[start + 2..].find("]]").map(|i| i + start + 2) else {
+            break;
+        };
+        search_from = end + 2;
+        let inner = &markdown[start + 2..end];
+        if inner.contains('\n') || code_ranges.iter().any(|r| r.contains(&start)) {
+            search_from = start + 2;
+            continue;
+        }
+        let target = inner.split('|').next().unwrap_or("").split('#').next().unwrap_or("").trim();
+        if target.is_empty() {
+            continue;
+        }
+        links.push(NoteLink {
+            target: target.to_string(),
+            embed: start > 0 && markdown.as_bytes()[start - 1] == b'!',
+            wiki: true,
+            line: line_of(start),
+        });
+    }
+
+    links.sort_by_key(|link| link.line);
+    links
+}
+
+/// Outgoing links of every note in the vault, used to answer "what does this
+/// note reference" and "who references this file".
+#[derive(Clone)]
+pub struct LinkIndex {
+    root: PathBuf,
+    files: BTreeSet<PathBuf>,
+    links: HashMap<PathBuf, Vec<NoteLink>>,
+}
+
+impl LinkIndex {
+    pub fn new(root: &Path) -> LinkIndex {
+        LinkIndex {
+            root: root.to_path_buf(),
+            files: BTreeSet::new(),
+            links: HashMap::new(),
+        }
+    }
+
+    pub fn build(root: &Path) -> io::Result<LinkIndex> {
+        let mut index = LinkIndex::new(root);
+        for path in vault::vault_files(root)? {
+            if vault::is_note(&path) {
+                match fs::read_to_string(&path) {
+                    Ok(content) => index.update_note(&path, &content),
+                    Err(e) => eprintln!("Failed to index {:?}: {}", path, e),
+                }
+            }
+            index.files.insert(path);
+        }
+        Ok(index)
+    }
+
+    pub fn update_note(&mut self, note: &Path, content: &str) {
+        self.files.insert(note.to_path_buf());
+        self.links.insert(note.to_path_buf(), extract_links(content));
+    }
+
+    pub fn add_file(&mut self, path: &Path) {
+        self.files.insert(path.to_path_buf());
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.files.iter()
+    }
+
+    pub fn notes(&self) -> impl Iterator<Item = (&PathBuf, &Vec<NoteLink>)> {
+        self.links.iter()
+    }
+
+    /// Resolves a link to a vault path. Markdown links resolve relative to the
+    /// note; wikilinks match a vault-relative path or, failing that, a file name.
+    pub fn resolve(&self, note: &Path, link: &NoteLink) -> PathBuf {
+        if !link.wiki {
+            let note_dir = note.parent().unwrap_or(&self.root);
+            return normalize(&resolve(&link.target, note_dir, &self.root));
+        }
+        let wanted = link.target.trim_start_matches('/');
+        let with_md = format!("{}.md", wanted);
+        for candidate in [wanted, with_md.as_str()] {
+            let path = self.root.join(candidate);
+            if self.files.contains(&path) {
+                return path;
+            }
+        }
+        let by_name = self.files.iter().find(|path| {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            name == wanted || name == with_md
+        });
+        match by_name {
+            Some(path) => path.clone(),
+            None => self.root.join(if Path::new(wanted).extension().is_some() { wanted } else { &with_md }),
+        }
+    }
+
+    pub fn exists(&self, path: &Path) -> bool {
+        self.files.contains(path)
+    }
+}
@@ -0,0 +1,89 @@
+use chrono::NaiveDateTime;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::Config;
+use crate::vault;
+
+const CURSOR: &str = "{{cursor}}";
+
+/// A template with its variables substituted.
+pub struct Expanded {
+    pub content: String,
+    /// Byte offset of the first `{{cursor}}` marker, which is removed.
+    pub cursor: Option<usize>,
+}
+
+/// Notes in the templates folder, sorted by path.
+pub fn list_templates(config: &Config, root: &Path) -> Vec<PathBuf> {
+    let dir = config.templates_dir(root);
+    if !dir.is_dir() {
+        return Vec::new();
+    }
+    match vault::vault_files(&dir) {
+        Ok(files) => files.into_iter().filter(|path| vault::is_note(path)).collect(),
+        Err(e) => {
+            eprintln!("Failed to list templates: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn format_time(now: &NaiveDateTime, format: &str) -> Option<String> {
+    // 格式串无效或需要时区（%z、%Z）时 to_string() 会 panic，write! 则返回错误
+    let mut text = String::new();
+    write!(text, "{}", now.format(format)).ok()?;
+    Some(text)
+}
+
+fn substitute(name: &str, title: &str, now: &NaiveDateTime) -> Option<String> {
+    let (name, format) = match name.split_once(':') {
+        Some((name, format)) => (name.trim(), Some(format.trim())),
+        None => (name.trim(), None),
+    };
+    match (name, format) {
+        ("title", None) => Some(title.to_string()),
+        ("date", format) => format_time(now, format.unwrap_or("%Y-%m-%d")),
+        ("time", format) => format_time(now, format.unwrap_or("%H:%M")),
+        _ => None,
+    }
+}
+
+/// Replaces `{{title}}`, `{{date}}`, `{{date:FORMAT}}`, `{{time}}` and
+/// `{{time:FORMAT}}` (chrono format strings) and removes the `{{cursor}}`
+/// marker. Unknown variables are left as they are.
+pub fn expand(template: &str, title: &str, now: NaiveDateTime) -> Expanded {
+    let mut content = String::with_capacity(template.len());
+    let mut cursor = None;
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        content.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find("}}") else { break };
+        let variable = &rest[..end + 2];
+        if variable == CURSOR {
+            cursor.get_or_insert(content.len());
+        } else {
+            match substitute(&variable[2..end], title, &now) {
+                Some(value) => content.push_str(&value),
+                None => content.push_str(variable),
+            }
+        }
+        rest = &rest[end + 2..];
+    }
+    content.push_str(rest);
+
+    Expanded { content, cursor }
+}
+
+/// Reads and expands a template note.
+pub fn expand_file(path: &Path, title: &str, now: NaiveDateTime) -> Expanded {
+    match fs::read_to_string(path) {
+        Ok(template) => expand(&template, title, now),
+        Err(e) => {
+            eprintln!("Failed to read template {:?}: {}", path, e);
+            Expanded { content: String::new(), cursor: None }
+        }
+    }
+}